@@ -402,18 +402,26 @@ except Exception as e:
 PY
 }
 
+# Sync a panel directory (config plus the modules enabled by feature flags)
+sync_panel_dir() {
+    local source="$1"
+    local target="$2"
+    rm -rf "$target"
+    cp -r "$source" "$(dirname "$target")/"
+}
+
 update_configs_only() {
     local updated=false
 
     echo -e "\n[1/2] Updating sidebar config..."
     if [ -f "$PATCHES_DIR/cascade-panel/config.json" ] && [ -d "$TARGET_DIR_1/cascade-panel" ]; then
-        cp "$PATCHES_DIR/cascade-panel/config.json" "$TARGET_DIR_1/cascade-panel/"
-        echo "Updated cascade-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/cascade-panel" "$TARGET_DIR_1/cascade-panel"
+        echo "Synced cascade-panel config and feature modules"
         updated=true
     fi
     if [ -f "$PATCHES_DIR/sidebar-panel/config.json" ] && [ -d "$TARGET_DIR_2/sidebar-panel" ]; then
-        cp "$PATCHES_DIR/sidebar-panel/config.json" "$TARGET_DIR_2/sidebar-panel/"
-        echo "Updated sidebar-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/sidebar-panel" "$TARGET_DIR_2/sidebar-panel"
+        echo "Synced sidebar-panel config and feature modules"
         updated=true
     fi
     if [ "$updated" = "false" ]; then
@@ -422,8 +430,8 @@ update_configs_only() {
 
     echo -e "\n[2/2] Updating Manager config..."
     if [ -f "$PATCHES_DIR/manager-panel/config.json" ] && [ -d "$TARGET_DIR_2/manager-panel" ]; then
-        cp "$PATCHES_DIR/manager-panel/config.json" "$TARGET_DIR_2/manager-panel/"
-        echo "Updated manager-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/manager-panel" "$TARGET_DIR_2/manager-panel"
+        echo "Synced manager-panel config and feature modules"
     else
         echo "Warning: manager-panel config missing or target dir not found"
     fi
@@ -402,18 +402,26 @@ except Exception as e:
 PY
 }
 
+# 同步面板目录 (配置与按功能开关裁剪后的模块)
+sync_panel_dir() {
+    local source="$1"
+    local target="$2"
+    rm -rf "$target"
+    cp -r "$source" "$(dirname "$target")/"
+}
+
 update_configs_only() {
     local updated=false
 
     echo -e "\n[1/2] 正在更新侧边栏配置..."
     if [ -f "$PATCHES_DIR/cascade-panel/config.json" ] && [ -d "$TARGET_DIR_1/cascade-panel" ]; then
-        cp "$PATCHES_DIR/cascade-panel/config.json" "$TARGET_DIR_1/cascade-panel/"
-        echo "已更新 cascade-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/cascade-panel" "$TARGET_DIR_1/cascade-panel"
+        echo "已同步 cascade-panel 配置与功能模块"
         updated=true
     fi
     if [ -f "$PATCHES_DIR/sidebar-panel/config.json" ] && [ -d "$TARGET_DIR_2/sidebar-panel" ]; then
-        cp "$PATCHES_DIR/sidebar-panel/config.json" "$TARGET_DIR_2/sidebar-panel/"
-        echo "已更新 sidebar-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/sidebar-panel" "$TARGET_DIR_2/sidebar-panel"
+        echo "已同步 sidebar-panel 配置与功能模块"
         updated=true
     fi
     if [ "$updated" = "false" ]; then
@@ -422,8 +430,8 @@ update_configs_only() {
 
     echo -e "\n[2/2] 正在更新 Manager 配置..."
     if [ -f "$PATCHES_DIR/manager-panel/config.json" ] && [ -d "$TARGET_DIR_2/manager-panel" ]; then
-        cp "$PATCHES_DIR/manager-panel/config.json" "$TARGET_DIR_2/manager-panel/"
-        echo "已更新 manager-panel/config.json"
+        sync_panel_dir "$PATCHES_DIR/manager-panel" "$TARGET_DIR_2/manager-panel"
+        echo "已同步 manager-panel 配置与功能模块"
     else
         echo "警告: 未找到 manager-panel 配置或目标目录不存在"
     fi
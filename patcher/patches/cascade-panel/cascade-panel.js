@@ -95,5 +95,5 @@ const loadTableFix = () => {
 
     // 启动扫描模块，传入配置
     const { start } = await import('./scan.js');
-    start(config);
+    await start(config);
 })();
@@ -13,13 +13,6 @@
  */
 
 import { CONTENT_SELECTOR } from './constants.js';
-import {
-    ensureContentCopyButton,
-    addFeedbackCopyButtons,
-    bindMathSelectionCopyHandler,
-} from './copy.js';
-import { renderMath } from './math.js';
-import { renderMermaid } from './mermaid.js';
 
 /**
  * 功能配置（由入口传入）
@@ -33,6 +26,16 @@ let config = {
     fontSize: 16,
 };
 
+/**
+ * 功能模块（按配置动态加载）
+ *
+ * 安装器只部署已启用功能对应的文件，禁用功能的模块不在磁盘上，
+ * 因此不能静态导入。
+ */
+let copyModule = null;
+let mathModule = null;
+let mermaidModule = null;
+
 /**
  * 获取渲染根节点
  *
@@ -62,15 +65,15 @@ const scanClassic = (root) => {
     contentNodes.push(...root.querySelectorAll(CONTENT_SELECTOR));
 
     contentNodes.forEach((node) => {
-        if (config.copyButton) {
-            ensureContentCopyButton(node);
+        if (config.copyButton && copyModule) {
+            copyModule.ensureContentCopyButton(node);
         }
-        if (config.math) {
-            void renderMath(node);
+        if (config.math && mathModule) {
+            void mathModule.renderMath(node);
         }
     });
 
-    if (config.mermaid) {
+    if (config.mermaid && mermaidModule) {
         const mermaidNodes = [];
         if (root.matches && root.matches('[class*="language-mermaid"]')) {
             mermaidNodes.push(root);
@@ -78,7 +81,7 @@ const scanClassic = (root) => {
         mermaidNodes.push(...root.querySelectorAll('[class*="language-mermaid"]'));
 
         mermaidNodes.forEach((node) => {
-            void renderMermaid(node);
+            void mermaidModule.renderMermaid(node);
         });
     }
 };
@@ -102,8 +105,8 @@ const flushScanClassic = () => {
         if (node.isConnected) scanClassic(node);
     });
 
-    if (config.copyButton) {
-        addFeedbackCopyButtons();
+    if (config.copyButton && copyModule) {
+        copyModule.addFeedbackCopyButtons();
     }
 };
 
@@ -150,8 +153,8 @@ const scheduleScanClassic = (nodes) => {
 const initClassic = () => {
     const root = getRoot();
     scanClassic(root);
-    if (config.copyButton) {
-        addFeedbackCopyButtons();
+    if (config.copyButton && copyModule) {
+        copyModule.addFeedbackCopyButtons();
     }
 
     const observer = new MutationObserver((mutations) => {
@@ -175,19 +178,48 @@ const initClassic = () => {
     observer.observe(root, { childList: true, subtree: true, characterData: true });
 };
 
+/**
+ * 动态导入可选模块
+ *
+ * @param {string} path - 模块相对路径
+ * @returns {Promise<Object|null>} 模块对象，加载失败时返回 null
+ */
+const importOptional = async (path) => {
+    try {
+        return await import(path);
+    } catch (err) {
+        console.warn('[Cascade] 模块加载失败:', path, err);
+        return null;
+    }
+};
+
+/**
+ * 按配置加载功能模块
+ *
+ * @returns {Promise<void>}
+ */
+const loadFeatureModules = async () => {
+    [copyModule, mathModule, mermaidModule] = await Promise.all([
+        config.copyButton || config.math || config.mermaid ? importOptional('./copy.js') : null,
+        config.math ? importOptional('./math.js') : null,
+        config.mermaid ? importOptional('./mermaid.js') : null,
+    ]);
+};
+
 /**
  * 模块入口
  *
  * 接收配置并启动扫描模块。
  *
  * @param {Object} [userConfig={}] - 用户配置
- * @returns {void}
+ * @returns {Promise<void>}
  */
-export const start = (userConfig = {}) => {
+export const start = async (userConfig = {}) => {
     // 合并用户配置
     config = { ...config, ...userConfig };
-    if (config.math) {
-        bindMathSelectionCopyHandler();
+    await loadFeatureModules();
+    if (config.math && copyModule) {
+        copyModule.bindMathSelectionCopyHandler();
     }
 
     if (document.readyState === 'loading') {
@@ -14,13 +14,6 @@ import {
     SECTION_SELECTOR,
     STRUCTURED_CONTENT_SELECTOR,
 } from './constants.js';
-import {
-    ensureContentCopyButton,
-    addFeedbackCopyButtons,
-    bindMathSelectionCopyHandler,
-} from './copy.js';
-import { renderMath } from './math.js';
-import { scanMermaid } from './mermaid.js';
 
 /**
  * 功能配置
@@ -31,6 +24,16 @@ let config = {
     copyButton: true,
 };
 
+/**
+ * 功能模块（按配置动态加载）
+ *
+ * 安装器只部署已启用功能对应的文件，禁用功能的模块不在磁盘上，
+ * 因此不能静态导入。
+ */
+let copyModule = null;
+let mathModule = null;
+let mermaidModule = null;
+
 let activeRoot = null;
 let activeObserver = null;
 let feedbackTimer = null;
@@ -209,16 +212,16 @@ const scanClassic = (root) => {
     const contentNodes = collectContentNodes(root);
 
     contentNodes.forEach((node) => {
-        if (config.copyButton) {
-            ensureContentCopyButton(node);
+        if (config.copyButton && copyModule) {
+            copyModule.ensureContentCopyButton(node);
         }
-        if (config.math) {
-            void renderMath(node);
+        if (config.math && mathModule) {
+            void mathModule.renderMath(node);
         }
     });
 
-    if (config.mermaid) {
-        scanMermaid(root);
+    if (config.mermaid && mermaidModule) {
+        mermaidModule.scanMermaid(root);
     }
 };
 
@@ -324,9 +327,9 @@ const bindRoot = (root) => {
         characterData: true,
     });
 
-    if (config.copyButton) {
+    if (config.copyButton && copyModule) {
         const scanFeedback = () => {
-            addFeedbackCopyButtons(root);
+            copyModule.addFeedbackCopyButtons(root);
         };
 
         scanFeedback();
@@ -359,18 +362,47 @@ const init = () => {
     }, 1500);
 };
 
+/**
+ * 动态导入可选模块
+ *
+ * @param {string} path - 模块相对路径
+ * @returns {Promise<Object|null>} 模块对象，加载失败时返回 null
+ */
+const importOptional = async (path) => {
+    try {
+        return await import(path);
+    } catch (err) {
+        console.warn('[Sidebar Panel] 模块加载失败:', path, err);
+        return null;
+    }
+};
+
+/**
+ * 按配置加载功能模块
+ *
+ * @returns {Promise<void>}
+ */
+const loadFeatureModules = async () => {
+    [copyModule, mathModule, mermaidModule] = await Promise.all([
+        config.copyButton || config.math ? importOptional('./copy.js') : null,
+        config.math ? importOptional('./math.js') : null,
+        config.mermaid ? importOptional('./mermaid.js') : null,
+    ]);
+};
+
 /**
  * 模块入口
  *
  * 接收配置并启动扫描模块。
  *
  * @param {Object} [userConfig={}] - 用户配置
- * @returns {void}
+ * @returns {Promise<void>}
  */
-export const start = (userConfig = {}) => {
+export const start = async (userConfig = {}) => {
     config = { ...config, ...userConfig };
-    if (config.math) {
-        bindMathSelectionCopyHandler();
+    await loadFeatureModules();
+    if (config.math && copyModule) {
+        copyModule.bindMathSelectionCopyHandler();
     }
 
     if (document.readyState === 'loading') {
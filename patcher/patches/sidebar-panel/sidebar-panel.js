@@ -158,7 +158,7 @@ const applyMaxWidth = (userConfig) => {
 
     // 启动扫描
     const { start } = await import('./scan.js');
-    start(config);
+    await start(config);
 
     console.log('[Sidebar Panel] 补丁已启动', config);
 })();
@@ -0,0 +1,335 @@
+//! 审计日志模块
+//!
+//! 以 JSONL 格式追加记录补丁安装、配置更新、清理与删除对话等操作,
+//! 便于在 IDE 出现异常时追溯安装器做过的修改
+
+use super::i18n::CommandError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 命令行查看审计日志的参数
+pub const AUDIT_LOG_ARG: &str = "--audit-log";
+
+const AUDIT_FILE_STEM: &str = "audit";
+/// 单个日志文件达到该大小后轮转
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// 保留的历史日志文件数量 (audit.1.jsonl ~ audit.N.jsonl)
+const MAX_ROTATED_FILES: usize = 3;
+
+/// 审计日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// 操作完成时间 (毫秒时间戳)
+    pub timestamp: u64,
+    pub command: String,
+    #[serde(default)]
+    pub resources_root: Option<String>,
+    #[serde(default)]
+    pub ide_version: Option<String>,
+    /// 本次操作应用的配置
+    #[serde(default)]
+    pub config: Option<Value>,
+    /// 是否通过提权进程执行
+    #[serde(default)]
+    pub escalated: bool,
+    /// 写入或删除的文件
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub ok: bool,
+    #[serde(default)]
+    pub error_key: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AuditFilter {
+    pub command: Option<String>,
+    /// 起始时间 (毫秒时间戳, 含)
+    pub since: Option<u64>,
+    /// 截止时间 (毫秒时间戳, 含)
+    pub until: Option<u64>,
+    pub ok: Option<bool>,
+    pub resources_root: Option<String>,
+    /// 最多返回的条目数 (从最新开始)
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.command.as_deref().is_none_or(|c| entry.command == c)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+            && self.ok.is_none_or(|ok| entry.ok == ok)
+            && self
+                .resources_root
+                .as_deref()
+                .is_none_or(|root| entry.resources_root.as_deref() == Some(root))
+    }
+}
+
+/// 当前线程正在记录的操作信息
+#[derive(Default)]
+struct Tracker {
+    files: Vec<PathBuf>,
+    escalated: bool,
+}
+
+thread_local! {
+    static TRACKER: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+}
+
+/// 记录当前操作写入或删除的文件
+pub fn track_file(path: &Path) {
+    TRACKER.with(|tracker| {
+        if let Some(tracker) = tracker.borrow_mut().as_mut() {
+            if !tracker.files.iter().any(|file| file == path) {
+                tracker.files.push(path.to_path_buf());
+            }
+        }
+    });
+}
+
+/// 标记当前操作通过提权进程执行
+pub fn mark_escalated() {
+    TRACKER.with(|tracker| {
+        if let Some(tracker) = tracker.borrow_mut().as_mut() {
+            tracker.escalated = true;
+        }
+    });
+}
+
+/// 执行操作并收集其写入或删除的文件 (提权进程内使用, 不写日志)
+pub fn collect_files<T>(op: impl FnOnce() -> T) -> (T, Vec<PathBuf>) {
+    let previous = TRACKER.with(|tracker| tracker.replace(Some(Tracker::default())));
+    let result = op();
+    let tracker = TRACKER.with(|tracker| tracker.replace(previous));
+    (result, tracker.map(|t| t.files).unwrap_or_default())
+}
+
+/// 单次操作的审计记录, 调用 finish 时写入日志并返回操作结果
+pub struct AuditRecord {
+    entry: AuditEntry,
+}
+
+impl AuditRecord {
+    pub fn new(command: &str) -> Self {
+        TRACKER.with(|tracker| tracker.replace(Some(Tracker::default())));
+        Self {
+            entry: AuditEntry {
+                timestamp: 0,
+                command: command.to_string(),
+                resources_root: None,
+                ide_version: None,
+                config: None,
+                escalated: false,
+                files: Vec::new(),
+                ok: false,
+                error_key: None,
+                error: None,
+            },
+        }
+    }
+
+    pub fn with_config(mut self, config: Value) -> Self {
+        self.entry.config = Some(config);
+        self
+    }
+
+    pub fn set_target(&mut self, resources_root: &Path, ide_version: Option<String>) {
+        self.entry.resources_root = Some(resources_root.display().to_string());
+        self.entry.ide_version = ide_version;
+    }
+
+    /// 返回操作本身的结果; 操作已经执行, 日志写入失败只输出到 stderr, 不改变结果
+    pub fn finish<T>(self, result: Result<T, CommandError>) -> Result<T, CommandError> {
+        let (error_key, error) = match &result {
+            Ok(_) => (None, None),
+            Err(err) => (
+                err.localized_key().map(ToString::to_string),
+                Some(err.to_message(Some("en-US"))),
+            ),
+        };
+        self.write(result.is_ok(), error_key, error);
+        result
+    }
+
+    /// 以字符串错误结束 (对话管理等未使用本地化错误的命令)
+    pub fn finish_with_message<T>(self, result: Result<T, String>) -> Result<T, String> {
+        let error = result.as_ref().err().cloned();
+        self.write(result.is_ok(), None, error);
+        result
+    }
+
+    fn write(mut self, ok: bool, error_key: Option<String>, error: Option<String>) {
+        let tracker = TRACKER.with(|tracker| tracker.take()).unwrap_or_default();
+        self.entry.timestamp = now_ms();
+        self.entry.escalated = tracker.escalated;
+        self.entry.files = tracker
+            .files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+        self.entry.ok = ok;
+        self.entry.error_key = error_key;
+        self.entry.error = error;
+        if let Err(err) = append_entry(&self.entry) {
+            eprintln!(
+                "Failed to write audit log for {}: {}",
+                self.entry.command, err
+            );
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 获取审计日志目录
+fn get_audit_dir() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+}
+
+/// 日志文件路径, index 为 0 时为当前文件, 否则为第 index 个历史文件
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(format!("{}.jsonl", AUDIT_FILE_STEM))
+    } else {
+        dir.join(format!("{}.{}.jsonl", AUDIT_FILE_STEM, index))
+    }
+}
+
+fn append_entry(entry: &AuditEntry) -> std::io::Result<()> {
+    let dir = get_audit_dir();
+    fs::create_dir_all(&dir)?;
+
+    let current = log_path(&dir, 0);
+    if fs::metadata(&current).is_ok_and(|m| m.len() >= MAX_LOG_BYTES) {
+        rotate(&dir)?;
+    }
+
+    let line = serde_json::to_string(entry)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&current)?;
+    writeln!(file, "{}", line)
+}
+
+/// 轮转日志: audit.jsonl -> audit.1.jsonl -> ... , 超出数量的最旧文件被删除
+fn rotate(dir: &Path) -> std::io::Result<()> {
+    let oldest = log_path(dir, MAX_ROTATED_FILES);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (0..MAX_ROTATED_FILES).rev() {
+        let from = log_path(dir, index);
+        if from.exists() {
+            fs::rename(&from, log_path(dir, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// 读取并过滤审计日志, 按时间倒序返回
+fn read_entries(filter: &AuditFilter) -> std::io::Result<Vec<AuditEntry>> {
+    let dir = get_audit_dir();
+    let mut entries = Vec::new();
+
+    // 从最旧的历史文件读到当前文件, 损坏的行直接跳过
+    for index in (0..=MAX_ROTATED_FILES).rev() {
+        let path = log_path(&dir, index);
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+                if filter.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+
+    entries.reverse();
+    if let Some(limit) = filter.limit {
+        entries.truncate(limit);
+    }
+    Ok(entries)
+}
+
+/// 查询审计日志
+#[tauri::command]
+pub fn get_audit_log(
+    filter: Option<AuditFilter>,
+    locale: Option<String>,
+) -> Result<Vec<AuditEntry>, String> {
+    let locale_ref = locale.as_deref();
+    read_entries(&filter.unwrap_or_default()).map_err(|e| {
+        CommandError::key_with(
+            "auditBackend.errors.readLogFailed",
+            &[("detail", e.to_string())],
+        )
+        .to_message(locale_ref)
+    })
+}
+
+/// 判断当前进程是否以命令行查看审计日志模式启动
+pub fn is_audit_log_cli() -> bool {
+    std::env::args().skip(1).any(|arg| arg == AUDIT_LOG_ARG)
+}
+
+/// 命令行查看审计日志, 每行输出一条 JSON, 读取失败时输出一行 {"error": ...}, 返回进程退出码
+/// 支持 --command <名称>, --since <毫秒>, --until <毫秒>, --failed, --limit <数量>
+pub fn run_audit_log_cli() -> i32 {
+    let mut filter = AuditFilter::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--command" => filter.command = args.next(),
+            "--since" => filter.since = args.next().and_then(|v| v.parse().ok()),
+            "--until" => filter.until = args.next().and_then(|v| v.parse().ok()),
+            "--limit" => filter.limit = args.next().and_then(|v| v.parse().ok()),
+            "--failed" => filter.ok = Some(false),
+            _ => {}
+        }
+    }
+
+    match read_entries(&filter) {
+        Ok(entries) => {
+            for entry in entries {
+                if let Ok(line) = serde_json::to_string(&entry) {
+                    println!("{}", line);
+                }
+            }
+            0
+        }
+        Err(err) => {
+            let message = CommandError::key_with(
+                "auditBackend.errors.readLogFailed",
+                &[("detail", err.to_string())],
+            )
+            .to_message(None);
+            println!("{}", serde_json::json!({ "error": message }));
+            1
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 提供对话缓存清理功能
 
+use super::audit::AuditRecord;
 use super::i18n::{self, CommandError};
 
 type CleanResult<T> = Result<T, CommandError>;
@@ -44,7 +45,7 @@ fn apply_vars(template: String, vars: &[(&str, String)]) -> String {
 }
 
 /// 清理目标配置
-#[derive(serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct CleanTargets {
     pub antigravity: bool,
     pub gemini: bool,
@@ -76,7 +77,15 @@ pub fn run_anti_clean(
     locale: Option<String>,
 ) -> Result<String, String> {
     let locale_ref = locale.as_deref();
-    run_anti_clean_internal(force, targets, locale_ref).map_err(|err| err.to_message(locale_ref))
+    let audit = AuditRecord::new("run_anti_clean").with_config(serde_json::json!({
+        "force": force,
+        "targets": &targets,
+    }));
+
+    let result = run_anti_clean_internal(force, targets, locale_ref);
+    audit
+        .finish(result)
+        .map_err(|err| err.to_message(locale_ref))
 }
 
 fn run_anti_clean_internal(
@@ -716,7 +725,7 @@ fn resolve_antigravity_data_dir() -> Option<std::path::PathBuf> {
 }
 
 #[cfg(target_os = "windows")]
-fn resolve_sqlite3_executable() -> Option<std::path::PathBuf> {
+pub(crate) fn resolve_sqlite3_executable() -> Option<std::path::PathBuf> {
     use std::ffi::OsStr;
     use std::path::PathBuf;
 
@@ -801,7 +810,7 @@ fn resolve_sqlite3_executable() -> Option<std::path::PathBuf> {
 }
 
 #[cfg(target_os = "windows")]
-fn new_windows_command<S: AsRef<std::ffi::OsStr>>(program: S) -> std::process::Command {
+pub(crate) fn new_windows_command<S: AsRef<std::ffi::OsStr>>(program: S) -> std::process::Command {
     use std::os::windows::process::CommandExt;
 
     let mut command = std::process::Command::new(program);
@@ -874,6 +883,7 @@ fn backup_file(
             e
         )
     })?;
+    super::audit::track_file(&backup_path);
 
     output_lines.push(apply_vars(
         clean_text(locale, "cleanBackend.logs.backup"),
@@ -1053,6 +1063,7 @@ fn clean_dir_contents(
                 )
             })?;
         }
+        super::audit::track_file(&item_path);
     }
 
     Ok(())
@@ -1081,6 +1092,7 @@ fn clean_file(
             e
         )
     })?;
+    super::audit::track_file(path);
 
     output_lines.push(format!(
         "{}: {}",
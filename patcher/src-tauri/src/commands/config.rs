@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+use super::escalation::EscalationBackend;
 use super::i18n::CommandError;
 
 type ConfigResult<T> = Result<T, CommandError>;
@@ -24,6 +25,66 @@ pub struct AppConfig {
 
     /// 功能开关
     pub features: FeatureFlags,
+
+    /// Linux 提权后端尝试顺序 (pkexec/sudo/doas/run0), 为空时使用默认顺序
+    #[serde(
+        rename = "escalationBackends",
+        deserialize_with = "super::escalation::deserialize_configured"
+    )]
+    pub escalation_backends: Vec<EscalationBackend>,
+
+    /// 模型单价表, 用于估算对话用量的费用
+    #[serde(rename = "modelPrices")]
+    pub model_prices: Vec<ModelPrice>,
+
+    /// 回收站中已删除对话的保留天数, 未设置时为 30 天, 0 表示永久保留
+    #[serde(rename = "trashRetentionDays")]
+    pub trash_retention_days: Option<u32>,
+
+    /// 对话归档的存放目录, 未设置时为应用数据目录下的 archives
+    #[serde(rename = "archiveDir")]
+    pub archive_dir: Option<String>,
+
+    /// 对话密钥检测的自定义规则, 与内置规则一同使用
+    #[serde(rename = "secretRules")]
+    pub secret_rules: Vec<SecretRule>,
+
+    /// 在 CLI 中继续对话时使用的终端 (程序名或路径, macOS 上为 Terminal 或 iTerm),
+    /// 未设置时按平台默认选择
+    pub terminal: Option<String>,
+}
+
+/// 自定义密钥检测规则
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SecretRule {
+    /// 规则名, 同时用于脱敏占位符 [REDACTED:name]
+    pub name: String,
+    /// 正则表达式, 含捕获组时只有第一个捕获组视为密钥
+    pub pattern: String,
+}
+
+/// 模型单价 (每百万 token)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ModelPrice {
+    /// 模型名, 以 * 结尾时按前缀匹配
+    pub model: String,
+    pub input: f64,
+    pub output: f64,
+    #[serde(rename = "cacheRead")]
+    pub cache_read: f64,
+    #[serde(rename = "cacheWrite")]
+    pub cache_write: f64,
+}
+
+impl ModelPrice {
+    pub fn matches(&self, model: &str) -> bool {
+        match self.model.strip_suffix('*') {
+            Some(prefix) => model.starts_with(prefix),
+            None => self.model == model,
+        }
+    }
 }
 
 /// 功能开关
@@ -119,3 +180,49 @@ fn save_config_internal(config: AppConfig, locale: Option<&str>) -> ConfigResult
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::escalation::EscalationBackend;
+
+    #[test]
+    fn unknown_escalation_backend_keeps_other_settings() {
+        let config: AppConfig = serde_json::from_str(
+            r#"{"antigravityPath":"/opt/antigravity","escalationBackends":["doas","su","stub","sudo"],"terminal":"kitty"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.escalation_backends,
+            vec![EscalationBackend::Doas, EscalationBackend::Sudo]
+        );
+        assert_eq!(config.antigravity_path.as_deref(), Some("/opt/antigravity"));
+        assert_eq!(config.terminal.as_deref(), Some("kitty"));
+    }
+
+    #[test]
+    fn model_price_matches_exact_name_or_star_prefix() {
+        let exact = ModelPrice {
+            model: "gpt-4o".to_string(),
+            ..ModelPrice::default()
+        };
+        assert!(exact.matches("gpt-4o"));
+        assert!(!exact.matches("gpt-4o-mini"));
+        assert!(!exact.matches("GPT-4o"));
+
+        let prefix = ModelPrice {
+            model: "claude-3-*".to_string(),
+            ..ModelPrice::default()
+        };
+        assert!(prefix.matches("claude-3-"));
+        assert!(prefix.matches("claude-3-opus"));
+        assert!(!prefix.matches("claude-3"));
+        assert!(!prefix.matches("anthropic/claude-3-opus"));
+
+        let any = ModelPrice {
+            model: "*".to_string(),
+            ..ModelPrice::default()
+        };
+        assert!(any.matches("whatever"));
+    }
+}
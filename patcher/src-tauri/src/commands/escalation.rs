@@ -0,0 +1,256 @@
+//! 提权后端模块
+//!
+//! Linux 上按配置顺序依次尝试 pkexec、sudo、doas、run0 执行提权辅助进程,
+//! 后端未安装或无法非交互授权时回退到下一个; 各后端的取消与认证失败映射为独立的错误键
+//! macOS 仅使用桩后端, Windows 不需要提权
+
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use super::i18n::CommandError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 设置为非空值时使用桩后端: 不提权, 直接以当前用户执行辅助进程 (用于 CI 集成测试)
+pub const STUB_ENV: &str = "ANTI_POWER_ESCALATION_STUB";
+
+/// 未配置时的默认尝试顺序
+pub const DEFAULT_ORDER: &[EscalationBackend] = &[
+    EscalationBackend::Pkexec,
+    EscalationBackend::Sudo,
+    EscalationBackend::Doas,
+    EscalationBackend::Run0,
+];
+
+/// 提权后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EscalationBackend {
+    Pkexec,
+    /// 设置了 SUDO_ASKPASS 时使用 `sudo -A`, 否则使用 `sudo -n`
+    Sudo,
+    /// `doas -n`, 仅在免密或已缓存授权时可用
+    Doas,
+    Run0,
+    /// 桩后端, 只能通过环境变量启用, 不接受配置
+    #[serde(skip)]
+    Stub,
+}
+
+/// 提权失败类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationFailure {
+    /// 后端无法使用 (如缺少认证代理或需要交互输入密码), 回退到下一个后端
+    Unavailable,
+    /// 用户取消了授权
+    Canceled,
+    /// 认证失败或无权限
+    AuthFailed,
+}
+
+/// 后端执行结果
+pub enum EscalationAttempt {
+    /// 后端程序不存在
+    NotInstalled,
+    Completed(EscalationOutput),
+}
+
+pub struct EscalationOutput {
+    pub success: bool,
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl EscalationBackend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pkexec => "pkexec",
+            Self::Sudo => "sudo",
+            Self::Doas => "doas",
+            Self::Run0 => "run0",
+            Self::Stub => "stub",
+        }
+    }
+
+    /// 构造提权命令, 桩后端直接执行目标程序
+    fn command(self, executable: &Path, args: &[&str]) -> std::process::Command {
+        use std::process::Command;
+
+        let mut command = match self {
+            Self::Pkexec => Command::new("pkexec"),
+            Self::Sudo => {
+                let mut command = Command::new("sudo");
+                let has_askpass = std::env::var_os("SUDO_ASKPASS").is_some_and(|v| !v.is_empty());
+                command.arg(if has_askpass { "-A" } else { "-n" });
+                command
+            }
+            Self::Doas => {
+                let mut command = Command::new("doas");
+                command.arg("-n");
+                command
+            }
+            Self::Run0 => Command::new("run0"),
+            Self::Stub => return Self::direct_command(executable, args),
+        };
+        command.arg(executable).args(args);
+        command
+    }
+
+    fn direct_command(executable: &Path, args: &[&str]) -> std::process::Command {
+        let mut command = std::process::Command::new(executable);
+        command.args(args);
+        command
+    }
+
+    /// 根据退出码与 stderr 判断提权本身是否失败 (辅助进程未输出结果时调用)
+    pub fn classify_failure(self, code: Option<i32>, stderr: &str) -> Option<EscalationFailure> {
+        let lower = stderr.to_ascii_lowercase();
+        if lower.contains("cancel") {
+            return Some(EscalationFailure::Canceled);
+        }
+
+        match self {
+            // pkexec: 126 表示关闭了授权对话框, 127 表示未获授权
+            Self::Pkexec => match code {
+                Some(126) => Some(EscalationFailure::Canceled),
+                Some(127) if lower.contains("authentication agent") => {
+                    Some(EscalationFailure::Unavailable)
+                }
+                Some(127) => Some(EscalationFailure::AuthFailed),
+                _ => None,
+            },
+            Self::Sudo => {
+                if lower.contains("a password is required")
+                    || lower.contains("no askpass program")
+                    || lower.contains("a terminal is required")
+                {
+                    Some(EscalationFailure::Unavailable)
+                } else if lower.contains("no password was provided") {
+                    Some(EscalationFailure::Canceled)
+                } else if lower.contains("incorrect password")
+                    || lower.contains("not in the sudoers")
+                    || lower.contains("not allowed to execute")
+                {
+                    Some(EscalationFailure::AuthFailed)
+                } else {
+                    None
+                }
+            }
+            Self::Doas => {
+                if lower.contains("authorization required")
+                    || lower.contains("a password is required")
+                {
+                    Some(EscalationFailure::Unavailable)
+                } else if lower.contains("authentication failed")
+                    || lower.contains("operation not permitted")
+                {
+                    Some(EscalationFailure::AuthFailed)
+                } else {
+                    None
+                }
+            }
+            Self::Run0 => {
+                if lower.contains("interactive authentication required") {
+                    Some(EscalationFailure::Unavailable)
+                } else if lower.contains("access denied") {
+                    Some(EscalationFailure::AuthFailed)
+                } else {
+                    None
+                }
+            }
+            Self::Stub => None,
+        }
+    }
+
+    /// 取消或认证失败对应的错误
+    pub fn failure_error(self, failure: EscalationFailure) -> Option<CommandError> {
+        let key = match (self, failure) {
+            (_, EscalationFailure::Unavailable) | (Self::Stub, _) => return None,
+            (Self::Pkexec, EscalationFailure::Canceled) => "patchBackend.errors.pkexecCanceled",
+            (Self::Pkexec, EscalationFailure::AuthFailed) => "patchBackend.errors.pkexecAuthFailed",
+            (Self::Sudo, EscalationFailure::Canceled) => "patchBackend.errors.sudoCanceled",
+            (Self::Sudo, EscalationFailure::AuthFailed) => "patchBackend.errors.sudoAuthFailed",
+            (Self::Doas, EscalationFailure::Canceled) => "patchBackend.errors.doasCanceled",
+            (Self::Doas, EscalationFailure::AuthFailed) => "patchBackend.errors.doasAuthFailed",
+            (Self::Run0, EscalationFailure::Canceled) => "patchBackend.errors.run0Canceled",
+            (Self::Run0, EscalationFailure::AuthFailed) => "patchBackend.errors.run0AuthFailed",
+        };
+        Some(CommandError::key(key))
+    }
+
+    /// 以当前后端执行辅助进程, 通过 stdin 传入数据
+    pub fn run(
+        self,
+        executable: &Path,
+        args: &[&str],
+        input: &str,
+    ) -> std::io::Result<EscalationAttempt> {
+        use std::io::{ErrorKind, Write};
+        use std::process::Stdio;
+
+        let mut child = match self
+            .command(executable, args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound && self != Self::Stub => {
+                return Ok(EscalationAttempt::NotInstalled);
+            }
+            Err(err) => return Err(err),
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // 授权被取消时子进程可能已退出, 写入失败交由退出状态判断
+            let _ = stdin.write_all(input.as_bytes());
+        }
+
+        let output = child.wait_with_output()?;
+        Ok(EscalationAttempt::Completed(EscalationOutput {
+            success: output.status.success(),
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }))
+    }
+}
+
+/// 读取配置中的后端顺序, 跳过无法识别的后端 (如旧版本或手动编辑写入的名称),
+/// 避免整个配置解析失败后回退为默认值
+pub fn deserialize_configured<'de, D>(deserializer: D) -> Result<Vec<EscalationBackend>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .filter_map(|value| EscalationBackend::deserialize(value).ok())
+        .collect())
+}
+
+/// 是否通过环境变量启用了桩后端
+pub fn is_stub_enabled() -> bool {
+    std::env::var_os(STUB_ENV).is_some_and(|value| !value.is_empty())
+}
+
+/// 计算本次提权的尝试顺序: 启用桩后端时只用桩后端, 否则为配置顺序 (去重), 未配置时使用默认顺序
+pub fn resolve_order(configured: &[EscalationBackend], stub: bool) -> Vec<EscalationBackend> {
+    if stub {
+        return vec![EscalationBackend::Stub];
+    }
+
+    let mut order = Vec::new();
+    for backend in configured {
+        if *backend != EscalationBackend::Stub && !order.contains(backend) {
+            order.push(*backend);
+        }
+    }
+
+    if order.is_empty() {
+        DEFAULT_ORDER.to_vec()
+    } else {
+        order
+    }
+}
@@ -1,10 +1,11 @@
 use serde_json::Value;
+use std::borrow::Cow;
 use std::sync::OnceLock;
 
 #[derive(Debug)]
 pub enum CommandError {
     Localized {
-        key: &'static str,
+        key: Cow<'static, str>,
         vars: Vec<(String, String)>,
     },
     Raw(String),
@@ -13,14 +14,14 @@ pub enum CommandError {
 impl CommandError {
     pub fn key(key: &'static str) -> Self {
         Self::Localized {
-            key,
+            key: Cow::Borrowed(key),
             vars: Vec::new(),
         }
     }
 
     pub fn key_with(key: &'static str, vars: &[(&str, String)]) -> Self {
         Self::Localized {
-            key,
+            key: Cow::Borrowed(key),
             vars: vars
                 .iter()
                 .map(|(name, value)| ((*name).to_string(), value.clone()))
@@ -41,6 +42,14 @@ impl CommandError {
         }
     }
 
+    /// 本地化键, 原始错误返回 None
+    pub fn localized_key(&self) -> Option<&str> {
+        match self {
+            Self::Localized { key, .. } => Some(key),
+            Self::Raw(_) => None,
+        }
+    }
+
     pub fn details_for_match(&self) -> String {
         match self {
             Self::Localized { vars, .. } => vars
@@ -2,19 +2,34 @@
 //!
 //! 导出所有 Tauri 命令供前端调用
 
+mod audit;
 mod clean;
 mod config;
 mod detect;
+mod escalation;
 mod i18n;
 mod patch;
 mod paths;
+mod permissions;
+mod privileged;
 mod sessions;
 
+pub use audit::{get_audit_log, is_audit_log_cli, run_audit_log_cli};
 pub use clean::run_anti_clean;
 pub use config::{get_config, save_config};
 pub use detect::{detect_antigravity_path, detect_antigravity_version, normalize_antigravity_path};
 pub use patch::{
-    check_patch_status, install_patch, read_manager_patch_config, read_patch_config,
-    uninstall_patch, update_config,
+    check_patch_status, install_patch, probe_patch_permissions, read_manager_patch_config,
+    read_patch_config, uninstall_patch, update_config,
+};
+pub use privileged::{is_privileged_apply, run_privileged_apply};
+pub use sessions::{
+    archive_sessions, bulk_session_action, cancel_message_stream, cancel_session_scan,
+    delete_session, empty_trash, export_session, export_sessions, launch_session_resume,
+    list_archives, list_session_projects, list_session_providers, list_trash,
+    load_archived_session, load_message_block, load_session_messages, load_session_page,
+    load_session_tree, redact_session, restore_archived_session, restore_session,
+    scan_session_secrets, scan_sessions, search_sessions, session_resume_command,
+    session_usage_report, start_message_stream, start_session_scan, start_session_tail,
+    start_session_watch, stop_session_watch,
 };
-pub use sessions::{delete_session, load_session_messages, scan_sessions};
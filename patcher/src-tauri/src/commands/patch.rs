@@ -2,20 +2,26 @@
 //!
 //! 处理补丁文件的安装、卸载、配置更新等操作
 
-use super::i18n::{self, CommandError};
+use super::audit::{self, AuditRecord};
+use super::i18n::CommandError;
 use super::paths;
+use super::permissions::{self, PathAccess, PermissionReport, ProbeIssue};
+use super::privileged::{self, PrivilegedPlan};
 use crate::embedded::{self, EmbeddedError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
-use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
+#[cfg(target_os = "linux")]
+use super::config;
 #[cfg(any(target_os = "macos", target_os = "linux"))]
+use super::escalation::{self, EscalationAttempt, EscalationBackend, EscalationFailure};
+#[cfg(target_os = "macos")]
 use std::env;
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
-#[cfg(any(target_os = "macos", target_os = "linux"))]
+#[cfg(target_os = "macos")]
+use std::io::ErrorKind;
+#[cfg(target_os = "macos")]
 use std::process::Command;
 
 /// 需要从 product.json checksums 中移除的文件路径
@@ -27,6 +33,114 @@ const CHECKSUMS_TO_REMOVE: &[&str] = &[
     // 未来如果有其他需要清理的，添加到这里
 ];
 
+/// 补丁面板布局: 入口 HTML 与对应的模块目录
+struct PanelLayout {
+    entry: &'static str,
+    dir: &'static str,
+    remove_dir_error: &'static str,
+    create_dir_error: &'static str,
+}
+
+const LEGACY_SIDEBAR_LAYOUT: PanelLayout = PanelLayout {
+    entry: "cascade-panel.html",
+    dir: "cascade-panel",
+    remove_dir_error: "patchBackend.errors.removeOldCascadeDirFailed",
+    create_dir_error: "patchBackend.errors.createCascadeDirFailed",
+};
+
+const MODERN_SIDEBAR_LAYOUT: PanelLayout = PanelLayout {
+    entry: "workbench.html",
+    dir: "sidebar-panel",
+    remove_dir_error: "patchBackend.errors.removeOldCascadeDirFailed",
+    create_dir_error: "patchBackend.errors.createCascadeDirFailed",
+};
+
+const MANAGER_LAYOUT: PanelLayout = PanelLayout {
+    entry: "workbench-jetski-agent.html",
+    dir: "manager-panel",
+    remove_dir_error: "patchBackend.errors.removeOldManagerDirFailed",
+    create_dir_error: "patchBackend.errors.createManagerDirFailed",
+};
+
+/// 由功能开关控制的面板模块
+/// 面板目录下未在清单中列出的文件视为核心文件, 始终部署
+struct FeatureModule<C> {
+    /// 相对于面板目录的路径
+    file: &'static str,
+    /// 当前配置是否需要该模块
+    required: fn(&C) -> bool,
+}
+
+/// 旧版侧边栏模块清单
+/// cascade-panel 的 mermaid.js 依赖 copy.js 生成复制按钮, copy.js 又依赖 extract.js/icons.js
+const LEGACY_SIDEBAR_MODULES: &[FeatureModule<FeatureConfig>] = &[
+    FeatureModule {
+        file: "mermaid.js",
+        required: |f| f.mermaid,
+    },
+    FeatureModule {
+        file: "math.js",
+        required: |f| f.math,
+    },
+    FeatureModule {
+        file: "copy.js",
+        required: |f| f.copy_button || f.math || f.mermaid,
+    },
+    FeatureModule {
+        file: "extract.js",
+        required: |f| f.copy_button || f.math || f.mermaid,
+    },
+    FeatureModule {
+        file: "icons.js",
+        required: |f| f.copy_button || f.math || f.mermaid,
+    },
+    FeatureModule {
+        file: "table-fix.css",
+        required: |f| f.table_color,
+    },
+];
+
+/// 新版侧边栏模块清单 (copy.js 同时提供公式选区复制)
+const MODERN_SIDEBAR_MODULES: &[FeatureModule<FeatureConfig>] = &[
+    FeatureModule {
+        file: "mermaid.js",
+        required: |f| f.mermaid,
+    },
+    FeatureModule {
+        file: "math.js",
+        required: |f| f.math,
+    },
+    FeatureModule {
+        file: "copy.js",
+        required: |f| f.copy_button || f.math,
+    },
+];
+
+/// Manager 模块清单 (copy.js 同时提供公式选区复制)
+const MANAGER_MODULES: &[FeatureModule<ManagerFeatureConfig>] = &[
+    FeatureModule {
+        file: "mermaid.js",
+        required: |f| f.mermaid,
+    },
+    FeatureModule {
+        file: "math.js",
+        required: |f| f.math,
+    },
+    FeatureModule {
+        file: "copy.js",
+        required: |f| f.copy_button || f.math,
+    },
+];
+
+/// 判断面板文件在当前配置下是否需要部署 (核心文件始终需要)
+fn is_module_required<C>(modules: &[FeatureModule<C>], file: &str, config: &C) -> bool {
+    modules
+        .iter()
+        .find(|module| module.file == file)
+        .map(|module| (module.required)(config))
+        .unwrap_or(true)
+}
+
 /// 侧边栏补丁模式:
 /// - Legacy: 小于 1.18.3，沿用 cascade-panel.html 入口
 /// - Modern: 大于等于 1.18.3，使用 workbench.html 入口
@@ -74,7 +188,10 @@ fn parse_version_component(input: &str) -> Option<u32> {
     digits.parse::<u32>().ok()
 }
 
-enum PatchMode {
+/// 补丁操作类型 (同时作为提权操作计划的一部分序列化)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatchMode {
     Install,
     Uninstall,
     UpdateConfig,
@@ -82,16 +199,6 @@ enum PatchMode {
 
 type PatchResult<T> = Result<T, CommandError>;
 
-impl PatchMode {
-    fn as_str(&self) -> &'static str {
-        match self {
-            PatchMode::Install => "install",
-            PatchMode::Uninstall => "uninstall",
-            PatchMode::UpdateConfig => "update-config",
-        }
-    }
-}
-
 fn patch_text(_locale: Option<&str>, key: &'static str) -> CommandError {
     CommandError::key(key)
 }
@@ -114,11 +221,15 @@ fn map_embedded_error(locale: Option<&str>, err: EmbeddedError) -> CommandError
 }
 
 fn read_ide_version(resources_root: &Path) -> Option<IdeVersion> {
+    IdeVersion::parse(&read_ide_version_raw(resources_root)?)
+}
+
+/// 读取 product.json 中的原始 ideVersion 字符串
+fn read_ide_version_raw(resources_root: &Path) -> Option<String> {
     let product_json_path = resources_root.join("product.json");
     let content = fs::read_to_string(product_json_path).ok()?;
     let json: Value = serde_json::from_str(&content).ok()?;
-    let raw = json.get("ideVersion")?.as_str()?;
-    IdeVersion::parse(raw)
+    Some(json.get("ideVersion")?.as_str()?.to_string())
 }
 
 fn detect_sidebar_patch_variant(resources_root: &Path) -> SidebarPatchVariant {
@@ -131,7 +242,7 @@ fn detect_sidebar_patch_variant(resources_root: &Path) -> SidebarPatchVariant {
 }
 
 /// 侧边栏功能开关配置
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FeatureConfig {
     /// 是否启用侧边栏补丁 (禁用时还原所有侧边栏相关文件)
@@ -176,7 +287,7 @@ impl Default for FeatureConfig {
 }
 
 /// Manager 窗口功能开关配置
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ManagerFeatureConfig {
     /// 是否启用 Manager 补丁 (禁用时还原所有 Manager 相关文件)
@@ -232,33 +343,42 @@ pub fn install_patch(
     locale: Option<String>,
 ) -> Result<(), String> {
     let locale_ref = locale.as_deref();
-    let antigravity_root =
-        resolve_antigravity_root(&path, locale_ref).map_err(|err| err.to_message(locale_ref))?;
-    let resources_root = paths::resources_app_root(&antigravity_root);
+    let mut audit = AuditRecord::new("install_patch").with_config(serde_json::json!({
+        "features": &features,
+        "managerFeatures": &manager_features,
+    }));
 
-    let result = if should_use_privileged(&resources_root) {
-        run_privileged_patch(
-            PatchMode::Install,
-            &resources_root,
-            Some(&features),
-            Some(&manager_features),
-            locale_ref,
-        )
-    } else {
-        match install_patch_internal(&resources_root, &features, &manager_features, locale_ref) {
-            Ok(()) => Ok(()),
-            Err(err) if is_permission_error(&err) => run_privileged_patch(
+    let result = resolve_antigravity_root(&path, locale_ref).and_then(|antigravity_root| {
+        let resources_root = paths::resources_app_root(&antigravity_root);
+        audit.set_target(&resources_root, read_ide_version_raw(&resources_root));
+
+        if should_use_privileged(PatchMode::Install, &resources_root, locale_ref) {
+            run_privileged_patch(
                 PatchMode::Install,
                 &resources_root,
                 Some(&features),
                 Some(&manager_features),
                 locale_ref,
-            ),
-            Err(err) => Err(err),
+            )
+        } else {
+            match install_patch_internal(&resources_root, &features, &manager_features, locale_ref)
+            {
+                Ok(()) => Ok(()),
+                Err(err) if is_permission_error(&err) => run_privileged_patch(
+                    PatchMode::Install,
+                    &resources_root,
+                    Some(&features),
+                    Some(&manager_features),
+                    locale_ref,
+                ),
+                Err(err) => Err(err),
+            }
         }
-    };
+    });
 
-    result.map_err(|err| err.to_message(locale_ref))
+    audit
+        .finish(result)
+        .map_err(|err| err.to_message(locale_ref))
 }
 
 fn install_patch_internal(
@@ -283,12 +403,7 @@ fn install_patch_internal(
     }
 
     let sidebar_variant = detect_sidebar_patch_variant(resources_root);
-    let mut writable_checks = vec![workbench_dir.as_path(), resources_root];
-    if extensions_dir.exists() {
-        writable_checks.push(extensions_dir.as_path());
-    }
-
-    if let Some(dir) = first_unwritable_dir(&writable_checks, locale)? {
+    if let Some(dir) = first_blocked_path(PatchMode::Install, resources_root, locale)? {
         return handle_privileged_or_error(
             PatchMode::Install,
             resources_root,
@@ -348,33 +463,38 @@ fn install_patch_internal(
 #[tauri::command]
 pub fn uninstall_patch(path: String, locale: Option<String>) -> Result<(), String> {
     let locale_ref = locale.as_deref();
-    let antigravity_root =
-        resolve_antigravity_root(&path, locale_ref).map_err(|err| err.to_message(locale_ref))?;
-    let resources_root = paths::resources_app_root(&antigravity_root);
+    let mut audit = AuditRecord::new("uninstall_patch");
 
-    let result = if should_use_privileged(&resources_root) {
-        run_privileged_patch(
-            PatchMode::Uninstall,
-            &resources_root,
-            None,
-            None,
-            locale_ref,
-        )
-    } else {
-        match uninstall_patch_internal(&resources_root, locale_ref) {
-            Ok(()) => Ok(()),
-            Err(err) if is_permission_error(&err) => run_privileged_patch(
+    let result = resolve_antigravity_root(&path, locale_ref).and_then(|antigravity_root| {
+        let resources_root = paths::resources_app_root(&antigravity_root);
+        audit.set_target(&resources_root, read_ide_version_raw(&resources_root));
+
+        if should_use_privileged(PatchMode::Uninstall, &resources_root, locale_ref) {
+            run_privileged_patch(
                 PatchMode::Uninstall,
                 &resources_root,
                 None,
                 None,
                 locale_ref,
-            ),
-            Err(err) => Err(err),
+            )
+        } else {
+            match uninstall_patch_internal(&resources_root, locale_ref) {
+                Ok(()) => Ok(()),
+                Err(err) if is_permission_error(&err) => run_privileged_patch(
+                    PatchMode::Uninstall,
+                    &resources_root,
+                    None,
+                    None,
+                    locale_ref,
+                ),
+                Err(err) => Err(err),
+            }
         }
-    };
+    });
 
-    result.map_err(|err| err.to_message(locale_ref))
+    audit
+        .finish(result)
+        .map_err(|err| err.to_message(locale_ref))
 }
 
 fn uninstall_patch_internal(resources_root: &Path, locale: Option<&str>) -> PatchResult<()> {
@@ -391,12 +511,7 @@ fn uninstall_patch_internal(resources_root: &Path, locale: Option<&str>) -> Patc
         return Err(patch_text(locale, "patchBackend.errors.managerDirMissing"));
     }
 
-    let mut writable_checks = vec![workbench_dir.as_path()];
-    if extensions_dir.exists() {
-        writable_checks.push(extensions_dir.as_path());
-    }
-
-    if let Some(dir) = first_unwritable_dir(&writable_checks, locale)? {
+    if let Some(dir) = first_blocked_path(PatchMode::Uninstall, resources_root, locale)? {
         return handle_privileged_or_error(
             PatchMode::Uninstall,
             resources_root,
@@ -422,33 +537,42 @@ pub fn update_config(
     locale: Option<String>,
 ) -> Result<(), String> {
     let locale_ref = locale.as_deref();
-    let antigravity_root =
-        resolve_antigravity_root(&path, locale_ref).map_err(|err| err.to_message(locale_ref))?;
-    let resources_root = paths::resources_app_root(&antigravity_root);
+    let mut audit = AuditRecord::new("update_config").with_config(serde_json::json!({
+        "features": &features,
+        "managerFeatures": &manager_features,
+    }));
 
-    let result = if should_use_privileged(&resources_root) {
-        run_privileged_patch(
-            PatchMode::UpdateConfig,
-            &resources_root,
-            Some(&features),
-            Some(&manager_features),
-            locale_ref,
-        )
-    } else {
-        match update_config_internal(&resources_root, &features, &manager_features, locale_ref) {
-            Ok(()) => Ok(()),
-            Err(err) if is_permission_error(&err) => run_privileged_patch(
+    let result = resolve_antigravity_root(&path, locale_ref).and_then(|antigravity_root| {
+        let resources_root = paths::resources_app_root(&antigravity_root);
+        audit.set_target(&resources_root, read_ide_version_raw(&resources_root));
+
+        if should_use_privileged(PatchMode::UpdateConfig, &resources_root, locale_ref) {
+            run_privileged_patch(
                 PatchMode::UpdateConfig,
                 &resources_root,
                 Some(&features),
                 Some(&manager_features),
                 locale_ref,
-            ),
-            Err(err) => Err(err),
+            )
+        } else {
+            match update_config_internal(&resources_root, &features, &manager_features, locale_ref)
+            {
+                Ok(()) => Ok(()),
+                Err(err) if is_permission_error(&err) => run_privileged_patch(
+                    PatchMode::UpdateConfig,
+                    &resources_root,
+                    Some(&features),
+                    Some(&manager_features),
+                    locale_ref,
+                ),
+                Err(err) => Err(err),
+            }
         }
-    };
+    });
 
-    result.map_err(|err| err.to_message(locale_ref))
+    audit
+        .finish(result)
+        .map_err(|err| err.to_message(locale_ref))
 }
 
 fn update_config_internal(
@@ -457,6 +581,13 @@ fn update_config_internal(
     manager_features: &ManagerFeatureConfig,
     locale: Option<&str>,
 ) -> PatchResult<()> {
+    let workbench_dir = resources_root
+        .join("out")
+        .join("vs")
+        .join("code")
+        .join("electron-browser")
+        .join("workbench");
+
     // 侧边栏配置（旧版）
     let legacy_sidebar_config_path = resources_root
         .join("extensions")
@@ -465,14 +596,7 @@ fn update_config_internal(
         .join("config.json");
 
     // 侧边栏配置（新版）
-    let modern_sidebar_config_path = resources_root
-        .join("out")
-        .join("vs")
-        .join("code")
-        .join("electron-browser")
-        .join("workbench")
-        .join("sidebar-panel")
-        .join("config.json");
+    let modern_sidebar_config_path = workbench_dir.join("sidebar-panel").join("config.json");
 
     let has_legacy_sidebar = legacy_sidebar_config_path
         .parent()
@@ -484,14 +608,7 @@ fn update_config_internal(
         .unwrap_or(false);
 
     // Manager 配置
-    let manager_config_path = resources_root
-        .join("out")
-        .join("vs")
-        .join("code")
-        .join("electron-browser")
-        .join("workbench")
-        .join("manager-panel")
-        .join("config.json");
+    let manager_config_path = workbench_dir.join("manager-panel").join("config.json");
 
     let has_manager = manager_config_path
         .parent()
@@ -502,46 +619,48 @@ fn update_config_internal(
         return Err(patch_text(locale, "patchBackend.errors.patchNotInstalled"));
     }
 
-    let mut writable_checks = Vec::new();
-    if has_legacy_sidebar {
-        if let Some(parent) = legacy_sidebar_config_path.parent() {
-            writable_checks.push(parent.to_path_buf());
-        }
-    }
-    if has_modern_sidebar {
-        if let Some(parent) = modern_sidebar_config_path.parent() {
-            writable_checks.push(parent.to_path_buf());
-        }
-    }
-    if has_manager {
-        if let Some(parent) = manager_config_path.parent() {
-            writable_checks.push(parent.to_path_buf());
-        }
-    }
-
-    if !writable_checks.is_empty() {
-        let refs: Vec<&Path> = writable_checks.iter().map(|p| p.as_path()).collect();
-        if let Some(dir) = first_unwritable_dir(&refs, locale)? {
-            return handle_privileged_or_error(
-                PatchMode::UpdateConfig,
-                resources_root,
-                Some(features),
-                Some(manager_features),
-                &dir,
-                locale,
-            );
-        }
+    if let Some(dir) = first_blocked_path(PatchMode::UpdateConfig, resources_root, locale)? {
+        return handle_privileged_or_error(
+            PatchMode::UpdateConfig,
+            resources_root,
+            Some(features),
+            Some(manager_features),
+            &dir,
+            locale,
+        );
     }
 
+    // 写入配置, 并按新的功能开关增量部署模块
     if has_legacy_sidebar {
         write_config_file(&legacy_sidebar_config_path, features, locale)?;
+        sync_feature_modules(
+            &resources_root.join("extensions").join("antigravity"),
+            &LEGACY_SIDEBAR_LAYOUT,
+            LEGACY_SIDEBAR_MODULES,
+            features,
+            locale,
+        )?;
     }
     if has_modern_sidebar {
         write_config_file(&modern_sidebar_config_path, features, locale)?;
+        sync_feature_modules(
+            &workbench_dir,
+            &MODERN_SIDEBAR_LAYOUT,
+            MODERN_SIDEBAR_MODULES,
+            features,
+            locale,
+        )?;
     }
 
     if has_manager {
         write_manager_config_file(&manager_config_path, manager_features, locale)?;
+        sync_feature_modules(
+            &workbench_dir,
+            &MANAGER_LAYOUT,
+            MANAGER_MODULES,
+            manager_features,
+            locale,
+        )?;
     }
 
     Ok(())
@@ -707,6 +826,7 @@ fn backup_legacy_sidebar_files(extensions_dir: &Path, locale: Option<&str>) -> P
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&cascade_backup);
     }
     Ok(())
 }
@@ -723,6 +843,7 @@ fn backup_modern_sidebar_files(workbench_dir: &Path, locale: Option<&str>) -> Pa
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&workbench_backup);
     }
     Ok(())
 }
@@ -739,6 +860,7 @@ fn backup_manager_files(workbench_dir: &Path, locale: Option<&str>) -> PatchResu
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&jetski_backup);
     }
     Ok(())
 }
@@ -749,63 +871,16 @@ fn write_legacy_sidebar_patches(
     features: &FeatureConfig,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let cascade_panel_dir = extensions_dir.join("cascade-panel");
-
-    // 先删除旧目录, 确保文件结构干净
-    if cascade_panel_dir.exists() {
-        fs::remove_dir_all(&cascade_panel_dir).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.removeOldCascadeDirFailed",
-                &[("detail", e.to_string())],
-            )
-        })?;
-    }
-
-    // 创建目录
-    fs::create_dir_all(&cascade_panel_dir).map_err(|e| {
-        patch_with(
-            locale,
-            "patchBackend.errors.createCascadeDirFailed",
-            &[("detail", e.to_string())],
-        )
-    })?;
-
-    // 写入侧边栏相关补丁文件
-    let patch_files =
-        embedded::get_all_files_runtime().map_err(|e| map_embedded_error(locale, e))?;
-    for (relative_path, content) in patch_files {
-        // 只处理侧边栏相关文件
-        if relative_path != "cascade-panel.html" && !relative_path.starts_with("cascade-panel/") {
-            continue;
-        }
-
-        let full_path = extensions_dir.join(&relative_path);
-
-        // 确保父目录存在
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
-                    patch_with(
-                        locale,
-                        "patchBackend.errors.createDirFailed",
-                        &[("detail", e.to_string())],
-                    )
-                })?;
-            }
-        }
-
-        fs::write(&full_path, content).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.writeFileFailed",
-                &[("detail", format!("{:?}: {}", full_path, e))],
-            )
-        })?;
-    }
+    write_panel_patches(
+        extensions_dir,
+        &LEGACY_SIDEBAR_LAYOUT,
+        LEGACY_SIDEBAR_MODULES,
+        features,
+        locale,
+    )?;
 
     // 生成侧边栏配置文件
-    let cascade_config_path = cascade_panel_dir.join("config.json");
+    let cascade_config_path = extensions_dir.join("cascade-panel").join("config.json");
     write_config_file(&cascade_config_path, features, locale)?;
 
     Ok(())
@@ -817,61 +892,16 @@ fn write_modern_sidebar_patches(
     features: &FeatureConfig,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let sidebar_panel_dir = workbench_dir.join("sidebar-panel");
-
-    // 先删除旧目录, 确保文件结构干净
-    if sidebar_panel_dir.exists() {
-        fs::remove_dir_all(&sidebar_panel_dir).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.removeOldCascadeDirFailed",
-                &[("detail", e.to_string())],
-            )
-        })?;
-    }
-
-    // 创建目录
-    fs::create_dir_all(&sidebar_panel_dir).map_err(|e| {
-        patch_with(
-            locale,
-            "patchBackend.errors.createCascadeDirFailed",
-            &[("detail", e.to_string())],
-        )
-    })?;
-
-    // 写入新版侧边栏相关补丁文件
-    let patch_files =
-        embedded::get_all_files_runtime().map_err(|e| map_embedded_error(locale, e))?;
-    for (relative_path, content) in patch_files {
-        if relative_path != "workbench.html" && !relative_path.starts_with("sidebar-panel/") {
-            continue;
-        }
-
-        let full_path = workbench_dir.join(&relative_path);
-
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
-                    patch_with(
-                        locale,
-                        "patchBackend.errors.createDirFailed",
-                        &[("detail", e.to_string())],
-                    )
-                })?;
-            }
-        }
-
-        fs::write(&full_path, content).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.writeFileFailed",
-                &[("detail", format!("{:?}: {}", full_path, e))],
-            )
-        })?;
-    }
+    write_panel_patches(
+        workbench_dir,
+        &MODERN_SIDEBAR_LAYOUT,
+        MODERN_SIDEBAR_MODULES,
+        features,
+        locale,
+    )?;
 
     // 生成新版侧边栏配置文件
-    let sidebar_config_path = sidebar_panel_dir.join("config.json");
+    let sidebar_config_path = workbench_dir.join("sidebar-panel").join("config.json");
     write_config_file(&sidebar_config_path, features, locale)?;
 
     Ok(())
@@ -883,66 +913,136 @@ fn write_manager_patches(
     manager_features: &ManagerFeatureConfig,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let manager_panel_dir = workbench_dir.join("manager-panel");
+    write_panel_patches(
+        workbench_dir,
+        &MANAGER_LAYOUT,
+        MANAGER_MODULES,
+        manager_features,
+        locale,
+    )?;
+
+    // 生成 Manager 配置文件
+    let manager_config_path = workbench_dir.join("manager-panel").join("config.json");
+    write_manager_config_file(&manager_config_path, manager_features, locale)?;
+
+    Ok(())
+}
+
+/// 写入面板补丁文件 (入口 HTML + 面板目录)
+/// 面板目录会先整体删除, 再写入核心文件与已启用的功能模块
+fn write_panel_patches<C>(
+    target_dir: &Path,
+    layout: &PanelLayout,
+    modules: &[FeatureModule<C>],
+    config: &C,
+    locale: Option<&str>,
+) -> PatchResult<()> {
+    let panel_dir = target_dir.join(layout.dir);
 
     // 先删除旧目录, 确保文件结构干净
-    if manager_panel_dir.exists() {
-        fs::remove_dir_all(&manager_panel_dir).map_err(|e| {
+    if panel_dir.exists() {
+        fs::remove_dir_all(&panel_dir).map_err(|e| {
             patch_with(
                 locale,
-                "patchBackend.errors.removeOldManagerDirFailed",
+                layout.remove_dir_error,
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&panel_dir);
     }
 
     // 创建目录
-    fs::create_dir_all(&manager_panel_dir).map_err(|e| {
+    fs::create_dir_all(&panel_dir).map_err(|e| {
         patch_with(
             locale,
-            "patchBackend.errors.createManagerDirFailed",
+            layout.create_dir_error,
             &[("detail", e.to_string())],
         )
     })?;
 
-    // 写入 Manager 相关补丁文件
+    let panel_prefix = format!("{}/", layout.dir);
     let patch_files =
         embedded::get_all_files_runtime().map_err(|e| map_embedded_error(locale, e))?;
     for (relative_path, content) in patch_files {
-        // 只处理 Manager 相关文件
-        if relative_path != "workbench-jetski-agent.html"
-            && !relative_path.starts_with("manager-panel/")
-        {
-            continue;
+        // 只处理当前面板的入口与目录文件, 并跳过未启用的功能模块
+        match relative_path.strip_prefix(&panel_prefix) {
+            Some(panel_file) if is_module_required(modules, panel_file, config) => {}
+            Some(_) => continue,
+            None if relative_path == layout.entry => {}
+            None => continue,
         }
 
-        let full_path = workbench_dir.join(&relative_path);
+        write_patch_file(&target_dir.join(&relative_path), &content, locale)?;
+    }
+
+    Ok(())
+}
+
+/// 按功能开关增量同步已安装面板的功能模块
+/// 写入已启用的模块, 删除已禁用的模块, 核心文件保持不变
+fn sync_feature_modules<C>(
+    target_dir: &Path,
+    layout: &PanelLayout,
+    modules: &[FeatureModule<C>],
+    config: &C,
+    locale: Option<&str>,
+) -> PatchResult<()> {
+    let panel_dir = target_dir.join(layout.dir);
+    let patch_files =
+        embedded::get_all_files_runtime().map_err(|e| map_embedded_error(locale, e))?;
 
-        // 确保父目录存在
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
+    for module in modules {
+        let full_path = panel_dir.join(module.file);
+        if (module.required)(config) {
+            let relative_path = format!("{}/{}", layout.dir, module.file);
+            let (_, content) = patch_files
+                .iter()
+                .find(|(path, _)| *path == relative_path)
+                .ok_or_else(|| {
                     patch_with(
                         locale,
-                        "patchBackend.errors.createDirFailed",
-                        &[("detail", e.to_string())],
+                        "patchBackend.errors.missingPatchModule",
+                        &[("path", relative_path.clone())],
                     )
                 })?;
-            }
+            write_patch_file(&full_path, content, locale)?;
+        } else if full_path.exists() {
+            fs::remove_file(&full_path).map_err(|e| {
+                patch_with(
+                    locale,
+                    "patchBackend.errors.removeFileFailed",
+                    &[("detail", format!("{:?}: {}", full_path, e))],
+                )
+            })?;
+            audit::track_file(&full_path);
         }
+    }
 
-        fs::write(&full_path, content).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.writeFileFailed",
-                &[("detail", format!("{:?}: {}", full_path, e))],
-            )
-        })?;
+    Ok(())
+}
+
+/// 写入单个补丁文件, 必要时创建父目录
+fn write_patch_file(full_path: &Path, content: &str, locale: Option<&str>) -> PatchResult<()> {
+    if let Some(parent) = full_path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| {
+                patch_with(
+                    locale,
+                    "patchBackend.errors.createDirFailed",
+                    &[("detail", e.to_string())],
+                )
+            })?;
+        }
     }
 
-    // 生成 Manager 配置文件
-    let manager_config_path = manager_panel_dir.join("config.json");
-    write_manager_config_file(&manager_config_path, manager_features, locale)?;
+    fs::write(full_path, content).map_err(|e| {
+        patch_with(
+            locale,
+            "patchBackend.errors.writeFileFailed",
+            &[("detail", format!("{:?}: {}", full_path, e))],
+        )
+    })?;
+    audit::track_file(full_path);
 
     Ok(())
 }
@@ -981,6 +1081,7 @@ fn write_config_file(
             &[("detail", e.to_string())],
         )
     })?;
+    audit::track_file(config_path);
 
     Ok(())
 }
@@ -1020,6 +1121,7 @@ fn write_manager_config_file(
             &[("detail", e.to_string())],
         )
     })?;
+    audit::track_file(config_path);
 
     Ok(())
 }
@@ -1041,7 +1143,10 @@ fn restore_legacy_sidebar_files(extensions_dir: &Path, locale: Option<&str>) ->
                 &[("detail", e.to_string())],
             )
         })?;
-        let _ = fs::remove_file(&cascade_backup);
+        audit::track_file(&cascade_panel);
+        if fs::remove_file(&cascade_backup).is_ok() {
+            audit::track_file(&cascade_backup);
+        }
     }
 
     // 删除侧边栏补丁目录
@@ -1054,6 +1159,7 @@ fn restore_legacy_sidebar_files(extensions_dir: &Path, locale: Option<&str>) ->
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&cascade_dir);
     }
 
     Ok(())
@@ -1072,7 +1178,10 @@ fn restore_modern_sidebar_files(workbench_dir: &Path, locale: Option<&str>) -> P
                 &[("detail", e.to_string())],
             )
         })?;
-        let _ = fs::remove_file(&workbench_backup);
+        audit::track_file(&workbench);
+        if fs::remove_file(&workbench_backup).is_ok() {
+            audit::track_file(&workbench_backup);
+        }
     }
 
     // 删除新版侧边栏补丁目录
@@ -1085,6 +1194,7 @@ fn restore_modern_sidebar_files(workbench_dir: &Path, locale: Option<&str>) -> P
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&sidebar_dir);
     }
 
     Ok(())
@@ -1103,7 +1213,10 @@ fn restore_manager_files(workbench_dir: &Path, locale: Option<&str>) -> PatchRes
                 &[("detail", e.to_string())],
             )
         })?;
-        let _ = fs::remove_file(&jetski_backup);
+        audit::track_file(&jetski_agent);
+        if fs::remove_file(&jetski_backup).is_ok() {
+            audit::track_file(&jetski_backup);
+        }
     }
 
     // 删除 Manager 补丁目录
@@ -1116,6 +1229,7 @@ fn restore_manager_files(workbench_dir: &Path, locale: Option<&str>) -> PatchRes
                 &[("detail", e.to_string())],
             )
         })?;
+        audit::track_file(&manager_dir);
     }
 
     Ok(())
@@ -1187,6 +1301,7 @@ fn clean_checksums(product_json_path: &Path, locale: Option<&str>) -> PatchResul
                         &[("detail", e.to_string())],
                     )
                 })?;
+                audit::track_file(product_json_path);
             }
         }
     }
@@ -1207,73 +1322,163 @@ fn is_permission_error(error: &CommandError) -> bool {
         || lower.contains("read-only file system")
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-fn should_use_privileged(resources_root: &Path) -> bool {
-    let path = resources_root.to_string_lossy();
-    let prefixes = [
-        "/Applications/",
-        "/System/Applications/",
-        "/Library/",
-        "/System/",
-        "/usr/",
-        "/opt/",
-        "/lib/",
-        "/lib64/",
-        "/var/",
-        "/snap/",
+/// 操作计划将修改或删除的路径
+fn plan_targets(mode: PatchMode, resources_root: &Path) -> Vec<(PathBuf, PathAccess)> {
+    let extensions_dir = resources_root.join("extensions").join("antigravity");
+    let workbench_dir = resources_root
+        .join("out")
+        .join("vs")
+        .join("code")
+        .join("electron-browser")
+        .join("workbench");
+
+    let mut panels = vec![
+        (workbench_dir.clone(), &MODERN_SIDEBAR_LAYOUT),
+        (workbench_dir.clone(), &MANAGER_LAYOUT),
     ];
+    if extensions_dir.exists() {
+        panels.insert(0, (extensions_dir.clone(), &LEGACY_SIDEBAR_LAYOUT));
+    }
 
-    prefixes.iter().any(|prefix| path.starts_with(prefix))
-}
+    let mut targets = Vec::new();
+    match mode {
+        PatchMode::Install | PatchMode::Uninstall => {
+            if mode == PatchMode::Install {
+                targets.push((resources_root.join("product.json"), PathAccess::Modify));
+            }
+            if extensions_dir.exists() {
+                targets.push((extensions_dir.clone(), PathAccess::Modify));
+            }
+            targets.push((workbench_dir.clone(), PathAccess::Modify));
+
+            // 入口文件被改写, 备份文件被创建或删除, 模块目录被整体重建或删除
+            for (target_dir, layout) in &panels {
+                targets.push((target_dir.join(layout.entry), PathAccess::Modify));
+                targets.push((
+                    target_dir.join(format!("{}.bak", layout.entry)),
+                    PathAccess::Remove,
+                ));
+                collect_tree_targets(
+                    &target_dir.join(layout.dir),
+                    PathAccess::Remove,
+                    &mut targets,
+                );
+            }
+        }
+        PatchMode::UpdateConfig => {
+            // 仅改写已安装面板的配置与模块
+            for (target_dir, layout) in &panels {
+                collect_tree_targets(
+                    &target_dir.join(layout.dir),
+                    PathAccess::Modify,
+                    &mut targets,
+                );
+            }
+        }
+    }
 
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn should_use_privileged(_resources_root: &Path) -> bool {
-    false
+    targets
 }
 
-fn first_unwritable_dir(dirs: &[&Path], locale: Option<&str>) -> PatchResult<Option<PathBuf>> {
-    for dir in dirs {
-        match can_write_dir(dir, locale)? {
-            true => {}
-            false => return Ok(Some(dir.to_path_buf())),
+/// 收集目录树: 目录本身需要可修改, 其中的文件按指定方式访问
+fn collect_tree_targets(
+    dir: &Path,
+    file_access: PathAccess,
+    targets: &mut Vec<(PathBuf, PathAccess)>,
+) {
+    if !dir.is_dir() {
+        return;
+    }
+
+    targets.push((dir.to_path_buf(), PathAccess::Modify));
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_tree_targets(&path, file_access, targets);
+            } else {
+                targets.push((path, file_access));
+            }
         }
     }
-    Ok(None)
 }
 
-fn can_write_dir(dir: &Path, locale: Option<&str>) -> PatchResult<bool> {
-    let test_path = dir.join(".anti-power-write-test");
-    match fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(&test_path)
-    {
-        Ok(_) => {
-            let _ = fs::remove_file(&test_path);
-            Ok(true)
-        }
-        Err(err) => match err.kind() {
-            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => Ok(false),
-            _ => Err(patch_with(
-                locale,
-                "patchBackend.errors.cannotWriteDir",
-                &[("detail", format!("{}: {}", dir.display(), err))],
-            )),
-        },
+/// 对操作计划做权限预检
+fn probe_plan(
+    mode: PatchMode,
+    resources_root: &Path,
+    locale: Option<&str>,
+) -> PatchResult<PermissionReport> {
+    permissions::probe_paths(&plan_targets(mode, resources_root)).map_err(|e| {
+        patch_with(
+            locale,
+            "patchBackend.errors.probePermissionsFailed",
+            &[("detail", e.to_string())],
+        )
+    })
+}
+
+/// 提权也无法解决的问题 (只读挂载、不可变或仅追加属性), 需要用户自行处理
+fn unfixable_error(report: &PermissionReport, locale: Option<&str>) -> Option<CommandError> {
+    let (entry, issue) = report.first_unfixable()?;
+    let key = match issue {
+        ProbeIssue::ReadOnlyMount => "patchBackend.errors.readOnlyMount",
+        _ => "patchBackend.errors.immutableFile",
+    };
+    Some(patch_with(
+        locale,
+        key,
+        &[("path", entry.probed_path.display().to_string())],
+    ))
+}
+
+/// 操作计划中第一个不可写的路径, 存在提权也无法解决的问题时直接返回错误
+fn first_blocked_path(
+    mode: PatchMode,
+    resources_root: &Path,
+    locale: Option<&str>,
+) -> PatchResult<Option<PathBuf>> {
+    let report = probe_plan(mode, resources_root, locale)?;
+    if let Some(err) = unfixable_error(&report, locale) {
+        return Err(err);
     }
+    Ok(report
+        .first_blocked()
+        .map(|entry| entry.probed_path.clone()))
 }
 
-fn is_zh_locale(locale: Option<&str>) -> bool {
-    i18n::is_zh_locale(locale)
+/// 预检补丁操作涉及路径的写入权限, 返回逐路径报告
+#[tauri::command]
+pub fn probe_patch_permissions(
+    path: String,
+    mode: PatchMode,
+    locale: Option<String>,
+) -> Result<PermissionReport, String> {
+    let locale_ref = locale.as_deref();
+    let antigravity_root =
+        resolve_antigravity_root(&path, locale_ref).map_err(|err| err.to_message(locale_ref))?;
+    let resources_root = paths::resources_app_root(&antigravity_root);
+
+    probe_plan(mode, &resources_root, locale_ref).map_err(|err| err.to_message(locale_ref))
 }
 
-fn select_privileged_script(locale: Option<&str>) -> &'static str {
-    if is_zh_locale(locale) {
-        "anti-power.sh"
-    } else {
-        "anti-power.en.sh"
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn should_use_privileged(mode: PatchMode, resources_root: &Path, locale: Option<&str>) -> bool {
+    // 桩后端用于在普通环境中走通完整提权流程
+    if escalation::is_stub_enabled() {
+        return true;
     }
+
+    // 预检失败时先直接执行, 由实际操作给出具体错误;
+    // 提权也无法解决时同样直接执行, 由 first_blocked_path 报告具体原因
+    probe_plan(mode, resources_root, locale)
+        .map(|report| report.needs_privilege && report.first_unfixable().is_none())
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn should_use_privileged(_mode: PatchMode, _resources_root: &Path, _locale: Option<&str>) -> bool {
+    false
 }
 
 fn handle_privileged_or_error(
@@ -1284,14 +1489,23 @@ fn handle_privileged_or_error(
     dir: &Path,
     locale: Option<&str>,
 ) -> PatchResult<()> {
+    // 提权进程内仍不可写时直接报错, 避免重复提权
+    if privileged::is_helper_process() {
+        return Err(patch_with(
+            locale,
+            "patchBackend.errors.permissionDeniedDir",
+            &[("dir", dir.display().to_string())],
+        ));
+    }
+
     #[cfg(any(target_os = "macos", target_os = "linux"))]
     {
-        let _ = dir;
         run_privileged_patch(mode, resources_root, features, manager_features, locale)
     }
 
     #[cfg(not(any(target_os = "macos", target_os = "linux")))]
     {
+        let _ = (mode, resources_root, features, manager_features);
         Err(patch_with(
             locale,
             "patchBackend.errors.permissionDeniedDir",
@@ -1300,12 +1514,47 @@ fn handle_privileged_or_error(
     }
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
+/// 在提权进程中执行操作计划 (与非提权流程共用同一套实现)
+pub fn apply_privileged_plan(plan: &PrivilegedPlan) -> PatchResult<()> {
+    let locale = plan.locale.as_deref();
+    let resources_root = plan.resources_root.as_path();
+    if !resources_root.join("product.json").is_file() {
+        return Err(patch_text(locale, "patchBackend.errors.invalidInstallDir"));
+    }
+
+    match plan.mode {
+        PatchMode::Install => {
+            let features = plan
+                .features
+                .as_ref()
+                .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingSidebarConfig"))?;
+            let manager_features = plan
+                .manager_features
+                .as_ref()
+                .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingManagerConfig"))?;
+            install_patch_internal(resources_root, features, manager_features, locale)
+        }
+        PatchMode::Uninstall => uninstall_patch_internal(resources_root, locale),
+        PatchMode::UpdateConfig => {
+            let features = plan
+                .features
+                .as_ref()
+                .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingSidebarConfig"))?;
+            let manager_features = plan
+                .manager_features
+                .as_ref()
+                .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingManagerConfig"))?;
+            update_config_internal(resources_root, features, manager_features, locale)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
 struct TempDirGuard {
     path: PathBuf,
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
+#[cfg(target_os = "macos")]
 impl TempDirGuard {
     fn new(path: PathBuf) -> Self {
         Self { path }
@@ -1316,13 +1565,14 @@ impl TempDirGuard {
     }
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
+#[cfg(target_os = "macos")]
 impl Drop for TempDirGuard {
     fn drop(&mut self) {
         let _ = fs::remove_dir_all(&self.path);
     }
 }
 
+/// 以提权辅助模式重新执行安装器, 传入操作计划并读取结构化结果
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 fn run_privileged_patch(
     mode: PatchMode,
@@ -1331,83 +1581,58 @@ fn run_privileged_patch(
     manager_features: Option<&ManagerFeatureConfig>,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let temp_dir = TempDirGuard::new(prepare_temp_patch_dir(locale)?);
-    write_embedded_files_to_dir(temp_dir.path(), locale)?;
-
-    if matches!(mode, PatchMode::Install | PatchMode::UpdateConfig) {
-        let feature_config = features
-            .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingSidebarConfig"))?;
-        let manager_config = manager_features
-            .ok_or_else(|| patch_text(locale, "patchBackend.errors.missingManagerConfig"))?;
-
-        let cascade_config_path = temp_dir.path().join("cascade-panel").join("config.json");
-        write_config_file(&cascade_config_path, feature_config, locale)?;
-
-        let sidebar_config_path = temp_dir.path().join("sidebar-panel").join("config.json");
-        write_config_file(&sidebar_config_path, feature_config, locale)?;
-
-        let manager_config_path = temp_dir.path().join("manager-panel").join("config.json");
-        write_manager_config_file(&manager_config_path, manager_config, locale)?;
-    }
-
-    let script_name = select_privileged_script(locale);
-    let script_path = temp_dir.path().join(script_name);
-    if !script_path.exists() {
-        return Err(patch_with(
+    audit::mark_escalated();
+
+    let plan = PrivilegedPlan {
+        mode,
+        resources_root: resources_root.to_path_buf(),
+        features: features.cloned(),
+        manager_features: manager_features.cloned(),
+        locale: locale.map(|value| value.to_string()),
+        patches_dir: embedded::dev_patches_dir(),
+    };
+    let plan_json = serde_json::to_string(&plan).map_err(|e| {
+        patch_with(
             locale,
-            "patchBackend.errors.notFound",
-            &[("name", script_name.to_string())],
-        ));
-    }
-
-    ensure_script_executable(&script_path, locale)?;
-
-    let cascade_enabled = features.map(|config| config.enabled).unwrap_or(true);
-    let manager_enabled = manager_features
-        .map(|config| config.enabled)
-        .unwrap_or(true);
-    let args = build_script_args(mode, resources_root, cascade_enabled, manager_enabled);
-    let status_path = temp_dir.path().join("privileged-status.txt");
+            "patchBackend.errors.serializePlanFailed",
+            &[("detail", e.to_string())],
+        )
+    })?;
+    let executable = privileged::helper_executable().map_err(|e| {
+        patch_with(
+            locale,
+            "patchBackend.errors.resolveExecutableFailed",
+            &[("detail", e.to_string())],
+        )
+    })?;
 
-    match run_privileged_script(&script_path, &args, &status_path, locale) {
-        Ok(()) => Ok(()),
-        Err(err) => {
-            let message = annotate_privileged_error(err, resources_root, locale);
-            Err(patch_with(
-                locale,
-                "patchBackend.errors.privilegedScriptFailed",
-                &[("message", message)],
-            ))
-        }
-    }
+    run_privileged_helper(&executable, &plan_json, locale)
+        .map_err(|err| annotate_privileged_error(err, resources_root, locale))
 }
 
 fn annotate_privileged_error(
     error: CommandError,
     resources_root: &Path,
     locale: Option<&str>,
-) -> String {
-    let details = error.details_for_match();
-    let message = error.to_message(locale);
-
+) -> CommandError {
     #[cfg(target_os = "macos")]
     {
+        let details = error.details_for_match();
         let lower = details.to_ascii_lowercase();
         if lower.contains("operation not permitted") || details.contains("权限") {
             return patch_with(
                 locale,
                 "patchBackend.errors.macosPermissionHint",
                 &[
-                    ("message", message.clone()),
+                    ("message", error.to_message(locale)),
                     ("path", resources_root.display().to_string()),
                 ],
-            )
-            .to_message(locale);
+            );
         }
     }
 
     let _ = (resources_root, locale);
-    message
+    error
 }
 
 #[cfg(not(any(target_os = "macos", target_os = "linux")))]
@@ -1424,8 +1649,8 @@ fn run_privileged_patch(
     ))
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-fn prepare_temp_patch_dir(locale: Option<&str>) -> PatchResult<PathBuf> {
+#[cfg(target_os = "macos")]
+fn prepare_temp_dir(locale: Option<&str>) -> PatchResult<PathBuf> {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     for attempt in 0..8 {
@@ -1459,98 +1684,66 @@ fn prepare_temp_patch_dir(locale: Option<&str>) -> PatchResult<PathBuf> {
     ))
 }
 
+/// 解读提权进程输出: 优先使用 JSON 结果, 缺失时回退到 stderr/stdout
 #[cfg(any(target_os = "macos", target_os = "linux"))]
-fn write_embedded_files_to_dir(root: &Path, locale: Option<&str>) -> PatchResult<()> {
-    let patch_files =
-        embedded::get_all_files_runtime().map_err(|e| map_embedded_error(locale, e))?;
-    for (relative_path, content) in patch_files {
-        let full_path = root.join(&relative_path);
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
-                    patch_with(
-                        locale,
-                        "patchBackend.errors.createDirFailed",
-                        &[("detail", e.to_string())],
-                    )
-                })?;
-            }
-        }
-
-        fs::write(&full_path, content).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.writeFileFailed",
-                &[("detail", format!("{:?}: {}", full_path, e))],
-            )
-        })?;
+fn interpret_helper_output(
+    success: bool,
+    stdout: &str,
+    stderr: &str,
+    locale: Option<&str>,
+) -> PatchResult<()> {
+    if let Some(report) = privileged::parse_report(stdout) {
+        return report.into_result();
     }
 
-    Ok(())
-}
-
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-fn ensure_script_executable(script_path: &Path, locale: Option<&str>) -> PatchResult<()> {
-    #[cfg(unix)]
-    {
-        let mut perms = fs::metadata(script_path)
-            .map_err(|e| {
-                patch_with(
-                    locale,
-                    "patchBackend.errors.readScriptPermissionsFailed",
-                    &[("detail", e.to_string())],
-                )
-            })?
-            .permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(script_path, perms).map_err(|e| {
-            patch_with(
-                locale,
-                "patchBackend.errors.setScriptPermissionsFailed",
-                &[("detail", e.to_string())],
-            )
-        })?;
+    let stderr = stderr.trim();
+    let stdout = stdout.trim();
+    if !success && !stderr.is_empty() {
+        Err(CommandError::from(stderr.to_string()))
+    } else if !success && !stdout.is_empty() {
+        Err(CommandError::from(stdout.to_string()))
+    } else if !success {
+        Err(patch_text(
+            locale,
+            "patchBackend.errors.privilegedCanceledOrFailed",
+        ))
+    } else {
+        Err(patch_text(locale, "patchBackend.errors.privilegedNoResult"))
     }
-
-    Ok(())
-}
-
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-fn build_script_args(
-    mode: PatchMode,
-    resources_root: &Path,
-    cascade_enabled: bool,
-    manager_enabled: bool,
-) -> Vec<String> {
-    vec![
-        "--mode".to_string(),
-        mode.as_str().to_string(),
-        "--app-path".to_string(),
-        resources_root.to_string_lossy().to_string(),
-        "--cascade-enabled".to_string(),
-        cascade_enabled.to_string(),
-        "--manager-enabled".to_string(),
-        manager_enabled.to_string(),
-    ]
 }
 
 #[cfg(target_os = "macos")]
-fn run_privileged_script(
-    script_path: &Path,
-    args: &[String],
-    status_path: &Path,
+fn run_privileged_helper(
+    executable: &Path,
+    plan_json: &str,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let mut command_parts = Vec::new();
-    command_parts.push(shell_quote("/bin/bash"));
-    command_parts.push(shell_quote(script_path.to_string_lossy().as_ref()));
-    for arg in args {
-        command_parts.push(shell_quote(arg));
+    if escalation::is_stub_enabled() {
+        return run_escalation_backends(&[EscalationBackend::Stub], executable, plan_json, locale);
     }
 
-    let command_line = command_parts.join(" ");
-    let status_path_quoted = shell_quote(status_path.to_string_lossy().as_ref());
-    let terminal_command = format!("sudo {} ; echo $? > {}", command_line, status_path_quoted);
+    // Terminal 无法直接接管 stdin, 操作计划与结果通过临时文件重定向
+    let temp_dir = TempDirGuard::new(prepare_temp_dir(locale)?);
+    let plan_path = temp_dir.path().join("plan.json");
+    let result_path = temp_dir.path().join("result.json");
+    let status_path = temp_dir.path().join("privileged-status.txt");
+
+    fs::write(&plan_path, plan_json).map_err(|e| {
+        patch_with(
+            locale,
+            "patchBackend.errors.writePlanFailed",
+            &[("detail", e.to_string())],
+        )
+    })?;
+
+    let terminal_command = format!(
+        "sudo {} {} < {} > {} ; echo $? > {}",
+        shell_quote(executable.to_string_lossy().as_ref()),
+        privileged::PRIVILEGED_APPLY_ARG,
+        shell_quote(plan_path.to_string_lossy().as_ref()),
+        shell_quote(result_path.to_string_lossy().as_ref()),
+        shell_quote(status_path.to_string_lossy().as_ref())
+    );
     let apple_script = format!(
         "tell application \"Terminal\"\nactivate\ndo script \"{}\"\nend tell",
         escape_applescript_string(&terminal_command)
@@ -1568,47 +1761,85 @@ fn run_privileged_script(
             )
         })?;
 
-    wait_for_status(status_path, std::time::Duration::from_secs(900), locale)
+    let code = wait_for_status(&status_path, std::time::Duration::from_secs(900), locale)?;
+    let stdout = fs::read_to_string(&result_path).unwrap_or_default();
+    if code != 0 && privileged::parse_report(&stdout).is_none() {
+        return Err(patch_with(
+            locale,
+            "patchBackend.errors.terminalCommandFailedCode",
+            &[("code", code.to_string())],
+        ));
+    }
+
+    interpret_helper_output(code == 0, &stdout, "", locale)
 }
 
 #[cfg(target_os = "linux")]
-fn run_privileged_script(
-    script_path: &Path,
-    args: &[String],
-    _status_path: &Path,
+fn run_privileged_helper(
+    executable: &Path,
+    plan_json: &str,
     locale: Option<&str>,
 ) -> PatchResult<()> {
-    let output = Command::new("pkexec")
-        .arg("/bin/bash")
-        .arg(script_path)
-        .args(args)
-        .output();
-
-    match output {
-        Ok(output) if output.status.success() => Ok(()),
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
-            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !stderr.is_empty() {
-                Err(CommandError::from(stderr))
-            } else if !stdout.is_empty() {
-                Err(CommandError::from(stdout))
-            } else {
-                Err(patch_text(
+    let order = escalation::resolve_order(
+        &config::get_config().escalation_backends,
+        escalation::is_stub_enabled(),
+    );
+    run_escalation_backends(&order, executable, plan_json, locale)
+}
+
+/// 按顺序尝试提权后端, 未安装或无法授权时回退到下一个
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn run_escalation_backends(
+    order: &[EscalationBackend],
+    executable: &Path,
+    plan_json: &str,
+    locale: Option<&str>,
+) -> PatchResult<()> {
+    let mut tried = Vec::new();
+
+    for backend in order {
+        let attempt = backend
+            .run(executable, &[privileged::PRIVILEGED_APPLY_ARG], plan_json)
+            .map_err(|e| {
+                patch_with(
                     locale,
-                    "patchBackend.errors.privilegedCanceledOrFailed",
-                ))
-            }
+                    "patchBackend.errors.executeEscalationFailed",
+                    &[
+                        ("backend", backend.name().to_string()),
+                        ("detail", e.to_string()),
+                    ],
+                )
+            })?;
+        tried.push(backend.name());
+
+        let output = match attempt {
+            EscalationAttempt::NotInstalled => continue,
+            EscalationAttempt::Completed(output) => output,
+        };
+
+        // 辅助进程已输出结果时以结果为准
+        if output.success || privileged::parse_report(&output.stdout).is_some() {
+            return interpret_helper_output(output.success, &output.stdout, &output.stderr, locale);
         }
-        Err(err) if err.kind() == ErrorKind::NotFound => {
-            Err(patch_text(locale, "patchBackend.errors.pkexecNotFound"))
+
+        match backend.classify_failure(output.code, &output.stderr) {
+            Some(EscalationFailure::Unavailable) => continue,
+            Some(failure) => {
+                if let Some(error) = backend.failure_error(failure) {
+                    return Err(error);
+                }
+            }
+            None => {}
         }
-        Err(err) => Err(patch_with(
-            locale,
-            "patchBackend.errors.executePkexecFailed",
-            &[("detail", err.to_string())],
-        )),
+
+        return interpret_helper_output(output.success, &output.stdout, &output.stderr, locale);
     }
+
+    Err(patch_with(
+        locale,
+        "patchBackend.errors.escalationUnavailable",
+        &[("backends", tried.join(", "))],
+    ))
 }
 
 #[cfg(target_os = "macos")]
@@ -1634,12 +1865,13 @@ fn escape_applescript_string(value: &str) -> String {
     value.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
+/// 等待 Terminal 中的提权命令结束, 返回其退出码
 #[cfg(target_os = "macos")]
 fn wait_for_status(
     status_path: &Path,
     timeout: std::time::Duration,
     locale: Option<&str>,
-) -> PatchResult<()> {
+) -> PatchResult<i32> {
     let start = std::time::Instant::now();
     while start.elapsed() < timeout {
         if status_path.exists() {
@@ -1651,15 +1883,7 @@ fn wait_for_status(
                 )
             })?;
             let _ = fs::remove_file(status_path);
-            let code = content.trim().parse::<i32>().unwrap_or(1);
-            if code == 0 {
-                return Ok(());
-            }
-            return Err(patch_with(
-                locale,
-                "patchBackend.errors.terminalCommandFailedCode",
-                &[("code", code.to_string())],
-            ));
+            return Ok(content.trim().parse::<i32>().unwrap_or(1));
         }
         std::thread::sleep(std::time::Duration::from_millis(500));
     }
@@ -1669,3 +1893,113 @@ fn wait_for_status(
         "patchBackend.errors.terminalNotFinished",
     ))
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// 桩后端启动的辅助进程经包装脚本进入 privileged_helper_entry, 未设置时该测试直接通过
+    const HELPER_TEST_ENV: &str = "ANTI_POWER_TEST_HELPER";
+
+    #[test]
+    fn privileged_helper_entry() {
+        if env::var_os(HELPER_TEST_ENV).is_none() {
+            return;
+        }
+        std::process::exit(privileged::run_privileged_apply());
+    }
+
+    /// 生成以提权辅助模式运行当前测试程序的脚本 (测试程序不接受 --privileged-apply 参数)
+    fn helper_script(dir: &Path) -> PathBuf {
+        let test_exe = env::current_exe().unwrap();
+        let (_, module) = module_path!().split_once("::").unwrap();
+        let script = dir.join("helper.sh");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nexec env {}=1 '{}' --exact {}::privileged_helper_entry --nocapture --quiet --test-threads=1\n",
+                HELPER_TEST_ENV,
+                test_exe.display(),
+                module
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("anti-power-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn uninstall_plan(resources_root: &Path) -> String {
+        serde_json::to_string(&PrivilegedPlan {
+            mode: PatchMode::Uninstall,
+            resources_root: resources_root.to_path_buf(),
+            features: None,
+            manager_features: None,
+            locale: None,
+            patches_dir: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn stub_backend_runs_privileged_plan() {
+        assert_eq!(
+            escalation::resolve_order(&[EscalationBackend::Pkexec], false),
+            vec![EscalationBackend::Pkexec]
+        );
+        let order = escalation::resolve_order(&[EscalationBackend::Pkexec], true);
+        assert_eq!(order, vec![EscalationBackend::Stub]);
+
+        let dir = temp_dir("stub-apply");
+        let script = helper_script(&dir);
+        let resources_root = dir.join("resources").join("app");
+        let workbench_dir = resources_root
+            .join("out")
+            .join("vs")
+            .join("code")
+            .join("electron-browser")
+            .join("workbench");
+        fs::create_dir_all(workbench_dir.join("manager-panel")).unwrap();
+        fs::write(resources_root.join("product.json"), "{}").unwrap();
+        fs::write(workbench_dir.join("workbench-jetski-agent.html"), "patched").unwrap();
+        fs::write(
+            workbench_dir.join("workbench-jetski-agent.html.bak"),
+            "original",
+        )
+        .unwrap();
+
+        let (result, touched) = audit::collect_files(|| {
+            run_escalation_backends(&order, &script, &uninstall_plan(&resources_root), None)
+        });
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(
+            fs::read_to_string(workbench_dir.join("workbench-jetski-agent.html")).unwrap(),
+            "original"
+        );
+        assert!(!workbench_dir
+            .join("workbench-jetski-agent.html.bak")
+            .exists());
+        assert!(!workbench_dir.join("manager-panel").exists());
+        // 辅助进程写入的文件回传给父进程记录审计日志
+        assert!(touched.contains(&workbench_dir.join("workbench-jetski-agent.html")));
+
+        // 辅助进程的错误以本地化键回传
+        let missing = dir.join("missing");
+        let error =
+            run_escalation_backends(&order, &script, &uninstall_plan(&missing), None).unwrap_err();
+        assert!(matches!(
+            error,
+            CommandError::Localized { ref key, .. } if key == "patchBackend.errors.invalidInstallDir"
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
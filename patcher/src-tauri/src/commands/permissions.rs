@@ -0,0 +1,357 @@
+//! 写入权限预检模块
+//!
+//! 对操作计划涉及的每个文件和目录检查所有者、权限位、ACL、只读挂载与不可变属性,
+//! 生成逐路径报告, 用于判断是否需要提权
+
+use serde::Serialize;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// 操作计划对路径的访问方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathAccess {
+    /// 就地修改文件, 或在目录中创建/删除条目
+    Modify,
+    /// 删除路径本身 (所在目录的写权限由目录自身的探测项覆盖)
+    Remove,
+}
+
+/// 阻止写入的原因
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeIssue {
+    /// 所有者/组/其他用户的权限位不允许写入
+    ModeDenied,
+    /// 权限位允许但 ACL 拒绝写入
+    AclDenied,
+    /// 所在文件系统以只读方式挂载
+    ReadOnlyMount,
+    /// 设置了不可变属性
+    Immutable,
+    /// 设置了仅追加属性
+    AppendOnly,
+    /// 上级目录不可访问, 无法读取路径信息
+    Inaccessible,
+}
+
+impl ProbeIssue {
+    /// 提权后能否写入; 只读挂载与文件属性对 root 同样生效
+    pub fn fixed_by_privilege(self) -> bool {
+        matches!(
+            self,
+            ProbeIssue::ModeDenied | ProbeIssue::AclDenied | ProbeIssue::Inaccessible
+        )
+    }
+}
+
+/// 单个路径的探测结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathProbe {
+    /// 操作计划涉及的路径
+    pub path: PathBuf,
+    pub access: PathAccess,
+    pub exists: bool,
+    /// 实际探测的路径, 路径不存在时为最近的已存在上级目录
+    pub probed_path: PathBuf,
+    pub owner_uid: Option<u32>,
+    pub mode: Option<u32>,
+    pub owned_by_current_user: bool,
+    /// ACL 授予了权限位之外的写权限
+    pub acl_grants: bool,
+    pub issues: Vec<ProbeIssue>,
+}
+
+impl PathProbe {
+    pub fn writable(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// 操作计划的权限预检报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionReport {
+    /// 存在提权后可以解决的问题
+    pub needs_privilege: bool,
+    pub entries: Vec<PathProbe>,
+}
+
+impl PermissionReport {
+    /// 第一个不可写的路径
+    pub fn first_blocked(&self) -> Option<&PathProbe> {
+        self.entries.iter().find(|entry| !entry.writable())
+    }
+
+    /// 第一个提权也无法解决的问题及其路径
+    pub fn first_unfixable(&self) -> Option<(&PathProbe, ProbeIssue)> {
+        self.entries.iter().find_map(|entry| {
+            entry
+                .issues
+                .iter()
+                .find(|issue| !issue.fixed_by_privilege())
+                .map(|issue| (entry, *issue))
+        })
+    }
+}
+
+/// 探测一组路径的写入权限
+pub fn probe_paths(targets: &[(PathBuf, PathAccess)]) -> io::Result<PermissionReport> {
+    let mut entries = Vec::with_capacity(targets.len());
+    for (path, access) in targets {
+        if let Some(entry) = probe_path(path, *access)? {
+            entries.push(entry);
+        }
+    }
+
+    Ok(PermissionReport {
+        needs_privilege: entries
+            .iter()
+            .flat_map(|entry| &entry.issues)
+            .any(|issue| issue.fixed_by_privilege()),
+        entries,
+    })
+}
+
+/// 探测单个路径, 待删除但不存在的路径无需探测
+fn probe_path(path: &Path, access: PathAccess) -> io::Result<Option<PathProbe>> {
+    let exists = match path.symlink_metadata() {
+        Ok(_) => true,
+        Err(err) if err.kind() == ErrorKind::NotFound => false,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return Ok(Some(PathProbe {
+                path: path.to_path_buf(),
+                access,
+                exists: false,
+                probed_path: path.to_path_buf(),
+                owner_uid: None,
+                mode: None,
+                owned_by_current_user: false,
+                acl_grants: false,
+                issues: vec![ProbeIssue::Inaccessible],
+            }));
+        }
+        Err(err) => return Err(err),
+    };
+
+    if !exists && access == PathAccess::Remove {
+        return Ok(None);
+    }
+
+    // 不存在的路径需要在最近的已存在上级目录中创建
+    let (probed_path, access) = if exists {
+        (path.to_path_buf(), access)
+    } else {
+        match path.ancestors().skip(1).find(|dir| dir.exists()) {
+            Some(dir) => (dir.to_path_buf(), PathAccess::Modify),
+            None => return Ok(None),
+        }
+    };
+
+    let mut entry = PathProbe {
+        path: path.to_path_buf(),
+        access,
+        exists,
+        probed_path,
+        owner_uid: None,
+        mode: None,
+        owned_by_current_user: false,
+        acl_grants: false,
+        issues: Vec::new(),
+    };
+    platform::probe(&mut entry)?;
+    Ok(Some(entry))
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::{PathAccess, PathProbe, ProbeIssue};
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    pub fn probe(entry: &mut PathProbe) -> io::Result<()> {
+        let metadata = entry.probed_path.metadata()?;
+        let c_path = CString::new(entry.probed_path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let euid = unsafe { libc::geteuid() };
+
+        entry.owner_uid = Some(metadata.uid());
+        entry.mode = Some(metadata.mode() & 0o7777);
+        entry.owned_by_current_user = metadata.uid() == euid;
+
+        if is_read_only_mount(&c_path) {
+            entry.issues.push(ProbeIssue::ReadOnlyMount);
+        }
+        let (immutable, append_only) = file_flags(&entry.probed_path, &metadata);
+        if immutable {
+            entry.issues.push(ProbeIssue::Immutable);
+        }
+        if append_only {
+            entry.issues.push(ProbeIssue::AppendOnly);
+        }
+
+        // 删除仅受只读挂载与文件属性限制, 目录写权限由目录探测项覆盖
+        if entry.access == PathAccess::Remove {
+            return Ok(());
+        }
+
+        let mode_allows = mode_allows_write(&metadata, euid);
+        let mut access_mode = libc::W_OK;
+        if metadata.is_dir() {
+            access_mode |= libc::X_OK;
+        }
+        // access() 由内核判定, 会计入 ACL
+        let kernel_allows = unsafe { libc::access(c_path.as_ptr(), access_mode) } == 0;
+
+        entry.acl_grants = kernel_allows && !mode_allows;
+        if !kernel_allows && !immutable && !entry.issues.contains(&ProbeIssue::ReadOnlyMount) {
+            entry.issues.push(if mode_allows {
+                ProbeIssue::AclDenied
+            } else {
+                ProbeIssue::ModeDenied
+            });
+        }
+
+        Ok(())
+    }
+
+    /// 按所有者/组/其他用户权限位判断写权限 (目录还需要执行权限)
+    fn mode_allows_write(metadata: &std::fs::Metadata, euid: libc::uid_t) -> bool {
+        if euid == 0 {
+            return true;
+        }
+
+        let mode = metadata.mode();
+        let shift = if metadata.uid() == euid {
+            6
+        } else if in_group(metadata.gid()) {
+            3
+        } else {
+            0
+        };
+        let mut required = 0o2;
+        if metadata.is_dir() {
+            required |= 0o1;
+        }
+        (mode >> shift) & required == required
+    }
+
+    fn in_group(gid: libc::gid_t) -> bool {
+        if unsafe { libc::getegid() } == gid {
+            return true;
+        }
+
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if count <= 0 {
+            return false;
+        }
+        let mut groups = vec![0 as libc::gid_t; count as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        count > 0 && groups[..count as usize].contains(&gid)
+    }
+
+    fn is_read_only_mount(c_path: &CString) -> bool {
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return false;
+        }
+        stat.f_flag & libc::ST_RDONLY != 0
+    }
+
+    /// 读取不可变/仅追加属性 (chattr +i / +a)
+    #[cfg(target_os = "linux")]
+    fn file_flags(path: &Path, _metadata: &std::fs::Metadata) -> (bool, bool) {
+        use std::os::unix::fs::OpenOptionsExt;
+        use std::os::unix::io::AsRawFd;
+
+        const FS_IMMUTABLE_FL: libc::c_long = 0x10;
+        const FS_APPEND_FL: libc::c_long = 0x20;
+        // _IOR('f', 1, long)
+        const FS_IOC_GETFLAGS: libc::c_ulong = (2 << 30)
+            | ((std::mem::size_of::<libc::c_long>() as libc::c_ulong) << 16)
+            | ((b'f' as libc::c_ulong) << 8)
+            | 1;
+
+        let file = match std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(_) => return (false, false),
+        };
+
+        let mut flags: libc::c_long = 0;
+        // 文件系统不支持时返回错误, 视为未设置属性
+        if unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_GETFLAGS as _, &mut flags) } != 0 {
+            return (false, false);
+        }
+        (flags & FS_IMMUTABLE_FL != 0, flags & FS_APPEND_FL != 0)
+    }
+
+    /// 读取不可变/仅追加标志 (chflags uchg/schg, uappnd/sappnd)
+    #[cfg(target_os = "macos")]
+    fn file_flags(_path: &Path, metadata: &std::fs::Metadata) -> (bool, bool) {
+        use std::os::macos::fs::MetadataExt;
+
+        const UF_IMMUTABLE: u32 = 0x0000_0002;
+        const UF_APPEND: u32 = 0x0000_0004;
+        const SF_IMMUTABLE: u32 = 0x0002_0000;
+        const SF_APPEND: u32 = 0x0004_0000;
+
+        let flags = metadata.st_flags();
+        (
+            flags & (UF_IMMUTABLE | SF_IMMUTABLE) != 0,
+            flags & (UF_APPEND | SF_APPEND) != 0,
+        )
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn file_flags(_path: &Path, _metadata: &std::fs::Metadata) -> (bool, bool) {
+        (false, false)
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::{PathAccess, PathProbe, ProbeIssue};
+    use std::fs;
+    use std::io::{self, ErrorKind};
+
+    pub fn probe(entry: &mut PathProbe) -> io::Result<()> {
+        let metadata = entry.probed_path.metadata()?;
+        entry.owned_by_current_user = true;
+
+        if metadata.permissions().readonly() && !metadata.is_dir() {
+            entry.issues.push(ProbeIssue::ModeDenied);
+            return Ok(());
+        }
+
+        // Windows 目录的只读属性不代表写权限, 通过创建测试文件判断
+        if metadata.is_dir() && entry.access == PathAccess::Modify {
+            let test_path = entry.probed_path.join(".anti-power-write-test");
+            match fs::OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(&test_path)
+            {
+                Ok(_) => {
+                    let _ = fs::remove_file(&test_path);
+                }
+                Err(err) => match err.kind() {
+                    ErrorKind::PermissionDenied => entry.issues.push(ProbeIssue::ModeDenied),
+                    ErrorKind::ReadOnlyFilesystem => entry.issues.push(ProbeIssue::ReadOnlyMount),
+                    _ => return Err(err),
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
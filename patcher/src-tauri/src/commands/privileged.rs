@@ -0,0 +1,203 @@
+//! 提权辅助模块
+//!
+//! macOS/Linux 上写入系统目录需要提权时, 以 `--privileged-apply` 模式重新执行安装器自身,
+//! 通过 stdin 传入 JSON 操作计划, 提权进程复用与普通流程相同的 Rust 实现,
+//! 并在 stdout 输出 JSON 结果, 保证提权与非提权安装行为一致
+
+use super::audit;
+use super::i18n::CommandError;
+use super::patch::{self, FeatureConfig, ManagerFeatureConfig, PatchMode};
+use crate::embedded;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 提权辅助模式的命令行参数
+pub const PRIVILEGED_APPLY_ARG: &str = "--privileged-apply";
+
+/// 当前进程是否为提权辅助进程 (辅助进程内不再尝试二次提权)
+static HELPER_PROCESS: AtomicBool = AtomicBool::new(false);
+
+pub fn is_helper_process() -> bool {
+    HELPER_PROCESS.load(Ordering::Relaxed)
+}
+
+/// 提权操作计划 (父进程 -> 提权进程)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivilegedPlan {
+    pub mode: PatchMode,
+    /// resources/app 目录
+    pub resources_root: PathBuf,
+    pub features: Option<FeatureConfig>,
+    pub manager_features: Option<ManagerFeatureConfig>,
+    pub locale: Option<String>,
+    /// 开发模式下的补丁源目录 (提权进程的工作目录不可靠, 发布模式使用内嵌文件)
+    #[serde(default)]
+    pub patches_dir: Option<PathBuf>,
+}
+
+/// 提权执行结果 (提权进程 -> 父进程)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivilegedReport {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<PrivilegedError>,
+    /// 提权进程写入或删除的文件, 供父进程记录审计日志
+    #[serde(default)]
+    pub touched_files: Vec<PathBuf>,
+}
+
+/// 结构化错误, 保留本地化键与参数, 由父进程按界面语言重新生成消息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivilegedError {
+    /// 本地化键 (原始错误时为空)
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    /// 提权进程按计划语言生成的消息, 便于日志排查
+    pub message: String,
+}
+
+impl PrivilegedReport {
+    fn success() -> Self {
+        Self {
+            ok: true,
+            error: None,
+            touched_files: Vec::new(),
+        }
+    }
+
+    fn failure(error: &CommandError, locale: Option<&str>) -> Self {
+        let (key, vars) = match error {
+            CommandError::Localized { key, vars } => (
+                Some(key.to_string()),
+                vars.iter().cloned().collect::<BTreeMap<_, _>>(),
+            ),
+            CommandError::Raw(_) => (None, BTreeMap::new()),
+        };
+
+        Self {
+            ok: false,
+            error: Some(PrivilegedError {
+                key,
+                vars,
+                message: error.to_message(locale),
+            }),
+            touched_files: Vec::new(),
+        }
+    }
+
+    /// 转换为父进程使用的结果
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    pub fn into_result(self) -> Result<(), CommandError> {
+        for file in &self.touched_files {
+            audit::track_file(file);
+        }
+
+        if self.ok {
+            return Ok(());
+        }
+
+        let error = match self.error {
+            Some(error) => error,
+            None => {
+                return Err(CommandError::key(
+                    "patchBackend.errors.privilegedCanceledOrFailed",
+                ))
+            }
+        };
+
+        match error.key {
+            Some(key) => Err(CommandError::Localized {
+                key: key.into(),
+                vars: error.vars.into_iter().collect(),
+            }),
+            None => Err(CommandError::Raw(error.message)),
+        }
+    }
+}
+
+/// 判断当前进程是否以提权辅助模式启动
+pub fn is_privileged_apply() -> bool {
+    std::env::args()
+        .skip(1)
+        .any(|arg| arg == PRIVILEGED_APPLY_ARG)
+}
+
+/// 提权辅助模式入口
+/// 从 stdin 读取操作计划并执行, 在 stdout 输出单行 JSON 结果, 返回进程退出码
+pub fn run_privileged_apply() -> i32 {
+    HELPER_PROCESS.store(true, Ordering::Relaxed);
+
+    let mut input = String::new();
+    let report = match std::io::stdin().read_to_string(&mut input) {
+        Ok(_) => match serde_json::from_str::<PrivilegedPlan>(&input) {
+            Ok(plan) => {
+                let locale = plan.locale.as_deref();
+                if let Some(dir) = &plan.patches_dir {
+                    embedded::set_patches_dir_override(dir.clone());
+                }
+                let (result, touched_files) =
+                    audit::collect_files(|| patch::apply_privileged_plan(&plan));
+                let report = match result {
+                    Ok(()) => PrivilegedReport::success(),
+                    Err(err) => PrivilegedReport::failure(&err, locale),
+                };
+                PrivilegedReport {
+                    touched_files,
+                    ..report
+                }
+            }
+            Err(err) => PrivilegedReport::failure(
+                &CommandError::key_with(
+                    "patchBackend.errors.parsePlanFailed",
+                    &[("detail", err.to_string())],
+                ),
+                None,
+            ),
+        },
+        Err(err) => PrivilegedReport::failure(
+            &CommandError::key_with(
+                "patchBackend.errors.readPlanFailed",
+                &[("detail", err.to_string())],
+            ),
+            None,
+        ),
+    };
+
+    let exit_code = if report.ok { 0 } else { 1 };
+    match serde_json::to_string(&report) {
+        Ok(line) => println!("{}", line),
+        Err(err) => eprintln!("{}", err),
+    }
+    exit_code
+}
+
+/// 解析提权进程的 stdout, 取最后一行 JSON 结果
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn parse_report(stdout: &str) -> Option<PrivilegedReport> {
+    stdout
+        .lines()
+        .rev()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .find_map(|line| serde_json::from_str::<PrivilegedReport>(line).ok())
+}
+
+/// 提权进程要执行的程序路径
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn helper_executable() -> std::io::Result<PathBuf> {
+    // AppImage 运行时挂载点仅对当前用户可见, 提权后需改为执行 AppImage 文件本身
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+
+    std::env::current_exe()
+}
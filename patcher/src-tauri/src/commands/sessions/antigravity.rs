@@ -0,0 +1,664 @@
+//! Antigravity 对话记录 (~/.gemini/antigravity{,-ide})
+//!
+//! conversations/<id>.pb 为加密的对话内容, 无法解码, 只用于发现对话与判断活跃时间;
+//! 标题与时间来自 state.vscdb 中 trajectorySummaries 键的 protobuf (无 schema, 按结构尽力解析),
+//! 消息为 brain/<id>/ 下 Agent 生成的 Markdown artifact 与截图
+
+use super::audit;
+use super::provider::{ProviderCapabilities, SessionProvider};
+use super::util::{
+    decode_base64, encode_base64, file_modified_ms, parse_timestamp, path_basename, truncate,
+    Fingerprint,
+};
+use super::{AttachmentKind, ContentBlock, SessionMessage, SessionMeta};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+const TRAJECTORY_SUMMARIES_KEY: &str = "antigravityUnifiedStateSync.trajectorySummaries";
+
+/// 已解析的 trajectorySummaries, state.vscdb 未变化时复用
+static SUMMARIES: Mutex<Option<(Fingerprint, HashMap<String, TrajectorySummary>)>> =
+    Mutex::new(None);
+
+pub struct AntigravityProvider;
+
+impl SessionProvider for AntigravityProvider {
+    fn id(&self) -> &'static str {
+        "antigravity"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Antigravity"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        let gemini = home.join(".gemini");
+        vec![gemini.join("antigravity-ide"), gemini.join("antigravity")]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: true,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_antigravity_sources(root)
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        read_antigravity_meta(source)
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        parse_antigravity_messages(source_path)
+    }
+
+    fn session_paths(&self, source_path: &Path, session_id: &str) -> Result<Vec<PathBuf>, String> {
+        antigravity_session_paths(source_path, session_id)
+    }
+
+    /// Antigravity 运行时会用内存中的状态覆盖数据库, 历史列表中的标题需在其退出后才会消失
+    fn detach(&self, _source_path: &Path, session_id: &str) -> Result<Option<Value>, String> {
+        remove_trajectory_summary(session_id)
+            .map(|entry| entry.map(|entry| Value::String(encode_base64(&entry))))
+            .map_err(|e| format!("更新 state.vscdb 失败: {}", e))
+    }
+
+    fn reattach(&self, detached: &Value) -> Result<(), String> {
+        let entry = detached
+            .as_str()
+            .and_then(decode_base64)
+            .ok_or("回收站中的 trajectorySummaries 条目无效")?;
+        restore_trajectory_summary(&entry).map_err(|e| format!("更新 state.vscdb 失败: {}", e))
+    }
+}
+
+/// 单个对话在 trajectorySummaries 中的信息
+#[derive(Debug, Default, Clone)]
+struct TrajectorySummary {
+    title: Option<String>,
+    workspace: Option<String>,
+    created_at: Option<u64>,
+    updated_at: Option<u64>,
+}
+
+/// 数据源: 有 conversations/<id>.pb 时为该文件, 否则为 brain/<id>/ 目录
+fn list_antigravity_sources(root: &Path) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    let conversations = root.join("conversations");
+    if let Ok(entries) = fs::read_dir(&conversations) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("pb") {
+                sources.push(path);
+            }
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(root.join("brain")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(id) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if path.is_dir() && !conversations.join(format!("{}.pb", id)).exists() {
+                sources.push(path);
+            }
+        }
+    }
+
+    sources
+}
+
+/// 由数据源路径得到 (数据根目录, 对话 id)
+fn split_source(source: &Path) -> Option<(&Path, String)> {
+    let root = source.parent()?.parent()?;
+    let id = if source.extension().and_then(|e| e.to_str()) == Some("pb") {
+        source.file_stem()?
+    } else {
+        source.file_name()?
+    };
+    Some((root, id.to_string_lossy().to_string()))
+}
+
+fn read_antigravity_meta(source: &Path) -> Option<SessionMeta> {
+    let (root, id) = split_source(source)?;
+    let summary = load_trajectory_summaries().remove(&id).unwrap_or_default();
+    let brain = read_brain_artifacts(&root.join("brain").join(&id));
+
+    let title = summary.title.or_else(|| {
+        brain
+            .iter()
+            .find_map(|artifact| markdown_heading(&artifact.content))
+    });
+    let artifact_summary = brain
+        .iter()
+        .find_map(|artifact| artifact.summary.clone())
+        .map(|text| truncate(&text, 160));
+
+    let mut times: Vec<u64> = brain.iter().filter_map(|artifact| artifact.ts).collect();
+    times.extend(file_modified_ms(source));
+    let created_at = summary.created_at.or_else(|| times.iter().min().copied());
+    let last_active_at = times.iter().copied().chain(summary.updated_at).max();
+    let project_name = summary.workspace.as_deref().and_then(path_basename);
+
+    Some(SessionMeta {
+        provider_id: "antigravity".to_string(),
+        session_id: id,
+        title: title.or_else(|| project_name.clone()),
+        summary: artifact_summary,
+        project_dir: summary.workspace,
+        project_name,
+        created_at,
+        last_active_at,
+        source_path: source.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+// brain artifact
+
+struct BrainArtifact {
+    path: PathBuf,
+    name: String,
+    content: String,
+    summary: Option<String>,
+    ts: Option<u64>,
+}
+
+/// 读取 brain/<id>/ 下的 Markdown artifact, .resolved 等历史版本不读取
+fn read_brain_artifacts(dir: &Path) -> Vec<BrainArtifact> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut artifacts = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // <name>.metadata.json 记录 artifact 的摘要与更新时间
+        let metadata = fs::read_to_string(dir.join(format!("{}.metadata.json", name)))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .unwrap_or(Value::Null);
+        let summary = metadata
+            .get("summary")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+        let ts = parse_timestamp(metadata.get("updatedAt").unwrap_or(&Value::Null))
+            .or_else(|| file_modified_ms(&path));
+
+        artifacts.push(BrainArtifact {
+            path,
+            name,
+            content,
+            summary,
+            ts,
+        });
+    }
+    artifacts.sort_by_key(|artifact| artifact.ts);
+    artifacts
+}
+
+fn markdown_heading(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+fn image_media_type(path: &Path) -> Option<&'static str> {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    }
+}
+
+fn parse_antigravity_messages(source: &Path) -> Result<Vec<SessionMessage>, String> {
+    let (root, id) = split_source(source).ok_or("无法确定 Antigravity 数据目录")?;
+    let brain = root.join("brain").join(&id);
+
+    let mut messages: Vec<SessionMessage> = read_brain_artifacts(&brain)
+        .into_iter()
+        .filter_map(|artifact| {
+            let blocks = vec![
+                ContentBlock::Attachment {
+                    kind: AttachmentKind::File,
+                    name: Some(artifact.name),
+                    media_type: Some("text/markdown".to_string()),
+                    uri: Some(artifact.path.to_string_lossy().to_string()),
+                },
+                ContentBlock::Text {
+                    text: artifact.content,
+                },
+            ];
+            SessionMessage::from_blocks("assistant".to_string(), artifact.ts, blocks)
+        })
+        .collect();
+
+    // Agent 截取的浏览器截图
+    if let Ok(entries) = fs::read_dir(&brain) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(media_type) = image_media_type(&path) else {
+                continue;
+            };
+            let blocks = vec![ContentBlock::Attachment {
+                kind: AttachmentKind::Image,
+                name: Some(entry.file_name().to_string_lossy().to_string()),
+                media_type: Some(media_type.to_string()),
+                uri: Some(path.to_string_lossy().to_string()),
+            }];
+            messages.extend(SessionMessage::from_blocks(
+                "assistant".to_string(),
+                file_modified_ms(&path),
+                blocks,
+            ));
+        }
+    }
+
+    messages.sort_by_key(|message| message.ts);
+    Ok(messages)
+}
+
+// state.vscdb
+
+/// Antigravity 用户数据目录 (与 anti-clean.sh 的查找顺序一致)
+fn resolve_antigravity_data_dir() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let mut candidates = Vec::new();
+    if let Some(config) = dirs::config_dir() {
+        candidates.push(config.join("Antigravity IDE"));
+        candidates.push(config.join("antigravity-ide"));
+        candidates.push(config.join("Antigravity"));
+    }
+    candidates.push(home.join(".antigravity-ide").join("antigravity-ide"));
+    candidates.push(home.join(".antigravity-ide"));
+    candidates.into_iter().find(|dir| dir.is_dir())
+}
+
+fn state_db_path() -> Option<PathBuf> {
+    resolve_antigravity_data_dir()
+        .map(|dir| dir.join("User").join("globalStorage").join("state.vscdb"))
+        .filter(|path| path.is_file())
+}
+
+#[cfg(target_os = "windows")]
+fn sqlite3_command() -> Option<Command> {
+    let executable = crate::commands::clean::resolve_sqlite3_executable()?;
+    Some(crate::commands::clean::new_windows_command(executable))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn sqlite3_command() -> Option<Command> {
+    Some(Command::new("sqlite3"))
+}
+
+/// 执行 SQL 并返回标准输出, sqlite3 不可用时返回 Err
+fn run_sqlite(db: &Path, sql: &str, readonly: bool) -> Result<String, String> {
+    let mut command = sqlite3_command().ok_or("未找到 sqlite3")?;
+    if readonly {
+        command.arg("-readonly");
+    }
+    let output = command
+        .arg(db)
+        .arg(sql)
+        .output()
+        .map_err(|e| format!("执行 sqlite3 失败: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn read_summaries_value(db: &Path) -> Option<Vec<u8>> {
+    let sql = format!(
+        "select value from ItemTable where key='{}';",
+        TRAJECTORY_SUMMARIES_KEY
+    );
+    let value = run_sqlite(db, &sql, true).ok()?;
+    if value.is_empty() {
+        return None;
+    }
+    decode_base64(&value).or_else(|| Some(value.into_bytes()))
+}
+
+/// 读取并解析 trajectorySummaries, 数据库或 sqlite3 不可用时返回空表
+fn load_trajectory_summaries() -> HashMap<String, TrajectorySummary> {
+    let Some(db) = state_db_path() else {
+        return HashMap::new();
+    };
+    let fingerprint = Fingerprint::of(&db);
+    let mut guard = SUMMARIES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((cached, summaries)) = guard.as_ref() {
+        if *cached == fingerprint {
+            return summaries.clone();
+        }
+    }
+
+    let summaries = read_summaries_value(&db)
+        .map(|value| parse_trajectory_summaries(&value))
+        .unwrap_or_default();
+    *guard = Some((fingerprint, summaries.clone()));
+    summaries
+}
+
+/// 从 trajectorySummaries 中移除指定对话, 返回移除的原始字段 (含字段头);
+/// 条目不存在时不写入数据库
+fn remove_trajectory_summary(id: &str) -> Result<Option<Vec<u8>>, String> {
+    let Some(db) = state_db_path() else {
+        return Ok(None);
+    };
+    let Some(value) = read_summaries_value(&db) else {
+        return Ok(None);
+    };
+    let Some(fields) = parse_proto(&value) else {
+        return Ok(None);
+    };
+
+    let mut kept = Vec::with_capacity(value.len());
+    let mut removed = None;
+    for field in &fields {
+        let is_target = match field.value {
+            Wire::Bytes(entry) => entry_id(entry).as_deref() == Some(id),
+            _ => false,
+        };
+        if is_target {
+            removed = Some(field.raw.to_vec());
+        } else {
+            kept.extend_from_slice(field.raw);
+        }
+    }
+    if removed.is_none() {
+        return Ok(None);
+    }
+
+    write_summaries_value(&db, &kept)?;
+    Ok(removed)
+}
+
+/// 将 remove_trajectory_summary 移除的字段追加回 trajectorySummaries
+fn restore_trajectory_summary(entry: &[u8]) -> Result<(), String> {
+    let db = state_db_path().ok_or("未找到 state.vscdb")?;
+    let mut value = read_summaries_value(&db).unwrap_or_default();
+    value.extend_from_slice(entry);
+    write_summaries_value(&db, &value)
+}
+
+fn write_summaries_value(db: &Path, value: &[u8]) -> Result<(), String> {
+    let sql = format!(
+        "insert or replace into ItemTable (key, value) values ('{}', '{}');",
+        TRAJECTORY_SUMMARIES_KEY,
+        encode_base64(value)
+    );
+    run_sqlite(db, &sql, false)?;
+    audit::track_file(db);
+    Ok(())
+}
+
+// protobuf wire format
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+struct Field<'a> {
+    number: u64,
+    value: Wire<'a>,
+    /// 该字段的完整编码 (含 tag), 用于删除条目后重新拼接
+    raw: &'a [u8],
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// 按 wire format 解析一层字段, 数据不是合法的 protobuf 时返回 None
+fn parse_proto(buf: &[u8]) -> Option<Vec<Field<'_>>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let start = pos;
+        let tag = read_varint(buf, &mut pos)?;
+        let number = tag >> 3;
+        if number == 0 {
+            return None;
+        }
+        let value = match tag & 0x7 {
+            0 => Wire::Varint(read_varint(buf, &mut pos)?),
+            1 => {
+                pos += 8;
+                Wire::Fixed
+            }
+            2 => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                let end = pos.checked_add(len).filter(|end| *end <= buf.len())?;
+                let bytes = &buf[pos..end];
+                pos = end;
+                Wire::Bytes(bytes)
+            }
+            5 => {
+                pos += 4;
+                Wire::Fixed
+            }
+            _ => return None,
+        };
+        if pos > buf.len() {
+            return None;
+        }
+        fields.push(Field {
+            number,
+            value,
+            raw: &buf[start..pos],
+        });
+    }
+    Some(fields)
+}
+
+fn is_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// 可读文本: 合法 UTF-8, 不以控制字符开头且不含除换行、制表符以外的控制字符
+/// (嵌套消息的首字节通常为 0x0a 即换行符, 以此与文本区分)
+fn as_text(bytes: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let readable = text.chars().next().is_some_and(|c| !c.is_control())
+        && !text
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t');
+    readable.then_some(text)
+}
+
+/// 像 base64 编码的嵌套数据 (Antigravity 会把子消息编码为 base64 字符串存放)
+fn looks_like_base64(text: &str) -> bool {
+    text.len() >= 16
+        && text.len().is_multiple_of(4)
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='))
+}
+
+/// 条目中直接出现的对话 id
+fn entry_id(entry: &[u8]) -> Option<String> {
+    parse_proto(entry)?
+        .iter()
+        .find_map(|field| match field.value {
+            Wire::Bytes(bytes) => as_text(bytes)
+                .filter(|text| is_uuid(text))
+                .map(str::to_string),
+            _ => None,
+        })
+}
+
+/// 顶层为重复的条目, 每个条目含对话 id 与 (可能 base64 编码的) 摘要消息
+fn parse_trajectory_summaries(value: &[u8]) -> HashMap<String, TrajectorySummary> {
+    let mut summaries = HashMap::new();
+    for field in parse_proto(value).unwrap_or_default() {
+        let Wire::Bytes(entry) = field.value else {
+            continue;
+        };
+        let Some(id) = entry_id(entry) else {
+            continue;
+        };
+        let mut summary = TrajectorySummary::default();
+        let mut times = Vec::new();
+        collect_summary(entry, &id, &mut summary, &mut times, 0);
+        summary.created_at = times.iter().min().copied();
+        summary.updated_at = times.iter().max().copied();
+        summaries.insert(id, summary);
+    }
+    summaries
+}
+
+/// 深度优先遍历: 第一个普通文本作为标题, 第一个 file:// URI 作为工作区,
+/// 形如 google.protobuf.Timestamp 的子消息作为时间
+fn collect_summary(
+    buf: &[u8],
+    id: &str,
+    summary: &mut TrajectorySummary,
+    times: &mut Vec<u64>,
+    depth: usize,
+) {
+    if depth > 8 {
+        return;
+    }
+    let Some(fields) = parse_proto(buf) else {
+        return;
+    };
+    if let Some(ts) = as_timestamp(&fields) {
+        times.push(ts);
+        return;
+    }
+
+    for field in &fields {
+        let Wire::Bytes(bytes) = field.value else {
+            continue;
+        };
+        match as_text(bytes) {
+            Some(text) if looks_like_base64(text) => {
+                if let Some(decoded) = decode_base64(text) {
+                    collect_summary(&decoded, id, summary, times, depth + 1);
+                }
+            }
+            Some(text) if text.starts_with("file://") => {
+                if summary.workspace.is_none() {
+                    summary.workspace = Some(file_uri_to_path(text));
+                }
+            }
+            Some(text) if text != id && !is_uuid(text) && !text.contains("://") => {
+                if summary.title.is_none() {
+                    summary.title = Some(text.trim().to_string());
+                }
+            }
+            Some(_) => {}
+            None => collect_summary(bytes, id, summary, times, depth + 1),
+        }
+    }
+}
+
+/// seconds (字段 1) 与可选 nanos (字段 2) 组成的时间戳, 秒数需在合理范围内
+fn as_timestamp(fields: &[Field]) -> Option<u64> {
+    let mut seconds = None;
+    for field in fields {
+        match (field.number, &field.value) {
+            (1, Wire::Varint(value)) => seconds = Some(*value),
+            (2, Wire::Varint(nanos)) if *nanos < 1_000_000_000 => {}
+            _ => return None,
+        }
+    }
+    seconds
+        .filter(|s| (1_500_000_000..4_000_000_000).contains(s))
+        .map(|s| s * 1000)
+}
+
+/// file:///path%20name → /path name (Windows 的 /C:/ 去掉开头的斜杠)
+fn file_uri_to_path(uri: &str) -> String {
+    let path = uri.trim_start_matches("file://");
+    let mut bytes = Vec::with_capacity(path.len());
+    let raw = path.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'%' && i + 2 < raw.len() {
+            if let Ok(byte) = u8::from_str_radix(&path[i + 1..i + 3], 16) {
+                bytes.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    let decoded = String::from_utf8_lossy(&bytes).to_string();
+    let is_drive = decoded.len() >= 3
+        && decoded.starts_with('/')
+        && decoded.as_bytes()[2] == b':'
+        && decoded.as_bytes()[1].is_ascii_alphabetic();
+    if is_drive {
+        decoded[1..].to_string()
+    } else {
+        decoded
+    }
+}
+
+// 删除
+
+/// 对话的全部文件: conversations/<id>.pb, brain/<id>/, annotations/<id>.pbtxt,
+/// browser_recordings/<id>/; trajectorySummaries 中的条目由 detach 移除
+fn antigravity_session_paths(source: &Path, session_id: &str) -> Result<Vec<PathBuf>, String> {
+    let (root, id) = split_source(source).ok_or("无法确定 Antigravity 数据目录")?;
+    if id != session_id {
+        return Err(format!("对话 id 与数据源不匹配: {}", session_id));
+    }
+
+    Ok([
+        root.join("conversations").join(format!("{}.pb", id)),
+        root.join("annotations").join(format!("{}.pbtxt", id)),
+        root.join("brain").join(&id),
+        root.join("browser_recordings").join(&id),
+    ]
+    .into_iter()
+    .filter(|path| path.exists())
+    .collect())
+}
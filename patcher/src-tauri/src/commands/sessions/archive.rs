@@ -0,0 +1,801 @@
+//! 对话归档
+//!
+//! 将选中的对话按各 provider 的原始目录结构打包为 tar.zst 并删除原文件,
+//! 归档中的第一个文件 manifest.json 记录对话元数据与每个文件的原始路径.
+//! 归档中的对话可只读浏览, 也可单独恢复到原位置
+
+use super::cache::cached_meta;
+use super::provider::find_provider;
+use super::util::{file_modified_ms, now_ms, remove_path, tar_header, Fingerprint};
+use super::{SessionMessage, SessionMeta};
+use crate::commands::audit::{self, AuditRecord};
+use crate::commands::config;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+const ARCHIVE_EXTENSION: &str = ".tar.zst";
+/// 归档内存放对话文件的目录
+const FILES_DIR_NAME: &str = "files";
+const COMPRESSION_LEVEL: i32 = 9;
+
+type ArchiveWriter = tar::Builder<zstd::stream::write::Encoder<'static, BufWriter<fs::File>>>;
+type ArchiveDecoder = zstd::stream::read::Decoder<'static, BufReader<fs::File>>;
+type ArchiveReader = tar::Archive<ArchiveDecoder>;
+type ArchiveEntries<'a> = tar::Entries<'a, ArchiveDecoder>;
+type ArchiveEntry<'a> = tar::Entry<'a, ArchiveDecoder>;
+
+/// 写入归档的文件及其写入前的指纹, 按条目的原始路径分组
+type ArchivedFiles = HashMap<String, Vec<(PathBuf, Fingerprint)>>;
+
+/// 归档清单
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveManifest {
+    version: u32,
+    created_at: u64,
+    sessions: Vec<ArchivedSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedSession {
+    meta: SessionMeta,
+    items: Vec<ArchivedItem>,
+    /// provider 恢复索引 (例如数据库记录) 所需的数据
+    #[serde(default)]
+    detached: Option<Value>,
+}
+
+/// 归档的单个文件或目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedItem {
+    original_path: String,
+    /// 在归档中的路径, 目录时为其下文件的公共前缀
+    entry: String,
+}
+
+/// 归档信息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveInfo {
+    pub id: String,
+    pub path: String,
+    pub created_at: u64,
+    /// 压缩后的文件大小
+    pub size: u64,
+    pub sessions: Vec<SessionMeta>,
+    /// 写入归档后又被修改、因此保留了原文件的对话 (归档中为修改前的副本);
+    /// 只在创建归档时返回
+    pub kept: Vec<String>,
+}
+
+/// 要归档的对话
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSessionRef {
+    pub provider_id: String,
+    pub source_path: String,
+}
+
+fn get_archive_dir() -> PathBuf {
+    if let Some(dir) = config::get_config()
+        .archive_dir
+        .filter(|dir| !dir.trim().is_empty())
+    {
+        return PathBuf::from(dir);
+    }
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join("archives")
+}
+
+/// 路径的普通组件, 含 .. 等其他组件时返回 None
+fn normal_components(path: &Path) -> Option<Vec<String>> {
+    path.components()
+        .filter(|component| !matches!(component, Component::Prefix(_) | Component::RootDir))
+        .map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 文件在归档中的路径: files/<provider>/home/<相对用户目录的路径>,
+/// 不在用户目录下时为 files/<provider>/root/<绝对路径>
+fn entry_name(provider_id: &str, path: &Path, home: &Path) -> Option<String> {
+    let (base, relative) = match path.strip_prefix(home) {
+        Ok(relative) => ("home", relative),
+        Err(_) => ("root", path),
+    };
+    let parts = normal_components(relative)?;
+    Some(format!(
+        "{}/{}/{}/{}",
+        FILES_DIR_NAME,
+        provider_id,
+        base,
+        parts.join("/")
+    ))
+}
+
+/// 条目包含的全部文件: 目录时为其下的文件, 否则为条目本身
+fn item_files(original: &Path) -> Vec<PathBuf> {
+    if original.is_dir() {
+        walk_files(original)
+    } else {
+        vec![original.to_path_buf()]
+    }
+}
+
+/// 目录下的全部文件
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// 拼接条目内的相对路径, 相对路径为空时即条目本身
+fn join_relative(base: &Path, relative: &Path) -> PathBuf {
+    if relative.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(relative)
+    }
+}
+
+impl ArchivedSession {
+    /// 归档中的文件属于该对话时, 返回其所属的条目与条目内的相对路径
+    fn locate(&self, name: &str) -> Option<(&ArchivedItem, PathBuf)> {
+        self.items.iter().find_map(|item| {
+            let relative = if name == item.entry {
+                ""
+            } else {
+                name.strip_prefix(&item.entry)?.strip_prefix('/')?
+            };
+            let parts = normal_components(Path::new(relative))?;
+            Some((item, parts.iter().collect()))
+        })
+    }
+
+    fn reattach(&self) -> Result<(), String> {
+        match &self.detached {
+            Some(detached) => find_provider(&self.meta.provider_id)?.reattach(detached),
+            None => Ok(()),
+        }
+    }
+}
+
+fn open_writer(path: &Path) -> Result<ArchiveWriter, String> {
+    let file = fs::File::create(path).map_err(|e| format!("创建归档失败: {}", e))?;
+    let encoder = zstd::stream::write::Encoder::new(BufWriter::new(file), COMPRESSION_LEVEL)
+        .map_err(|e| format!("创建归档失败: {}", e))?;
+    Ok(tar::Builder::new(encoder))
+}
+
+fn finish_writer(tar: ArchiveWriter) -> Result<(), String> {
+    let map_err = |e: io::Error| format!("写入归档失败: {}", e);
+    let file = tar
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(map_err)?
+        .into_inner()
+        .map_err(|e| map_err(e.into_error()))?;
+    file.sync_all().map_err(map_err)
+}
+
+fn write_manifest(tar: &mut ArchiveWriter, manifest: &ArchiveManifest) -> Result<(), String> {
+    let content =
+        serde_json::to_vec_pretty(manifest).map_err(|e| format!("序列化归档清单失败: {}", e))?;
+    append_exact(
+        tar,
+        MANIFEST_FILE_NAME,
+        content.len() as u64,
+        &mut &content[..],
+        manifest.created_at / 1000,
+    )
+    .map_err(|e| format!("写入归档失败: {}", e))
+}
+
+/// 追加一个 size 字节的文件; reader 提前结束时返回错误, 超出的部分不写入,
+/// 避免写入过程中被修改的文件使数据与头部记录的大小不一致
+fn append_exact<W: io::Write>(
+    tar: &mut tar::Builder<W>,
+    name: &str,
+    size: u64,
+    reader: &mut impl Read,
+    mtime_secs: u64,
+) -> io::Result<()> {
+    let mut data = ExactReader {
+        inner: reader.take(size),
+        remaining: size,
+    };
+    tar.append_data(&mut tar_header(size, mtime_secs), name, &mut data)
+}
+
+struct ExactReader<R> {
+    inner: io::Take<R>,
+    remaining: u64,
+}
+
+impl<R: Read> Read for ExactReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && self.remaining > 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "文件在写入归档时被截断",
+            ));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+fn open_archive(path: &Path) -> Result<ArchiveReader, String> {
+    let file = fs::File::open(path).map_err(|e| format!("打开归档失败: {}", e))?;
+    let decoder =
+        zstd::stream::read::Decoder::new(file).map_err(|e| format!("打开归档失败: {}", e))?;
+    Ok(tar::Archive::new(decoder))
+}
+
+/// 读取归档的第一个文件作为清单, 之后可继续从 entries 读取对话文件
+fn read_manifest(entries: &mut ArchiveEntries) -> Result<ArchiveManifest, String> {
+    let read_err = |e: io::Error| format!("读取归档失败: {}", e);
+    let mut entry = entries
+        .next()
+        .transpose()
+        .map_err(read_err)?
+        .ok_or("归档缺少清单")?;
+    if entry.path().map_err(read_err)? != Path::new(MANIFEST_FILE_NAME) {
+        return Err("归档缺少清单".to_string());
+    }
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).map_err(read_err)?;
+    serde_json::from_slice(&content).map_err(|e| format!("归档清单已损坏: {}", e))
+}
+
+/// 打开归档并只读取清单
+fn archive_manifest(path: &Path) -> Result<ArchiveManifest, String> {
+    let mut archive = open_archive(path)?;
+    let mut entries = archive
+        .entries()
+        .map_err(|e| format!("读取归档失败: {}", e))?;
+    read_manifest(&mut entries)
+}
+
+/// 归档中下一个普通文件及其路径, 到达结尾时返回 None
+fn next_file<'a>(
+    entries: &mut ArchiveEntries<'a>,
+) -> io::Result<Option<(ArchiveEntry<'a>, String)>> {
+    for entry in entries {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            let name = entry.path()?.to_string_lossy().to_string();
+            return Ok(Some((entry, name)));
+        }
+    }
+    Ok(None)
+}
+
+fn find_archive(archive_id: &str) -> Result<PathBuf, String> {
+    // archive_id 只能是归档目录下的文件名
+    if !archive_id.ends_with(ARCHIVE_EXTENSION)
+        || archive_id.contains(['/', '\\'])
+        || archive_id.starts_with('.')
+    {
+        return Err(format!("无效的归档 id: {}", archive_id));
+    }
+    let path = get_archive_dir().join(archive_id);
+    if !path.is_file() {
+        return Err(format!("归档不存在: {}", archive_id));
+    }
+    Ok(path)
+}
+
+fn archive_info(path: &Path, manifest: ArchiveManifest) -> ArchiveInfo {
+    ArchiveInfo {
+        id: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        path: path.to_string_lossy().to_string(),
+        created_at: manifest.created_at,
+        size: fs::metadata(path).map(|m| m.len()).unwrap_or_default(),
+        sessions: manifest
+            .sessions
+            .into_iter()
+            .map(|session| session.meta)
+            .collect(),
+        kept: Vec::new(),
+    }
+}
+
+fn reattach_all(sessions: &[ArchivedSession]) {
+    for session in sessions {
+        let _ = session.reattach();
+    }
+}
+
+/// 将对话打包为一个新的归档并删除原文件
+/// 写入归档前先清理 provider 的索引, 写入失败时还原索引并保留原文件
+pub fn create_archive(sessions: &[SessionMeta]) -> Result<ArchiveInfo, String> {
+    if sessions.is_empty() {
+        return Err("没有要归档的对话".to_string());
+    }
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+
+    // 1. 收集每个对话的文件
+    let mut archived = Vec::new();
+    for meta in sessions {
+        let provider = find_provider(&meta.provider_id)?;
+        let items = provider
+            .session_paths(Path::new(&meta.source_path), &meta.session_id)?
+            .into_iter()
+            .filter(|path| path.exists())
+            .map(|path| {
+                let entry = entry_name(provider.id(), &path, &home)
+                    .ok_or_else(|| format!("无法归档的路径: {}", path.display()))?;
+                Ok(ArchivedItem {
+                    original_path: path.to_string_lossy().to_string(),
+                    entry,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if items.is_empty() && !provider.source_exists(Path::new(&meta.source_path)) {
+            return Err(format!("文件不存在: {}", meta.source_path));
+        }
+        archived.push(ArchivedSession {
+            meta: meta.clone(),
+            items,
+            detached: None,
+        });
+    }
+
+    // 2. 清理 provider 的索引
+    for index in 0..archived.len() {
+        let meta = &archived[index].meta;
+        let detached = find_provider(&meta.provider_id)
+            .and_then(|provider| provider.detach(Path::new(&meta.source_path), &meta.session_id));
+        match detached {
+            Ok(detached) => archived[index].detached = detached,
+            Err(err) => {
+                reattach_all(&archived[..index]);
+                return Err(err);
+            }
+        }
+    }
+
+    // 3. 写入清单与文件, 完成后再改为正式文件名
+    let archive_dir = get_archive_dir();
+    fs::create_dir_all(&archive_dir).map_err(|e| format!("创建归档目录失败: {}", e))?;
+    let created_at = now_ms();
+    let mut path = archive_dir.join(format!("sessions-{}{}", created_at, ARCHIVE_EXTENSION));
+    let mut suffix = 1;
+    while path.exists() {
+        path = archive_dir.join(format!(
+            "sessions-{}-{}{}",
+            created_at, suffix, ARCHIVE_EXTENSION
+        ));
+        suffix += 1;
+    }
+    let partial = path.with_extension("zst.partial");
+    let manifest = ArchiveManifest {
+        version: MANIFEST_VERSION,
+        created_at,
+        sessions: archived,
+    };
+
+    let written = write_new_archive(&partial, &manifest).and_then(|files| {
+        fs::rename(&partial, &path).map_err(|e| format!("写入归档失败: {}", e))?;
+        Ok(files)
+    });
+    let files = match written {
+        Ok(files) => files,
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            reattach_all(&manifest.sessions);
+            return Err(err);
+        }
+    };
+
+    // 4. 归档已完整写入; 写入后又被修改的对话保留原文件并还原索引,
+    //    其余对话删除原文件, 删除失败的文件保留在原处
+    let mut kept = Vec::new();
+    for session in &manifest.sessions {
+        let changed = session.items.iter().any(|item| {
+            let recorded = files
+                .get(&item.original_path)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let current = item_files(Path::new(&item.original_path));
+            current.len() != recorded.len()
+                || recorded
+                    .iter()
+                    .any(|(file, fingerprint)| Fingerprint::of(file) != *fingerprint)
+        });
+        if changed {
+            session.reattach().map_err(|err| {
+                format!("归档已写入 {}, 但还原对话索引失败: {}", path.display(), err)
+            })?;
+            kept.push(session.meta.source_path.clone());
+            continue;
+        }
+        for item in &session.items {
+            let original = Path::new(&item.original_path);
+            if remove_path(original).is_ok() {
+                audit::track_file(original);
+            }
+        }
+    }
+    Ok(ArchiveInfo {
+        kept,
+        ..archive_info(&path, manifest)
+    })
+}
+
+/// 写入清单与对话文件, 返回写入的文件及其写入前的指纹
+fn write_new_archive(path: &Path, manifest: &ArchiveManifest) -> Result<ArchivedFiles, String> {
+    let mut tar = open_writer(path)?;
+    write_manifest(&mut tar, manifest)?;
+    let mut archived = ArchivedFiles::new();
+    for item in manifest.sessions.iter().flat_map(|session| &session.items) {
+        let original = Path::new(&item.original_path);
+        let recorded = archived.entry(item.original_path.clone()).or_default();
+        for file_path in item_files(original) {
+            let relative = file_path.strip_prefix(original).unwrap_or(Path::new(""));
+            let name = match normal_components(relative) {
+                Some(parts) if !parts.is_empty() => format!("{}/{}", item.entry, parts.join("/")),
+                _ => item.entry.clone(),
+            };
+            let fingerprint = Fingerprint::of(&file_path);
+            let mut file = fs::File::open(&file_path)
+                .map_err(|e| format!("读取文件失败: {}: {}", file_path.display(), e))?;
+            let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+            let mtime = file_modified_ms(&file_path).unwrap_or_default() / 1000;
+            append_exact(&mut tar, &name, size, &mut file, mtime)
+                .map_err(|e| format!("写入归档失败: {}: {}", file_path.display(), e))?;
+            recorded.push((file_path, fingerprint));
+        }
+    }
+    finish_writer(tar)?;
+    Ok(archived)
+}
+
+/// 从归档中恢复单个对话到原位置, 并从归档中移除该对话; 归档为空时删除归档
+fn restore_from_archive(path: &Path, source_path: &str) -> Result<SessionMeta, String> {
+    let read_err = |e: io::Error| format!("读取归档失败: {}", e);
+    let mut archive = open_archive(path)?;
+    let mut entries = archive.entries().map_err(read_err)?;
+    let mut manifest = read_manifest(&mut entries)?;
+    let index = manifest
+        .sessions
+        .iter()
+        .position(|session| session.meta.source_path == source_path)
+        .ok_or_else(|| format!("归档中不存在该对话: {}", source_path))?;
+    let session = manifest.sessions.remove(index);
+    if let Some(item) = session
+        .items
+        .iter()
+        .find(|item| Path::new(&item.original_path).exists())
+    {
+        return Err(format!("原位置已存在同名文件: {}", item.original_path));
+    }
+
+    // 其余对话写入新归档, 完成后替换原归档
+    let partial = path.with_extension("zst.partial");
+    let result = (|| {
+        let mut rest = if manifest.sessions.is_empty() {
+            None
+        } else {
+            let mut tar = open_writer(&partial)?;
+            write_manifest(&mut tar, &manifest)?;
+            Some(tar)
+        };
+        while let Some((mut entry, name)) = next_file(&mut entries).map_err(read_err)? {
+            let mtime = entry.header().mtime().unwrap_or_default();
+            if let Some((item, relative)) = session.locate(&name) {
+                let target = join_relative(Path::new(&item.original_path), &relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+                }
+                let mut file = fs::File::create(&target)
+                    .map_err(|e| format!("恢复失败: {}: {}", target.display(), e))?;
+                io::copy(&mut entry, &mut file).map_err(read_err)?;
+                let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime));
+            } else if let Some(tar) = rest.as_mut() {
+                let size = entry.size();
+                append_exact(tar, &name, size, &mut entry, mtime)
+                    .map_err(|e| format!("写入归档失败: {}", e))?;
+            }
+        }
+        match rest {
+            Some(tar) => {
+                finish_writer(tar)?;
+                fs::rename(&partial, path).map_err(|e| format!("写入归档失败: {}", e))
+            }
+            None => fs::remove_file(path).map_err(|e| format!("删除归档失败: {}", e)),
+        }
+    })();
+
+    if let Err(err) = result {
+        // 恢复前已确认原路径均不存在, 可直接删除已写出的文件
+        for item in &session.items {
+            let _ = remove_path(Path::new(&item.original_path));
+        }
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+
+    for item in &session.items {
+        audit::track_file(Path::new(&item.original_path));
+    }
+    session.reattach()?;
+    Ok(session.meta)
+}
+
+/// 将归档中的对话解压到临时目录并加载消息, 加载后删除临时文件
+fn load_from_archive(path: &Path, source_path: &str) -> Result<Vec<SessionMessage>, String> {
+    let read_err = |e: io::Error| format!("读取归档失败: {}", e);
+    let mut archive = open_archive(path)?;
+    let mut entries = archive.entries().map_err(read_err)?;
+    let manifest = read_manifest(&mut entries)?;
+    let session = manifest
+        .sessions
+        .iter()
+        .find(|session| session.meta.source_path == source_path)
+        .ok_or_else(|| format!("归档中不存在该对话: {}", source_path))?;
+
+    let provider = find_provider(&session.meta.provider_id)?;
+
+    // 数据源在临时目录中的位置: 所属条目的路径加上数据源在条目内的相对路径
+    let temp_root = std::env::temp_dir().join(format!(
+        "anti-power-archive-{}-{}",
+        std::process::id(),
+        now_ms()
+    ));
+    let temp_source = session.items.iter().find_map(|item| {
+        let relative = Path::new(source_path)
+            .strip_prefix(&item.original_path)
+            .ok()?;
+        Some(join_relative(&temp_root.join(&item.entry), relative))
+    });
+    let Some(temp_source) = temp_source else {
+        // 数据源不是文件 (例如数据库中的对话), 消息在清单的 detached 中
+        let detached = session
+            .detached
+            .as_ref()
+            .ok_or("归档清单中缺少对话数据源")?;
+        return provider.load_detached(detached);
+    };
+
+    let result = (|| {
+        while let Some((mut entry, name)) = next_file(&mut entries).map_err(read_err)? {
+            let Some((item, relative)) = session.locate(&name) else {
+                continue;
+            };
+            let target = join_relative(&temp_root.join(&item.entry), &relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+            }
+            let mut file = fs::File::create(&target).map_err(|e| format!("解压归档失败: {}", e))?;
+            io::copy(&mut entry, &mut file).map_err(read_err)?;
+        }
+        provider.load_messages(&temp_source)
+    })();
+
+    let _ = fs::remove_dir_all(&temp_root);
+    result
+}
+
+// Tauri 命令
+
+/// 将选中的对话打包为 tar.zst 归档并删除原文件, 返回新归档的信息
+#[tauri::command]
+pub fn archive_sessions(sessions: Vec<ArchiveSessionRef>) -> Result<ArchiveInfo, String> {
+    let audit = AuditRecord::new("archive_sessions").with_config(serde_json::json!({
+        "sourcePaths": sessions.iter().map(|s| &s.source_path).collect::<Vec<_>>(),
+    }));
+
+    let result = sessions
+        .iter()
+        .map(|session| {
+            let provider = find_provider(&session.provider_id)?;
+            cached_meta(provider, &session.source_path)
+                .ok_or_else(|| format!("文件不存在: {}", session.source_path))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .and_then(|metas| create_archive(&metas));
+
+    audit.finish_with_message(result)
+}
+
+/// 列出归档目录下的归档, 按创建时间倒序; 无法读取的文件会被跳过
+#[tauri::command]
+pub fn list_archives() -> Result<Vec<ArchiveInfo>, String> {
+    let Ok(entries) = fs::read_dir(get_archive_dir()) else {
+        return Ok(Vec::new());
+    };
+    let mut archives: Vec<ArchiveInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(ARCHIVE_EXTENSION))
+        .filter_map(|path| {
+            let manifest = archive_manifest(&path).ok()?;
+            Some(archive_info(&path, manifest))
+        })
+        .collect();
+    archives.sort_by_key(|archive| std::cmp::Reverse(archive.created_at));
+    Ok(archives)
+}
+
+/// 只读加载归档中对话的全部消息
+#[tauri::command]
+pub fn load_archived_session(
+    archive_id: String,
+    source_path: String,
+) -> Result<Vec<SessionMessage>, String> {
+    load_from_archive(&find_archive(&archive_id)?, &source_path)
+}
+
+/// 将归档中的对话恢复到原位置, 任一原路径已被占用时不做任何改动
+#[tauri::command]
+pub fn restore_archived_session(
+    archive_id: String,
+    source_path: String,
+) -> Result<SessionMeta, String> {
+    let audit = AuditRecord::new("restore_archived_session").with_config(serde_json::json!({
+        "archiveId": &archive_id,
+        "sourcePath": &source_path,
+    }));
+
+    let result =
+        find_archive(&archive_id).and_then(|path| restore_from_archive(&path, &source_path));
+
+    audit.finish_with_message(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("anti-power-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn meta(source_path: &Path) -> SessionMeta {
+        SessionMeta {
+            provider_id: "claude".to_string(),
+            session_id: "s1".to_string(),
+            title: None,
+            summary: None,
+            project_dir: None,
+            project_name: None,
+            created_at: None,
+            last_active_at: None,
+            source_path: source_path.to_string_lossy().to_string(),
+            message_count: None,
+            usage: None,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn tar_round_trip_keeps_long_names_and_mtime() {
+        let long_name = format!("files/claude/home/{}/s1.jsonl", "x".repeat(150));
+        let mut tar = tar::Builder::new(Vec::new());
+        append_exact(&mut tar, "short.txt", 5, &mut &b"hello"[..], 1_700_000_000).unwrap();
+        append_exact(&mut tar, &long_name, 3, &mut &b"abc"[..], 42).unwrap();
+        // 超出 size 的数据不写入
+        append_exact(&mut tar, "grown.txt", 2, &mut &b"grown"[..], 0).unwrap();
+        let bytes = tar.into_inner().unwrap();
+
+        let mut archive = tar::Archive::new(&bytes[..]);
+        let entries: Vec<(String, Vec<u8>, u64)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().to_string();
+                let mtime = entry.header().mtime().unwrap();
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                (name, data, mtime)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("short.txt".to_string(), b"hello".to_vec(), 1_700_000_000),
+                (long_name, b"abc".to_vec(), 42),
+                ("grown.txt".to_string(), b"gr".to_vec(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_file_fails_instead_of_padding() {
+        let mut tar = tar::Builder::new(Vec::new());
+        let err = append_exact(&mut tar, "short.txt", 10, &mut &b"hello"[..], 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn archive_round_trip_with_manifest_and_directories() {
+        let dir = temp_dir("archive-round-trip");
+        let source = dir.join("s1.jsonl");
+        let agents = dir.join("s1");
+        fs::write(&source, "{\"type\":\"user\"}\n").unwrap();
+        fs::create_dir_all(agents.join("subagents")).unwrap();
+        fs::write(agents.join("subagents").join("agent-a.jsonl"), "{}\n").unwrap();
+
+        let item = |path: &Path, entry: &str| ArchivedItem {
+            original_path: path.to_string_lossy().to_string(),
+            entry: entry.to_string(),
+        };
+        let manifest = ArchiveManifest {
+            version: MANIFEST_VERSION,
+            created_at: 1_700_000_000_000,
+            sessions: vec![ArchivedSession {
+                meta: meta(&source),
+                items: vec![
+                    item(&source, "files/claude/home/s1.jsonl"),
+                    item(&agents, "files/claude/home/s1"),
+                ],
+                detached: None,
+            }],
+        };
+        let path = dir.join("sessions.tar.zst");
+        let files = write_new_archive(&path, &manifest).unwrap();
+        assert_eq!(files[&source.to_string_lossy().to_string()].len(), 1);
+        assert_eq!(files[&agents.to_string_lossy().to_string()].len(), 1);
+
+        let mut archive = open_archive(&path).unwrap();
+        let mut entries = archive.entries().unwrap();
+        let read = read_manifest(&mut entries).unwrap();
+        assert_eq!(
+            read.sessions[0].meta.source_path,
+            manifest.sessions[0].meta.source_path
+        );
+        let mut names = Vec::new();
+        while let Some((mut entry, name)) = next_file(&mut entries).unwrap() {
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            let (item, relative) = read.sessions[0].locate(&name).unwrap();
+            let original = join_relative(Path::new(&item.original_path), &relative);
+            assert_eq!(fs::read_to_string(original).unwrap(), data);
+            names.push(name);
+        }
+        assert_eq!(
+            names,
+            vec![
+                "files/claude/home/s1.jsonl",
+                "files/claude/home/s1/subagents/agent-a.jsonl"
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
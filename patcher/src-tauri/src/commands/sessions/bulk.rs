@@ -0,0 +1,331 @@
+//! 按筛选条件批量处理对话
+//!
+//! 介于删除单个对话与整体清理之间: 先以 dry_run 预览匹配的对话及其占用空间,
+//! 确认后对同一批对话执行删除、归档或导出, 并逐个返回结果
+
+use super::archive::create_archive;
+use super::export::{export_to, ExportFormat};
+use super::provider::find_provider;
+use super::query::{matching_sessions, SessionQuery};
+use super::trash;
+use super::util::path_size;
+use super::SessionMeta;
+use crate::commands::audit::AuditRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 批量操作请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActionRequest {
+    /// 筛选条件, 忽略其中的分页参数
+    #[serde(default)]
+    pub query: SessionQuery,
+    /// 对话全部文件的总大小范围 (字节, 含)
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    pub action: BulkAction,
+    /// 为 true 时只返回匹配结果, 不执行操作
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 预览时确认的对话, 执行时必须提供; 只处理其中仍满足条件的对话,
+    /// 避免预览之后新出现的对话被一并处理
+    #[serde(default)]
+    pub source_paths: Option<Vec<String>>,
+}
+
+/// 批量操作类型
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BulkAction {
+    /// 移入回收站
+    Delete,
+    /// 打包为一个归档并删除原文件
+    Archive,
+    /// 导出到目录, archive 为 true 时 output_path 为 tar 文件路径
+    #[serde(rename_all = "camelCase")]
+    Export {
+        format: ExportFormat,
+        output_path: String,
+        #[serde(default)]
+        archive: bool,
+    },
+}
+
+impl BulkAction {
+    fn name(&self) -> &'static str {
+        match self {
+            BulkAction::Delete => "delete",
+            BulkAction::Archive => "archive",
+            BulkAction::Export { .. } => "export",
+        }
+    }
+}
+
+/// 匹配的对话及其占用空间
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkMatch {
+    #[serde(flatten)]
+    pub meta: SessionMeta,
+    pub size: u64,
+}
+
+/// 单个对话的执行结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkItemResult {
+    pub provider_id: String,
+    pub session_id: String,
+    pub source_path: String,
+    /// 删除时为回收站 id, 归档时为归档 id, 导出时为导出的文件名
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 批量操作结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActionReport {
+    pub matched: Vec<BulkMatch>,
+    pub total_size: u64,
+    /// dry_run 时为空
+    pub results: Vec<BulkItemResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// 对话的全部文件 (含子代理记录、分片目录等) 的总大小
+fn session_size(meta: &SessionMeta) -> u64 {
+    find_provider(&meta.provider_id)
+        .and_then(|provider| provider.session_paths(Path::new(&meta.source_path), &meta.session_id))
+        .map(|paths| paths.iter().map(|path| path_size(path)).sum())
+        .unwrap_or_default()
+}
+
+/// 执行时确认的对话, 预览时为 None (不限制)
+fn selected_paths(request: &BulkActionRequest) -> Result<Option<HashSet<&str>>, String> {
+    match &request.source_paths {
+        Some(paths) => Ok(Some(paths.iter().map(String::as_str).collect())),
+        None if request.dry_run => Ok(None),
+        None => Err("执行批量操作前需要先预览并确认对话 (source_paths)".to_string()),
+    }
+}
+
+/// 按确认的对话与大小范围筛选查询结果
+fn select_matches(
+    sessions: Vec<SessionMeta>,
+    selected: Option<&HashSet<&str>>,
+    request: &BulkActionRequest,
+    size_of: impl Fn(&SessionMeta) -> u64,
+) -> Vec<BulkMatch> {
+    sessions
+        .into_iter()
+        .filter(|meta| selected.is_none_or(|selected| selected.contains(meta.source_path.as_str())))
+        .map(|meta| {
+            let size = size_of(&meta);
+            BulkMatch { meta, size }
+        })
+        .filter(|matched| {
+            request.min_size.is_none_or(|min| matched.size >= min)
+                && request.max_size.is_none_or(|max| matched.size <= max)
+        })
+        .collect()
+}
+
+/// 预览时返回全部匹配的对话; 执行时只返回 source_paths 中仍满足条件的对话
+fn find_matches(request: &BulkActionRequest) -> Result<Vec<BulkMatch>, String> {
+    let selected = selected_paths(request)?;
+    Ok(select_matches(
+        matching_sessions(&request.query)?,
+        selected.as_ref(),
+        request,
+        session_size,
+    ))
+}
+
+fn run_action(action: &BulkAction, matched: &[BulkMatch]) -> Result<Vec<BulkItemResult>, String> {
+    // 确认的对话都已不满足条件时没有可处理的对话, 不创建空归档或导出文件
+    if matched.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let outputs: Vec<Result<String, String>> = match action {
+        BulkAction::Delete => matched
+            .iter()
+            .map(|matched| {
+                let meta = &matched.meta;
+                find_provider(&meta.provider_id).and_then(|provider| {
+                    trash::move_to_trash(provider, Path::new(&meta.source_path), &meta.session_id)
+                        .map(|entry| entry.id)
+                })
+            })
+            .collect(),
+        // 所有对话写入同一个归档, 任一对话失败时整体失败
+        BulkAction::Archive => {
+            let sessions: Vec<SessionMeta> =
+                matched.iter().map(|matched| matched.meta.clone()).collect();
+            let archive = create_archive(&sessions)?;
+            sessions
+                .iter()
+                .map(|meta| {
+                    if archive.kept.contains(&meta.source_path) {
+                        Err(format!(
+                            "对话在写入归档后有新内容, 已保留原文件 (归档 {} 中为修改前的副本)",
+                            archive.id
+                        ))
+                    } else {
+                        Ok(archive.id.clone())
+                    }
+                })
+                .collect()
+        }
+        BulkAction::Export {
+            format,
+            output_path,
+            archive,
+        } => {
+            let sessions: Vec<SessionMeta> =
+                matched.iter().map(|matched| matched.meta.clone()).collect();
+            export_to(&sessions, *format, Path::new(output_path), *archive)?
+        }
+    };
+
+    Ok(matched
+        .iter()
+        .zip(outputs)
+        .map(|(matched, output)| {
+            let (output, error) = match output {
+                Ok(output) => (Some(output), None),
+                Err(error) => (None, Some(error)),
+            };
+            BulkItemResult {
+                provider_id: matched.meta.provider_id.clone(),
+                session_id: matched.meta.session_id.clone(),
+                source_path: matched.meta.source_path.clone(),
+                output,
+                error,
+            }
+        })
+        .collect())
+}
+
+// Tauri 命令
+
+/// 按筛选条件批量删除、归档或导出对话
+/// dry_run 为 true 时只返回匹配的对话与总大小; 否则只处理预览时确认的 source_paths,
+/// 逐个执行, 单个对话失败不影响其余对话
+#[tauri::command]
+pub fn bulk_session_action(request: BulkActionRequest) -> Result<BulkActionReport, String> {
+    let matched = find_matches(&request)?;
+    let total_size = matched.iter().map(|matched| matched.size).sum();
+    if request.dry_run {
+        return Ok(BulkActionReport {
+            matched,
+            total_size,
+            results: Vec::new(),
+            succeeded: 0,
+            failed: 0,
+        });
+    }
+
+    let audit = AuditRecord::new("bulk_session_action").with_config(serde_json::json!({
+        "action": request.action.name(),
+        "sessions": matched.len(),
+        "totalSize": total_size,
+    }));
+
+    let results = run_action(&request.action, &matched);
+
+    let results = audit.finish_with_message(results)?;
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    Ok(BulkActionReport {
+        succeeded: results.len() - failed,
+        failed,
+        matched,
+        total_size,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(dry_run: bool, source_paths: Option<Vec<&str>>) -> BulkActionRequest {
+        BulkActionRequest {
+            query: SessionQuery::default(),
+            min_size: None,
+            max_size: None,
+            action: BulkAction::Archive,
+            dry_run,
+            source_paths: source_paths.map(|paths| paths.into_iter().map(str::to_string).collect()),
+        }
+    }
+
+    fn meta(source_path: &str) -> SessionMeta {
+        SessionMeta {
+            provider_id: "codex".to_string(),
+            session_id: source_path.to_string(),
+            title: None,
+            summary: None,
+            project_dir: None,
+            project_name: None,
+            created_at: None,
+            last_active_at: None,
+            source_path: source_path.to_string(),
+            message_count: None,
+            usage: None,
+            project: None,
+        }
+    }
+
+    fn size_of(meta: &SessionMeta) -> u64 {
+        meta.source_path.len() as u64 * 10
+    }
+
+    #[test]
+    fn execution_requires_confirmed_source_paths() {
+        assert!(selected_paths(&request(false, None)).is_err());
+        assert_eq!(selected_paths(&request(true, None)), Ok(None));
+        let confirmed = request(false, Some(vec!["a"]));
+        assert_eq!(selected_paths(&confirmed), Ok(Some(HashSet::from(["a"]))));
+    }
+
+    #[test]
+    fn select_matches_keeps_confirmed_sessions_within_size_range() {
+        let sessions = || vec![meta("a"), meta("bb"), meta("ccc"), meta("new")];
+
+        let preview = select_matches(sessions(), None, &request(true, None), size_of);
+        assert_eq!(preview.len(), 4);
+
+        let confirmed = request(false, Some(vec!["a", "bb", "ccc"]));
+        let selected = selected_paths(&confirmed).unwrap();
+        let mut ranged = request(false, None);
+        ranged.min_size = Some(20);
+        let matched = select_matches(sessions(), selected.as_ref(), &ranged, size_of);
+        let paths: Vec<&str> = matched
+            .iter()
+            .map(|matched| matched.meta.source_path.as_str())
+            .collect();
+        // 预览后新出现的 new 不在确认范围内, a 小于下限
+        assert_eq!(paths, ["bb", "ccc"]);
+        assert_eq!(matched[1].size, 30);
+
+        ranged.max_size = Some(20);
+        let matched = select_matches(sessions(), selected.as_ref(), &ranged, size_of);
+        assert_eq!(matched.len(), 1);
+    }
+
+    #[test]
+    fn actions_without_matches_return_empty_results() {
+        assert!(run_action(&BulkAction::Archive, &[]).unwrap().is_empty());
+        assert!(run_action(&BulkAction::Delete, &[]).unwrap().is_empty());
+    }
+}
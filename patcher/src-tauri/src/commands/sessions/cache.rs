@@ -0,0 +1,361 @@
+//! 对话元数据缓存
+//!
+//! 以数据源路径为键缓存解析出的 SessionMeta, 文件修改时间与大小不变时直接复用,
+//! 避免每次打开对话列表都重新读取全部记录文件
+
+use super::provider::SessionProvider;
+use super::scan::{ScanControl, ScanProgress, ScanReporter};
+use super::util::Fingerprint;
+use super::SessionMeta;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// 缓存格式版本, 元数据解析逻辑变化时递增以丢弃旧缓存
+const CACHE_VERSION: u32 = 8;
+const CACHE_FILE_NAME: &str = "session-meta-cache.json";
+
+/// 已加载的缓存, 首次扫描时从磁盘读取
+static CACHE: Mutex<Option<MetaCache>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetaCache {
+    version: u32,
+    /// provider id -> 数据源路径 -> 缓存项
+    providers: HashMap<String, HashMap<String, CachedMeta>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedMeta {
+    fingerprint: Fingerprint,
+    /// 无法解析的数据源同样缓存, 避免反复读取
+    meta: Option<SessionMeta>,
+}
+
+impl MetaCache {
+    fn empty() -> Self {
+        MetaCache {
+            version: CACHE_VERSION,
+            providers: HashMap::new(),
+        }
+    }
+
+    fn load() -> Self {
+        fs::read_to_string(get_cache_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<MetaCache>(&content).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_else(MetaCache::empty)
+    }
+
+    /// 先写临时文件再替换, 避免中断时留下损坏的缓存
+    fn save(&self) -> Result<(), String> {
+        let path = get_cache_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建缓存目录失败: {}", e))?;
+        }
+        let content = serde_json::to_string(self).map_err(|e| format!("序列化缓存失败: {}", e))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content).map_err(|e| format!("写入缓存失败: {}", e))?;
+        fs::rename(&temp, &path).map_err(|e| format!("写入缓存失败: {}", e))
+    }
+}
+
+fn get_cache_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join(CACHE_FILE_NAME)
+}
+
+/// 查找单个数据源的元数据, 不触发扫描: 优先使用缓存, 缓存中没有时重新解析;
+/// 与扫描结果一致, 以保存在数据源之外的标题覆盖解析出的标题
+pub fn cached_meta(provider: &dyn SessionProvider, source_path: &str) -> Option<SessionMeta> {
+    let cached = {
+        let mut guard = CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let cache = guard.get_or_insert_with(MetaCache::load);
+        cache
+            .providers
+            .get(provider.id())
+            .and_then(|entries| entries.get(source_path))
+            .and_then(|cached| cached.meta.clone())
+    };
+    let meta = cached.or_else(|| provider.read_meta(Path::new(source_path)))?;
+    let titles = dirs::home_dir()
+        .map(|home| provider.external_titles(&home))
+        .unwrap_or_default();
+    Some(with_external_title(meta, &titles))
+}
+
+/// 以保存在数据源之外的标题覆盖解析出的标题
+fn with_external_title(mut meta: SessionMeta, titles: &HashMap<String, String>) -> SessionMeta {
+    if let Some(title) = titles.get(&meta.session_id) {
+        meta.title = Some(title.clone());
+    }
+    meta
+}
+
+/// 刷新 provider 的部分数据源, 用于监视到变化时增量更新: 指纹变化的数据源重新解析,
+/// removed 中的数据源从缓存中移除; 返回各数据源的指纹与元数据 (无法解析时为 None),
+/// 缓存无法保存时返回错误
+pub fn refresh_cached(
+    provider: &dyn SessionProvider,
+    home: &Path,
+    sources: &[PathBuf],
+    removed: &[String],
+) -> Result<Vec<(String, Fingerprint, Option<SessionMeta>)>, String> {
+    let fingerprints: Vec<(String, Fingerprint)> = sources
+        .iter()
+        .map(|source| {
+            (
+                source.to_string_lossy().to_string(),
+                provider.fingerprint(source),
+            )
+        })
+        .collect();
+    let cached: Vec<Option<Option<SessionMeta>>> = {
+        let mut guard = CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entries = guard
+            .get_or_insert_with(MetaCache::load)
+            .providers
+            .get(provider.id());
+        fingerprints
+            .iter()
+            .map(|(key, fingerprint)| {
+                entries
+                    .and_then(|entries| entries.get(key))
+                    .filter(|cached| cached.fingerprint == *fingerprint)
+                    .map(|cached| cached.meta.clone())
+            })
+            .collect()
+    };
+
+    // 解析期间不持有缓存锁
+    let mut parsed = Vec::new();
+    let results: Vec<(String, Fingerprint, Option<SessionMeta>)> = fingerprints
+        .into_iter()
+        .zip(sources)
+        .zip(cached)
+        .map(|(((key, fingerprint), source), cached)| {
+            let meta = cached.unwrap_or_else(|| {
+                let meta = provider.read_meta(source);
+                parsed.push((key.clone(), fingerprint, meta.clone()));
+                meta
+            });
+            (key, fingerprint, meta)
+        })
+        .collect();
+
+    if !parsed.is_empty() || !removed.is_empty() {
+        let mut guard = CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let cache = guard.get_or_insert_with(MetaCache::load);
+        let entries = cache
+            .providers
+            .entry(provider.id().to_string())
+            .or_default();
+        for key in removed {
+            entries.remove(key);
+        }
+        for (key, fingerprint, meta) in parsed {
+            entries.insert(key, CachedMeta { fingerprint, meta });
+        }
+        cache.save()?;
+    }
+
+    let titles = provider.external_titles(home);
+    Ok(results
+        .into_iter()
+        .map(|(key, fingerprint, meta)| {
+            (
+                key,
+                fingerprint,
+                meta.map(|meta| with_external_title(meta, &titles)),
+            )
+        })
+        .collect())
+}
+
+/// 扫描多个 provider 的对话元数据, 只重新解析新增或修改过的数据源
+/// 各 provider 的目录遍历与文件解析在工作线程中并行执行, 进度通过 control 回调分批推送
+/// rebuild 为 true 时丢弃这些 provider 的已有缓存并全部重新解析; 被取消时返回 None,
+/// 缓存无法保存时返回错误 (内存中的缓存已更新)
+pub fn scan_cached(
+    providers: &[&dyn SessionProvider],
+    home: &Path,
+    rebuild: bool,
+    control: &ScanControl,
+) -> Result<Option<Vec<SessionMeta>>, String> {
+    // 扫描期间不持有缓存锁 (进度回调会推送事件), 以快照比较指纹, 结束时合并
+    let previous: Vec<HashMap<String, CachedMeta>> = {
+        let mut guard = CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let cache = guard.get_or_insert_with(MetaCache::load);
+        providers
+            .iter()
+            .map(|provider| match rebuild {
+                true => HashMap::new(),
+                false => cache
+                    .providers
+                    .get(provider.id())
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect()
+    };
+
+    // 1. 并行遍历各 provider 的目录
+    let sources: Vec<Vec<PathBuf>> = thread::scope(|scope| {
+        let handles: Vec<_> = providers
+            .iter()
+            .map(|provider| scope.spawn(move || provider.sources(home)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    let titles: Vec<HashMap<String, String>> = providers
+        .iter()
+        .map(|provider| provider.external_titles(home))
+        .collect();
+    let mut progress: Vec<ScanProgress> = providers
+        .iter()
+        .zip(&sources)
+        .map(|(provider, files)| ScanProgress {
+            provider_id: provider.id().to_string(),
+            files_total: files.len(),
+            files_scanned: 0,
+            sessions_found: 0,
+        })
+        .collect();
+    let mut entries: Vec<HashMap<String, CachedMeta>> =
+        providers.iter().map(|_| HashMap::new()).collect();
+    let mut sessions = Vec::new();
+    let mut reporter = ScanReporter::new(control);
+
+    // 2. 指纹未变化的数据源直接复用缓存, 其余进入待解析队列
+    let mut pending = Vec::new();
+    for (index, provider) in providers.iter().enumerate() {
+        for source in &sources[index] {
+            let key = source.to_string_lossy().to_string();
+            let fingerprint = provider.fingerprint(source);
+            match previous[index]
+                .get(&key)
+                .filter(|cached| cached.fingerprint == fingerprint)
+            {
+                Some(cached) => {
+                    progress[index].files_scanned += 1;
+                    if let Some(meta) = &cached.meta {
+                        let meta = with_external_title(meta.clone(), &titles[index]);
+                        progress[index].sessions_found += 1;
+                        reporter.push(&meta);
+                        sessions.push(meta);
+                    }
+                    entries[index].insert(
+                        key,
+                        CachedMeta {
+                            fingerprint,
+                            meta: cached.meta.clone(),
+                        },
+                    );
+                }
+                None => pending.push((index, source, key, fingerprint)),
+            }
+        }
+    }
+    reporter.flush(&progress);
+
+    // 3. 工作线程并行解析, 结果在当前线程汇总并推送
+    let next = AtomicUsize::new(0);
+    let mut parsed = 0;
+    let workers = thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(pending.len());
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, pending, cancel) = (&next, &pending, control.cancel);
+            scope.spawn(move || {
+                while !cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                    let item = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((index, source, _, _)) = pending.get(item) else {
+                        break;
+                    };
+                    if tx
+                        .send((item, providers[*index].read_meta(source)))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (item, meta) in rx {
+            let (index, _, key, fingerprint) = &pending[item];
+            progress[*index].files_scanned += 1;
+            if let Some(meta) = &meta {
+                let meta = with_external_title(meta.clone(), &titles[*index]);
+                progress[*index].sessions_found += 1;
+                reporter.push(&meta);
+                sessions.push(meta);
+            }
+            entries[*index].insert(
+                key.clone(),
+                CachedMeta {
+                    fingerprint: *fingerprint,
+                    meta,
+                },
+            );
+            parsed += 1;
+            reporter.maybe_flush(&progress);
+        }
+    });
+    reporter.flush(&progress);
+
+    let canceled = control.is_canceled();
+    let mut changed = parsed > 0;
+    let mut guard = CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let cache = guard.get_or_insert_with(MetaCache::load);
+    for ((provider, entries), previous) in providers.iter().zip(entries).zip(&previous) {
+        if canceled {
+            // 保留已解析的结果, 未处理的数据源沿用旧缓存
+            let cached = cache
+                .providers
+                .entry(provider.id().to_string())
+                .or_default();
+            if rebuild {
+                cached.clear();
+            }
+            cached.extend(entries);
+        } else {
+            // 已删除的数据源不会出现在本次扫描中
+            changed |= rebuild || previous.len() != entries.len();
+            cache.providers.insert(provider.id().to_string(), entries);
+        }
+    }
+
+    if changed {
+        cache.save()?;
+    }
+
+    Ok((!canceled).then_some(sessions))
+}
@@ -0,0 +1,895 @@
+//! Claude Code 对话记录 (~/.claude/projects/<项目>/*.jsonl)
+
+use super::provider::{LineParser, ProviderCapabilities, SessionProvider};
+use super::stats::SessionStats;
+use super::usage::{SessionUsage, TokenUsage};
+use super::util::{
+    collect_files_recursive, extract_text, file_modified_ms, lines_with_offsets, parse_timestamp,
+    path_basename, prompt_title, read_head_lines, read_tail_lines, tool_output_text, truncate,
+};
+use super::{
+    AttachmentKind, ContentBlock, MessageBranch, SessionMessage, SessionMeta, SessionTree,
+    SubagentSession,
+};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+pub struct ClaudeProvider;
+
+impl SessionProvider for ClaudeProvider {
+    fn id(&self) -> &'static str {
+        "claude"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Claude Code"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        vec![home.join(".claude").join("projects")]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: true,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_claude_sources(root)
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        read_claude_meta(source)
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        parse_claude_messages(source_path)
+    }
+
+    fn line_parser(&self) -> Option<LineParser> {
+        Some(parse_claude_record)
+    }
+
+    fn read_stats(&self, meta: &SessionMeta) -> Option<SessionStats> {
+        let source = Path::new(&meta.source_path);
+        let nodes = read_claude_nodes(source).ok()?;
+        let thread = build_claude_thread(&nodes, &thread_members(&nodes, None));
+        let message_count = thread
+            .main
+            .iter()
+            .filter(|&&i| nodes[i].message.is_some())
+            .count();
+        // 未采用的分支与子代理同样消耗 token, 用量按全部记录统计
+        let mut messages: Vec<SessionMessage> =
+            nodes.into_iter().filter_map(|node| node.message).collect();
+        if !is_agent_file(source) {
+            for file in list_agent_files(source, &meta.session_id) {
+                if let Ok(agent_nodes) = read_claude_nodes(&file) {
+                    messages.extend(agent_nodes.into_iter().filter_map(|node| node.message));
+                }
+            }
+        }
+        Some(SessionStats {
+            message_count,
+            usage: SessionUsage::from_messages(&messages),
+        })
+    }
+
+    fn load_tree(
+        &self,
+        source_path: &Path,
+        sidechain_root: Option<&str>,
+    ) -> Result<SessionTree, String> {
+        load_claude_tree(source_path, sidechain_root)
+    }
+
+    fn resume_command(&self, meta: &SessionMeta) -> Option<Vec<String>> {
+        Some(vec![
+            "claude".to_string(),
+            "--resume".to_string(),
+            meta.session_id.clone(),
+        ])
+    }
+
+    fn session_paths(&self, source_path: &Path, session_id: &str) -> Result<Vec<PathBuf>, String> {
+        let mut paths = vec![source_path.to_path_buf()];
+        if is_agent_file(source_path) {
+            return Ok(paths);
+        }
+        // 子代理记录随主对话一起删除; <会话 id>/ 目录下的子代理已包含在目录中
+        let session_dir = source_path.with_extension("");
+        paths.extend(
+            list_agent_files(source_path, session_id)
+                .into_iter()
+                .filter(|file| !file.starts_with(&session_dir)),
+        );
+        if session_dir.is_dir() {
+            paths.push(session_dir);
+        }
+        Ok(paths)
+    }
+}
+
+fn is_agent_file(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| name.starts_with("agent-"))
+}
+
+fn list_claude_sources(projects_dir: &Path) -> Vec<PathBuf> {
+    if !projects_dir.is_dir() {
+        return Vec::new();
+    }
+
+    // agent- 开头的子代理记录挂在主对话下, 不单独列出
+    collect_files_recursive(projects_dir, "jsonl")
+        .into_iter()
+        .filter(|file_path| !is_agent_file(file_path))
+        .collect()
+}
+
+/// 用户输入的文本, 工具结果与 isMeta 消息返回 None
+fn user_prompt_text(obj: &Value) -> Option<String> {
+    if obj.get("type").and_then(|v| v.as_str()) != Some("user")
+        || obj.get("isMeta").and_then(|v| v.as_bool()).unwrap_or(false)
+    {
+        return None;
+    }
+    match obj.get("message")?.get("content")? {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(blocks) => {
+            if blocks
+                .iter()
+                .any(|block| block.get("type").and_then(|v| v.as_str()) == Some("tool_result"))
+            {
+                return None;
+            }
+            let text: Vec<&str> = blocks
+                .iter()
+                .filter(|block| block.get("type").and_then(|v| v.as_str()) == Some("text"))
+                .filter_map(|block| block.get("text").and_then(|v| v.as_str()))
+                .collect();
+            Some(text.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+fn read_claude_meta(file_path: &Path) -> Option<SessionMeta> {
+    // 头部多读一些行, 以跳过命令包装消息找到第一条用户输入
+    let head = read_head_lines(file_path, 50);
+    let tail = read_tail_lines(file_path, 30);
+
+    let mut session_id = None;
+    let mut cwd = None;
+    let mut created_at = None;
+    let mut first_prompt = None;
+
+    // 从头部提取元数据
+    for line in &head {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            if session_id.is_none() {
+                session_id = obj
+                    .get("sessionId")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+            }
+            if cwd.is_none() {
+                cwd = obj
+                    .get("cwd")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+            }
+            if created_at.is_none() {
+                created_at = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+            }
+            if first_prompt.is_none() {
+                first_prompt = user_prompt_text(&obj).and_then(|text| prompt_title(&text));
+            }
+        }
+    }
+
+    // 标题记录: /rename 写入的 custom-title 优先, 其次是自动生成的 summary, 均以最后一条为准
+    let title_record = |kind: &str, field: &str| {
+        head.iter().chain(&tail).rev().find_map(|line| {
+            let obj = serde_json::from_str::<Value>(line).ok()?;
+            if obj.get("type").and_then(|v| v.as_str()) != Some(kind) {
+                return None;
+            }
+            obj.get(field)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|title| !title.is_empty())
+                .map(|title| title.to_string())
+        })
+    };
+    let native_title = title_record("custom-title", "customTitle")
+        .or_else(|| title_record("summary", "summary"))
+        .or(first_prompt);
+
+    // 从尾部提取 last_active_at 和 summary
+    let mut last_active_at = None;
+    let mut summary = None;
+    for line in tail.iter().rev() {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            if obj.get("isMeta").and_then(|v| v.as_bool()).unwrap_or(false) {
+                continue;
+            }
+            if last_active_at.is_none() {
+                last_active_at = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+            }
+            if summary.is_none() {
+                if let Some(msg) = obj.get("message") {
+                    let text = extract_text(msg.get("content").unwrap_or(&Value::Null));
+                    if !text.is_empty() {
+                        summary = Some(truncate(&text, 160));
+                    }
+                }
+            }
+            if last_active_at.is_some() && summary.is_some() {
+                break;
+            }
+        }
+    }
+
+    // fallback: 使用文件修改时间
+    if last_active_at.is_none() {
+        last_active_at = file_modified_ms(file_path);
+    }
+
+    let sid = session_id.unwrap_or_else(|| {
+        file_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+    // 记录中没有工作目录时, 由所在的项目目录名还原
+    let cwd = cwd.or_else(|| {
+        let project = file_path.parent()?;
+        if project.parent()?.file_name()? != "projects" {
+            return None;
+        }
+        decode_project_dir(project.file_name()?.to_str()?)
+            .map(|dir| dir.to_string_lossy().to_string())
+    });
+    let project_name = cwd.as_deref().and_then(path_basename);
+
+    Some(SessionMeta {
+        provider_id: "claude".to_string(),
+        session_id: sid,
+        title: native_title.or_else(|| project_name.clone()),
+        summary,
+        project_dir: cwd,
+        project_name,
+        created_at,
+        last_active_at,
+        source_path: file_path.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+/// Claude 存放对话的目录名: 工作目录中字母与数字以外的字符都替换为 '-'
+fn encode_project_dir(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// 还原 projects/ 下的目录名; 编码不可逆, 因此从根目录起逐级匹配现有的目录,
+/// 目录已不存在时返回 None
+fn decode_project_dir(encoded: &str) -> Option<PathBuf> {
+    fn descend(dir: &Path, rest: &str) -> Option<PathBuf> {
+        if rest.is_empty() {
+            return Some(dir.to_path_buf());
+        }
+        let mut children: Vec<PathBuf> = fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        children.sort();
+        children.into_iter().find_map(|child| {
+            let name = encode_project_dir(&child.file_name()?.to_string_lossy());
+            let rest = rest.strip_prefix(name.as_str())?;
+            match rest.strip_prefix('-') {
+                Some(rest) => descend(&child, rest),
+                None if rest.is_empty() => Some(child),
+                None => None,
+            }
+        })
+    }
+
+    // Windows 的 C:\ 编码为 C--
+    let bytes = encoded.as_bytes();
+    if cfg!(windows) && bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &encoded[1..3] == "--"
+    {
+        return descend(Path::new(&format!("{}:\\", &encoded[..1])), &encoded[3..]);
+    }
+    descend(Path::new("/"), encoded.strip_prefix('-')?)
+}
+
+/// 记录中带 uuid 的一行, 保留对话树结构
+struct ClaudeNode {
+    uuid: Option<String>,
+    parent: Option<String>,
+    sidechain: bool,
+    /// isMeta 等不展示的行为 None, 但仍参与父子链接
+    message: Option<SessionMessage>,
+    /// Task 工具结果中记录的 (子代理 id, 工具调用 id)
+    agent_result: Option<(String, String)>,
+}
+
+/// 读取记录的全部行, 按文件顺序返回
+fn read_claude_nodes(path: &Path) -> Result<Vec<ClaudeNode>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(file);
+    let mut nodes = Vec::new();
+    // 同一条 API 响应按内容块拆成多行, 每行携带相同的 usage, 只计入第一行
+    let mut counted_ids = HashSet::new();
+
+    for line in lines_with_offsets(reader) {
+        let (offset, line) = line.map_err(|e| e.to_string())?;
+        let Ok(obj) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let str_field = |key: &str| obj.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let uuid = str_field("uuid");
+        let message = parse_claude_line(&obj, &mut counted_ids).map(|mut message| {
+            message.id = uuid.clone();
+            message.line_offset = Some(offset);
+            message
+        });
+        // summary、file-history-snapshot 等行既无 uuid 也无消息
+        if uuid.is_none() && message.is_none() {
+            continue;
+        }
+
+        let agent_result = obj
+            .get("toolUseResult")
+            .and_then(|result| result.get("agentId"))
+            .and_then(|v| v.as_str())
+            .zip(message.as_ref().and_then(|message| {
+                message.blocks.iter().find_map(|block| match block {
+                    ContentBlock::ToolResult { call_id, .. } => call_id.as_deref(),
+                    _ => None,
+                })
+            }))
+            .map(|(agent_id, call_id)| (agent_id.to_string(), call_id.to_string()));
+
+        nodes.push(ClaudeNode {
+            // 上下文压缩后的第一行 parentUuid 为空, 由 logicalParentUuid 接续压缩前的历史
+            parent: str_field("parentUuid").or_else(|| str_field("logicalParentUuid")),
+            sidechain: obj
+                .get("isSidechain")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            uuid,
+            message,
+            agent_result,
+        });
+    }
+
+    Ok(nodes)
+}
+
+/// 单独解析一行, 用量不去重, 由分页索引补全
+fn parse_claude_record(line: &str) -> Option<SessionMessage> {
+    let obj = serde_json::from_str::<Value>(line).ok()?;
+    let mut message = parse_claude_line(&obj, &mut HashSet::new())?;
+    message.id = obj
+        .get("uuid")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    Some(message)
+}
+
+fn parse_claude_line(obj: &Value, counted_ids: &mut HashSet<String>) -> Option<SessionMessage> {
+    if obj.get("isMeta").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return None;
+    }
+    let ts = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+
+    // 系统行 (上下文压缩、hook 输出等) 没有 message 字段
+    if obj.get("type").and_then(|v| v.as_str()) == Some("system") {
+        let text = obj
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let blocks = vec![ContentBlock::SystemEvent { text }];
+        return SessionMessage::from_blocks("system".to_string(), ts, blocks);
+    }
+
+    let msg = obj.get("message")?;
+    let blocks = parse_claude_blocks(msg.get("content").unwrap_or(&Value::Null));
+    // 只包含工具结果的 user 消息实际由工具产生
+    let role = if !blocks.is_empty()
+        && blocks
+            .iter()
+            .all(|b| matches!(b, ContentBlock::ToolResult { .. }))
+    {
+        "tool".to_string()
+    } else {
+        msg.get("role")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string()
+    };
+    let mut message = SessionMessage::from_blocks(role, ts, blocks)?;
+    message.model = msg
+        .get("model")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let first_line = msg
+        .get("id")
+        .and_then(|v| v.as_str())
+        .is_none_or(|id| counted_ids.insert(id.to_string()));
+    if first_line {
+        message.usage = msg.get("usage").map(parse_claude_usage);
+    }
+    Some(message)
+}
+
+/// 一条对话线: 主线与编辑/重试产生的分支, 均为 nodes 下标
+struct ClaudeThread {
+    main: Vec<usize>,
+    /// (分叉处的节点, 分支上的节点)
+    branches: Vec<(Option<usize>, Vec<usize>)>,
+}
+
+/// 按 parentUuid 重建对话线
+/// 与 CLI 恢复对话的方式一致: 从最后写入的一行沿父链回溯得到主线,
+/// 其余节点各自沿最后写入的后代组成分支
+fn build_claude_thread(nodes: &[ClaudeNode], members: &[usize]) -> ClaudeThread {
+    // 旧版记录没有 uuid, 只能按文件顺序展示
+    if members.iter().all(|&i| nodes[i].uuid.is_none()) {
+        return ClaudeThread {
+            main: members.to_vec(),
+            branches: Vec::new(),
+        };
+    }
+
+    let index: HashMap<&str, usize> = members
+        .iter()
+        .filter_map(|&i| nodes[i].uuid.as_deref().map(|uuid| (uuid, i)))
+        .collect();
+    let parent_of = |i: usize| {
+        nodes[i]
+            .parent
+            .as_deref()
+            .and_then(|parent| index.get(parent))
+            .copied()
+    };
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &i in members {
+        if let Some(parent) = parent_of(i) {
+            children.entry(parent).or_default().push(i);
+        }
+    }
+
+    // 从 leaf 回溯到 stop (不含) 或根, 返回正序路径
+    let path_to = |leaf: usize, stop: Option<usize>| {
+        let mut path = vec![leaf];
+        let mut seen = HashSet::from([leaf]);
+        let mut current = leaf;
+        while let Some(parent) = parent_of(current) {
+            if Some(parent) == stop || !seen.insert(parent) {
+                break;
+            }
+            path.push(parent);
+            current = parent;
+        }
+        path.reverse();
+        path
+    };
+
+    let Some(&last) = members.last() else {
+        return ClaudeThread {
+            main: Vec::new(),
+            branches: Vec::new(),
+        };
+    };
+    let main = path_to(last, None);
+    let mut covered: HashSet<usize> = main.iter().copied().collect();
+    let mut branches = Vec::new();
+
+    for &start in members {
+        if covered.contains(&start) {
+            continue;
+        }
+        // 子树中最后写入的节点即该分支的末端
+        let mut leaf = start;
+        let mut stack = vec![start];
+        let mut seen = HashSet::from([start]);
+        while let Some(node) = stack.pop() {
+            leaf = leaf.max(node);
+            for &child in children.get(&node).into_iter().flatten() {
+                if !covered.contains(&child) && seen.insert(child) {
+                    stack.push(child);
+                }
+            }
+        }
+        let fork = parent_of(start);
+        let path = path_to(leaf, fork);
+        covered.extend(path.iter().copied());
+        branches.push((fork, path));
+    }
+
+    ClaudeThread { main, branches }
+}
+
+/// 主对话线的成员: 非 sidechain 的行; 子代理记录全部为 sidechain, 此时取全部
+/// 指定 sidechain_root 时取该节点及其后代
+fn thread_members(nodes: &[ClaudeNode], sidechain_root: Option<&str>) -> Vec<usize> {
+    match sidechain_root {
+        Some(root) => {
+            let mut included: HashSet<&str> = HashSet::from([root]);
+            // 子节点总在父节点之后写入, 顺序遍历一次即可
+            (0..nodes.len())
+                .filter(|&i| {
+                    let node = &nodes[i];
+                    let Some(uuid) = node.uuid.as_deref() else {
+                        return false;
+                    };
+                    let member = uuid == root
+                        || node
+                            .parent
+                            .as_deref()
+                            .is_some_and(|parent| included.contains(parent));
+                    if member {
+                        included.insert(uuid);
+                    }
+                    member
+                })
+                .collect()
+        }
+        None => {
+            let main: Vec<usize> = (0..nodes.len()).filter(|&i| !nodes[i].sidechain).collect();
+            if main.is_empty() {
+                (0..nodes.len()).collect()
+            } else {
+                main
+            }
+        }
+    }
+}
+
+fn thread_messages(nodes: &[ClaudeNode], path: &[usize]) -> Vec<SessionMessage> {
+    path.iter()
+        .filter_map(|&i| nodes[i].message.clone())
+        .collect()
+}
+
+fn parse_claude_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
+    let nodes = read_claude_nodes(path)?;
+    let thread = build_claude_thread(&nodes, &thread_members(&nodes, None));
+    Ok(thread_messages(&nodes, &thread.main))
+}
+
+/// 主线中启动子代理的 Task 工具调用
+struct TaskCall {
+    id: Option<String>,
+    description: Option<String>,
+    prompt: Option<String>,
+    agent_type: Option<String>,
+}
+
+fn collect_task_calls(messages: &[SessionMessage]) -> Vec<TaskCall> {
+    messages
+        .iter()
+        .flat_map(|message| &message.blocks)
+        .filter_map(|block| match block {
+            // 新版 CLI 将 Task 工具更名为 Agent
+            ContentBlock::ToolCall { id, name, input } if name == "Task" || name == "Agent" => {
+                let field = |key: &str| {
+                    input
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string())
+                };
+                Some(TaskCall {
+                    id: id.clone(),
+                    description: field("description"),
+                    prompt: field("prompt"),
+                    agent_type: field("subagent_type"),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// 同一会话的子代理记录: 项目目录下的 agent-*.jsonl, 或新版的 <会话 id>/subagents/ 目录
+fn list_agent_files(source_path: &Path, session_id: &str) -> Vec<PathBuf> {
+    let Some(dir) = source_path.parent() else {
+        return Vec::new();
+    };
+    let stem = source_path.file_stem().unwrap_or_default();
+    let mut files: Vec<PathBuf> = [dir.to_path_buf(), dir.join(stem).join("subagents")]
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl") && is_agent_file(path))
+        .filter(|path| {
+            read_head_lines(path, 5).iter().any(|line| {
+                serde_json::from_str::<Value>(line).is_ok_and(|obj| {
+                    obj.get("sessionId").and_then(|v| v.as_str()) == Some(session_id)
+                })
+            })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// 按提示词匹配 Task 调用, 工具结果未记录子代理 id 时使用
+fn find_task_by_prompt<'a>(tasks: &'a [TaskCall], prompt: &str) -> Option<&'a TaskCall> {
+    let prompt = prompt.trim();
+    tasks
+        .iter()
+        .find(|task| task.prompt.as_deref().is_some_and(|p| p.trim() == prompt))
+}
+
+fn subagent_session(
+    tasks: &[TaskCall],
+    task: Option<&TaskCall>,
+    first_prompt: Option<&str>,
+) -> SubagentSession {
+    let task = task.or_else(|| first_prompt.and_then(|prompt| find_task_by_prompt(tasks, prompt)));
+    SubagentSession {
+        agent_id: None,
+        tool_call_id: task.and_then(|task| task.id.clone()),
+        description: task.and_then(|task| task.description.clone()),
+        agent_type: task.and_then(|task| task.agent_type.clone()),
+        source_path: String::new(),
+        sidechain_root: None,
+        started_at: None,
+        message_count: 0,
+    }
+}
+
+fn load_claude_tree(path: &Path, sidechain_root: Option<&str>) -> Result<SessionTree, String> {
+    let nodes = read_claude_nodes(path)?;
+    let thread = build_claude_thread(&nodes, &thread_members(&nodes, sidechain_root));
+    let messages = thread_messages(&nodes, &thread.main);
+
+    let branches = thread
+        .branches
+        .iter()
+        .filter_map(|(fork, path)| {
+            let messages = thread_messages(&nodes, path);
+            if messages.is_empty() {
+                return None;
+            }
+            // 分叉处可能是不展示的行, 上溯到最近的可见消息
+            let fork_id = std::iter::successors(*fork, |&i| {
+                let parent = nodes[i].parent.as_deref()?;
+                nodes.iter().position(|n| n.uuid.as_deref() == Some(parent))
+            })
+            .find_map(|i| nodes[i].message.as_ref().and_then(|m| m.id.clone()));
+            Some(MessageBranch { fork_id, messages })
+        })
+        .collect();
+
+    // 子代理不会再启动子代理, 只有主对话需要查找
+    let subagents = if sidechain_root.is_some() || is_agent_file(path) {
+        Vec::new()
+    } else {
+        collect_subagents(path, &nodes, &messages)
+    };
+
+    Ok(SessionTree {
+        messages,
+        branches,
+        subagents,
+    })
+}
+
+fn collect_subagents(
+    path: &Path,
+    nodes: &[ClaudeNode],
+    messages: &[SessionMessage],
+) -> Vec<SubagentSession> {
+    let tasks = collect_task_calls(messages);
+    let agent_calls: HashMap<&str, &str> = nodes
+        .iter()
+        .filter_map(|node| node.agent_result.as_ref())
+        .map(|(agent_id, call_id)| (agent_id.as_str(), call_id.as_str()))
+        .collect();
+    let session_id = read_claude_meta(path)
+        .map(|meta| meta.session_id)
+        .unwrap_or_default();
+    let mut subagents = Vec::new();
+
+    for file in list_agent_files(path, &session_id) {
+        let Ok(agent_nodes) = read_claude_nodes(&file) else {
+            continue;
+        };
+        let thread = build_claude_thread(&agent_nodes, &thread_members(&agent_nodes, None));
+        let agent_messages = thread_messages(&agent_nodes, &thread.main);
+        let agent_id = read_head_lines(&file, 5)
+            .iter()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .find_map(|obj| obj.get("agentId")?.as_str().map(|s| s.to_string()))
+            .or_else(|| {
+                file.file_stem()?
+                    .to_str()?
+                    .strip_prefix("agent-")
+                    .map(|s| s.to_string())
+            });
+        let task = agent_id
+            .as_deref()
+            .and_then(|id| agent_calls.get(id))
+            .and_then(|call_id| tasks.iter().find(|t| t.id.as_deref() == Some(call_id)));
+        let first_prompt = agent_messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.as_str());
+
+        let mut subagent = subagent_session(&tasks, task, first_prompt);
+        subagent.agent_id = agent_id;
+        subagent.source_path = file.to_string_lossy().to_string();
+        subagent.started_at = agent_messages.iter().find_map(|m| m.ts);
+        subagent.message_count = agent_messages.len();
+        subagents.push(subagent);
+    }
+
+    // 旧版 CLI 将子代理消息以 sidechain 形式写在主记录中
+    let sidechain_roots = nodes.iter().filter(|node| {
+        node.sidechain
+            && node.parent.as_deref().is_none_or(|parent| {
+                !nodes
+                    .iter()
+                    .any(|n| n.sidechain && n.uuid.as_deref() == Some(parent))
+            })
+    });
+    for root in sidechain_roots {
+        let Some(uuid) = root.uuid.as_deref() else {
+            continue;
+        };
+        let members = thread_members(nodes, Some(uuid));
+        let thread = build_claude_thread(nodes, &members);
+        let agent_messages = thread_messages(nodes, &thread.main);
+        let first_prompt = agent_messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.as_str());
+
+        let mut subagent = subagent_session(&tasks, None, first_prompt);
+        subagent.source_path = path.to_string_lossy().to_string();
+        subagent.sidechain_root = Some(uuid.to_string());
+        subagent.started_at = agent_messages.iter().find_map(|m| m.ts);
+        subagent.message_count = agent_messages.len();
+        subagents.push(subagent);
+    }
+
+    subagents.sort_by_key(|subagent| subagent.started_at);
+    subagents
+}
+
+/// Anthropic API 的 usage 字段, input_tokens 不含缓存部分
+fn parse_claude_usage(usage: &Value) -> TokenUsage {
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    TokenUsage {
+        input: count("input_tokens"),
+        output: count("output_tokens"),
+        cache_read: count("cache_read_input_tokens"),
+        cache_write: count("cache_creation_input_tokens"),
+        reasoning: 0,
+    }
+}
+
+/// 解析 Anthropic 消息格式的 content (字符串或内容块数组)
+fn parse_claude_blocks(content: &Value) -> Vec<ContentBlock> {
+    let items = match content {
+        Value::String(text) => {
+            return vec![ContentBlock::Text { text: text.clone() }];
+        }
+        Value::Array(items) => items,
+        _ => return Vec::new(),
+    };
+
+    let str_field = |item: &Value, key: &str| {
+        item.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let block = match item.get("type").and_then(|v| v.as_str())? {
+                "text" => ContentBlock::Text {
+                    text: str_field(item, "text")?,
+                },
+                "thinking" => ContentBlock::Reasoning {
+                    text: str_field(item, "thinking")?,
+                },
+                "tool_use" | "server_tool_use" => ContentBlock::ToolCall {
+                    id: str_field(item, "id"),
+                    name: str_field(item, "name")?,
+                    input: item.get("input").cloned().unwrap_or(Value::Null),
+                },
+                "tool_result" => ContentBlock::ToolResult {
+                    call_id: str_field(item, "tool_use_id"),
+                    output: tool_output_text(item.get("content").unwrap_or(&Value::Null)),
+                    is_error: item
+                        .get("is_error")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                },
+                kind @ ("image" | "document") => {
+                    let source = item.get("source");
+                    ContentBlock::Attachment {
+                        kind: if kind == "image" {
+                            AttachmentKind::Image
+                        } else {
+                            AttachmentKind::File
+                        },
+                        name: str_field(item, "title"),
+                        media_type: source.and_then(|s| str_field(s, "media_type")),
+                        uri: source.and_then(|s| str_field(s, "url")),
+                    }
+                }
+                _ => return None,
+            };
+            Some(block)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn meta_skips_is_meta_lines_in_tail() {
+        let dir = env::temp_dir().join(format!("anti-power-claude-meta-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("s1.jsonl");
+        let lines = [
+            r#"{"type":"user","uuid":"a","message":{"role":"user","content":"fix the build"},"cwd":"/x/proj","sessionId":"s1","timestamp":"2025-01-01T00:00:00Z"}"#,
+            r#"{"type":"assistant","uuid":"b","parentUuid":"a","message":{"role":"assistant","content":[{"type":"text","text":"done"}]},"timestamp":"2025-01-01T00:00:05Z"}"#,
+            r#"{"type":"user","uuid":"c","parentUuid":"b","isMeta":true,"message":{"role":"user","content":"Caveat: The messages below were generated by the user while running local commands."},"timestamp":"2025-01-01T00:00:09Z"}"#,
+        ];
+        fs::write(&file, lines.join("\n")).unwrap();
+        let meta = read_claude_meta(&file);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let meta = meta.expect("session with isMeta tail line must stay listed");
+        assert_eq!(meta.session_id, "s1");
+        assert_eq!(meta.title.as_deref(), Some("fix the build"));
+        assert_eq!(meta.summary.as_deref(), Some("done"));
+        assert_eq!(meta.last_active_at, Some(1_735_689_605_000));
+    }
+
+    #[test]
+    fn decode_project_dir_matches_existing_directories() {
+        let dir = env::temp_dir().join(format!("anti-power-claude-decode-{}", process::id()));
+        let project = dir.join("my.app").join("sub_dir v2");
+        fs::create_dir_all(&project).unwrap();
+        // 同样是编码前缀的目录排在前面, 匹配失败后需回溯
+        fs::create_dir_all(dir.join("my")).unwrap();
+
+        let encoded = encode_project_dir(&project.to_string_lossy());
+        let decoded = decode_project_dir(&encoded);
+        let missing = decode_project_dir(&format!("{}-gone", encoded));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // . _ 与空格都编码为 -, 逐级匹配后仍还原出原始目录名
+        assert_eq!(encoded.matches("my-app-sub-dir-v2").count(), 1);
+        assert_eq!(decoded, Some(project));
+        assert_eq!(missing, None);
+    }
+}
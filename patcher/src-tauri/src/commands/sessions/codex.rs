@@ -0,0 +1,461 @@
+//! Codex 对话记录 (~/.codex/sessions/**/*.jsonl)
+
+use super::provider::{LineParser, ProviderCapabilities, SessionProvider};
+use super::usage::TokenUsage;
+use super::util::{
+    collect_files_recursive, extract_text, file_modified_ms, lines_with_offsets, parse_timestamp,
+    parse_tool_input, path_basename, prompt_title, read_head_lines, read_tail_lines,
+    tool_output_text, truncate,
+};
+use super::{AttachmentKind, ContentBlock, SessionMessage, SessionMeta};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+pub struct CodexProvider;
+
+impl SessionProvider for CodexProvider {
+    fn id(&self) -> &'static str {
+        "codex"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Codex"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        vec![home.join(".codex").join("sessions")]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: true,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_codex_sources(root)
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        read_codex_meta(source)
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        parse_codex_messages(source_path)
+    }
+
+    fn line_parser(&self) -> Option<LineParser> {
+        Some(parse_codex_record)
+    }
+
+    /// 重命名的对话名称记录在 ~/.codex/session_index.jsonl 中
+    fn external_titles(&self, home: &Path) -> HashMap<String, String> {
+        read_thread_names(&home.join(".codex").join("session_index.jsonl"))
+    }
+
+    fn resume_command(&self, meta: &SessionMeta) -> Option<Vec<String>> {
+        Some(vec![
+            "codex".to_string(),
+            "resume".to_string(),
+            meta.session_id.clone(),
+        ])
+    }
+
+    fn session_paths(&self, source_path: &Path, _session_id: &str) -> Result<Vec<PathBuf>, String> {
+        Ok(vec![source_path.to_path_buf()])
+    }
+}
+
+fn list_codex_sources(sessions_dir: &Path) -> Vec<PathBuf> {
+    if !sessions_dir.is_dir() {
+        return Vec::new();
+    }
+
+    collect_files_recursive(sessions_dir, "jsonl")
+}
+
+/// 读取对话 id 到名称的索引, 同一对话以最后一条为准
+fn read_thread_names(index_path: &Path) -> HashMap<String, String> {
+    let Ok(file) = fs::File::open(index_path) else {
+        return HashMap::new();
+    };
+    let mut names = HashMap::new();
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let Ok(obj) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let id = obj.get("id").and_then(|v| v.as_str());
+        let name = obj
+            .get("thread_name")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        if let (Some(id), Some(name)) = (id, name) {
+            names.insert(id.to_string(), name.to_string());
+        }
+    }
+    names
+}
+
+/// 用户输入的文本, 以及是否来自 user_message 事件;
+/// 事件中不含 CLI 注入的环境信息, 生成标题时优先于 response_item 中的用户消息
+fn user_prompt_text(obj: &Value) -> Option<(String, bool)> {
+    let payload = obj.get("payload")?;
+    let payload_type = payload.get("type").and_then(|v| v.as_str());
+    match obj.get("type").and_then(|v| v.as_str())? {
+        "event_msg" if payload_type == Some("user_message") => payload
+            .get("message")
+            .and_then(|v| v.as_str())
+            .map(|s| (s.to_string(), true)),
+        "response_item"
+            if payload_type == Some("message")
+                && payload.get("role").and_then(|v| v.as_str()) == Some("user") =>
+        {
+            Some((
+                extract_text(payload.get("content").unwrap_or(&Value::Null)),
+                false,
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn read_codex_meta(file_path: &Path) -> Option<SessionMeta> {
+    // 头部多读一些行, 以跳过注入的环境信息找到第一条用户输入
+    let head = read_head_lines(file_path, 50);
+    let tail = read_tail_lines(file_path, 30);
+
+    let mut session_id = None;
+    let mut cwd = None;
+    let mut created_at = None;
+    let mut event_prompt = None;
+    let mut item_prompt = None;
+
+    for line in &head {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            // 分叉的对话含多条 session_meta, 以第一条为准
+            if session_id.is_none()
+                && obj.get("type").and_then(|v| v.as_str()) == Some("session_meta")
+            {
+                if let Some(payload) = obj.get("payload") {
+                    session_id = payload
+                        .get("id")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    cwd = payload
+                        .get("cwd")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    created_at = parse_timestamp(
+                        payload
+                            .get("timestamp")
+                            .or_else(|| obj.get("timestamp"))
+                            .unwrap_or(&Value::Null),
+                    );
+                }
+            } else if let Some((text, from_event)) = user_prompt_text(&obj) {
+                if from_event {
+                    event_prompt = event_prompt.or_else(|| prompt_title(&text));
+                } else {
+                    item_prompt = item_prompt.or_else(|| prompt_title(&text));
+                }
+            }
+            if session_id.is_some() && event_prompt.is_some() {
+                break;
+            }
+        }
+    }
+
+    let mut last_active_at = None;
+    let mut summary = None;
+    for line in tail.iter().rev() {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            if last_active_at.is_none() {
+                last_active_at = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+            }
+            if summary.is_none() {
+                if obj.get("type").and_then(|v| v.as_str()) == Some("response_item") {
+                    if let Some(payload) = obj.get("payload") {
+                        if payload.get("type").and_then(|v| v.as_str()) == Some("message") {
+                            let text = extract_text(payload.get("content").unwrap_or(&Value::Null));
+                            if !text.is_empty() {
+                                summary = Some(truncate(&text, 160));
+                            }
+                        }
+                    }
+                }
+            }
+            if last_active_at.is_some() && summary.is_some() {
+                break;
+            }
+        }
+    }
+
+    if last_active_at.is_none() {
+        last_active_at = file_modified_ms(file_path);
+    }
+
+    // fallback: 从文件名提取 UUID
+    if session_id.is_none() {
+        session_id = file_path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .map(|s| s.to_string());
+    }
+
+    let sid = session_id.unwrap_or_default();
+    let project_name = cwd.as_deref().and_then(path_basename);
+
+    Some(SessionMeta {
+        provider_id: "codex".to_string(),
+        session_id: sid,
+        title: event_prompt
+            .or(item_prompt)
+            .or_else(|| project_name.clone()),
+        summary,
+        project_dir: cwd,
+        project_name,
+        created_at,
+        last_active_at,
+        source_path: file_path.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+fn parse_codex_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(file);
+    let mut messages: Vec<SessionMessage> = Vec::new();
+    // turn_context 记录之后各轮使用的模型
+    let mut model: Option<String> = None;
+
+    for line in lines_with_offsets(reader) {
+        let (offset, line) = line.map_err(|e| e.to_string())?;
+        if let Ok(obj) = serde_json::from_str::<Value>(&line) {
+            let Some(payload) = obj.get("payload") else {
+                continue;
+            };
+            match obj.get("type").and_then(|v| v.as_str()) {
+                Some("turn_context") => {
+                    if let Some(m) = payload.get("model").and_then(|v| v.as_str()) {
+                        model = Some(m.to_string());
+                    }
+                }
+                // token_count 事件在每次模型调用后记录本次用量, 计入最近的 assistant 消息
+                Some("event_msg") => {
+                    if payload.get("type").and_then(|v| v.as_str()) != Some("token_count") {
+                        continue;
+                    }
+                    let Some(usage) = payload.pointer("/info/last_token_usage") else {
+                        continue;
+                    };
+                    if let Some(message) = messages.iter_mut().rev().find(|m| m.role == "assistant")
+                    {
+                        *message.usage.get_or_insert_with(TokenUsage::default) +=
+                            parse_codex_usage(usage);
+                    }
+                }
+                Some("response_item") => {
+                    if let Some(mut message) = parse_codex_response_item(&obj, payload) {
+                        if message.role == "assistant" {
+                            message.model = model.clone();
+                        }
+                        message.line_offset = Some(offset);
+                        messages.push(message);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(messages)
+}
+
+fn parse_codex_response_item(obj: &Value, payload: &Value) -> Option<SessionMessage> {
+    let (role, blocks) = parse_codex_item(payload)?;
+    let ts = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+    SessionMessage::from_blocks(role, ts, blocks)
+}
+
+/// 单独解析一行, 模型与用量由分页索引补全
+fn parse_codex_record(line: &str) -> Option<SessionMessage> {
+    let obj = serde_json::from_str::<Value>(line).ok()?;
+    if obj.get("type").and_then(|v| v.as_str()) != Some("response_item") {
+        return None;
+    }
+    parse_codex_response_item(&obj, obj.get("payload")?)
+}
+
+/// OpenAI 的 usage: input_tokens 含缓存命中, output_tokens 含推理部分
+fn parse_codex_usage(usage: &Value) -> TokenUsage {
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let cached = count("cached_input_tokens");
+    let reasoning = count("reasoning_output_tokens");
+    TokenUsage {
+        input: count("input_tokens").saturating_sub(cached),
+        output: count("output_tokens").saturating_sub(reasoning),
+        cache_read: cached,
+        cache_write: 0,
+        reasoning,
+    }
+}
+
+/// 解析单个 response_item, 返回角色与内容块
+fn parse_codex_item(payload: &Value) -> Option<(String, Vec<ContentBlock>)> {
+    let str_field = |key: &str| {
+        payload
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+
+    match payload.get("type").and_then(|v| v.as_str())? {
+        "message" => {
+            let role = str_field("role").unwrap_or_else(|| "unknown".to_string());
+            let blocks = match payload.get("content") {
+                Some(Value::Array(items)) => items.iter().filter_map(parse_codex_content).collect(),
+                Some(other) => {
+                    let text = extract_text(other);
+                    vec![ContentBlock::Text { text }]
+                }
+                None => Vec::new(),
+            };
+            Some((role, blocks))
+        }
+        "reasoning" => {
+            let text = extract_text(payload.get("summary").unwrap_or(&Value::Null));
+            Some((
+                "assistant".to_string(),
+                vec![ContentBlock::Reasoning { text }],
+            ))
+        }
+        "function_call" | "custom_tool_call" => {
+            let input = payload
+                .get("arguments")
+                .or_else(|| payload.get("input"))
+                .map(parse_tool_input)
+                .unwrap_or(Value::Null);
+            let block = ContentBlock::ToolCall {
+                id: str_field("call_id"),
+                name: str_field("name")?,
+                input,
+            };
+            Some(("assistant".to_string(), vec![block]))
+        }
+        "local_shell_call" => {
+            let block = ContentBlock::ToolCall {
+                id: str_field("call_id"),
+                name: "shell".to_string(),
+                input: payload.get("action").cloned().unwrap_or(Value::Null),
+            };
+            Some(("assistant".to_string(), vec![block]))
+        }
+        "web_search_call" => {
+            let block = ContentBlock::ToolCall {
+                id: str_field("id"),
+                name: "web_search".to_string(),
+                input: payload.get("action").cloned().unwrap_or(Value::Null),
+            };
+            Some(("assistant".to_string(), vec![block]))
+        }
+        "function_call_output" | "custom_tool_call_output" => {
+            let (output, is_error) = parse_codex_output(payload.get("output")?);
+            let block = ContentBlock::ToolResult {
+                call_id: str_field("call_id"),
+                output,
+                is_error,
+            };
+            Some(("tool".to_string(), vec![block]))
+        }
+        _ => None,
+    }
+}
+
+fn parse_codex_content(item: &Value) -> Option<ContentBlock> {
+    match item.get("type").and_then(|v| v.as_str())? {
+        "input_text" | "output_text" | "text" => Some(ContentBlock::Text {
+            text: item.get("text")?.as_str()?.to_string(),
+        }),
+        "input_image" => Some(ContentBlock::Attachment {
+            kind: AttachmentKind::Image,
+            name: None,
+            media_type: None,
+            // 内联的 data URL 不保留
+            uri: item
+                .get("image_url")
+                .and_then(|v| v.as_str())
+                .filter(|url| !url.starts_with("data:"))
+                .map(|s| s.to_string()),
+        }),
+        "input_file" => Some(ContentBlock::Attachment {
+            kind: AttachmentKind::File,
+            name: item
+                .get("filename")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            media_type: None,
+            uri: None,
+        }),
+        _ => None,
+    }
+}
+
+/// 工具输出可能是纯文本, 也可能是带退出码的 JSON 字符串 {"output": ..., "metadata": {"exit_code": ...}}
+fn parse_codex_output(output: &Value) -> (String, bool) {
+    let parsed = parse_tool_input(output);
+    match parsed.get("output") {
+        Some(inner) => {
+            let exit_code = parsed
+                .pointer("/metadata/exit_code")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0);
+            (tool_output_text(inner), exit_code != 0)
+        }
+        None => (tool_output_text(output), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn title_prefers_user_message_event() {
+        let dir = env::temp_dir().join(format!("anti-power-codex-title-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("rollout.jsonl");
+        let lines = [
+            r#"{"type":"session_meta","payload":{"id":"c1","cwd":"/x/proj","timestamp":"2025-01-01T00:00:00Z"}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"wrapped prompt text"}]}}"#,
+            r#"{"type":"event_msg","payload":{"type":"user_message","message":"real prompt"}}"#,
+        ];
+        fs::write(&file, lines.join("\n")).unwrap();
+        let meta = read_codex_meta(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("real prompt"));
+    }
+
+    #[test]
+    fn title_falls_back_to_user_response_item() {
+        let dir = env::temp_dir().join(format!("anti-power-codex-item-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("rollout.jsonl");
+        let lines = [
+            r#"{"type":"session_meta","payload":{"id":"c2","cwd":"/x/proj"}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"<environment_context>x</environment_context>"}]}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"older prompt"}]}}"#,
+        ];
+        fs::write(&file, lines.join("\n")).unwrap();
+        let meta = read_codex_meta(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("older prompt"));
+    }
+}
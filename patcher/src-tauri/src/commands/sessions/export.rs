@@ -0,0 +1,592 @@
+//! 对话导出
+//!
+//! 将解析后的消息渲染为 Markdown、离线可用的单文件 HTML 或规范化的 JSON 记录,
+//! 批量导出时按查询条件筛选对话, 写入目录或打包为单个 tar 文件
+
+use super::cache::cached_meta;
+use super::provider::{find_provider, SessionProvider};
+use super::query::{matching_sessions, SessionQuery};
+use super::util::{format_iso8601, now_ms, tar_header};
+use super::{AttachmentKind, ContentBlock, SessionMessage, SessionMeta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// 规范化 JSON 记录的格式版本
+const TRANSCRIPT_VERSION: u32 = 1;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// 批量导出请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionExportRequest {
+    /// 筛选条件, 忽略其中的分页参数
+    #[serde(default)]
+    pub query: SessionQuery,
+    pub format: ExportFormat,
+    /// 输出目录; archive 为 true 时为 tar 文件路径
+    pub output_path: String,
+    #[serde(default)]
+    pub archive: bool,
+}
+
+/// 批量导出结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub output_path: String,
+    pub exported: usize,
+    pub failed: Vec<ExportFailure>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFailure {
+    pub source_path: String,
+    pub error: String,
+}
+
+/// JSON 导出的记录结构
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Transcript<'a> {
+    version: u32,
+    exported_at: String,
+    session: &'a SessionMeta,
+    messages: &'a [SessionMessage],
+}
+
+/// 渲染单个对话
+fn render(meta: &SessionMeta, messages: &[SessionMessage], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => render_markdown(meta, messages),
+        ExportFormat::Html => render_html(meta, messages),
+        ExportFormat::Json => serde_json::to_string_pretty(&Transcript {
+            version: TRANSCRIPT_VERSION,
+            exported_at: format_iso8601(now_ms()),
+            session: meta,
+            messages,
+        })
+        .unwrap_or_default(),
+    }
+}
+
+fn display_title(meta: &SessionMeta) -> String {
+    meta.title
+        .clone()
+        .or_else(|| meta.summary.clone())
+        .unwrap_or_else(|| meta.session_id.clone())
+}
+
+/// 元数据的 (标签, 值) 列表, Markdown 与 HTML 共用
+fn meta_rows(meta: &SessionMeta, message_count: usize) -> Vec<(&'static str, String)> {
+    let mut rows = vec![
+        ("Provider", meta.provider_id.clone()),
+        ("Session ID", meta.session_id.clone()),
+    ];
+    if let Some(dir) = &meta.project_dir {
+        rows.push(("Project", dir.clone()));
+    }
+    if let Some(ts) = meta.created_at {
+        rows.push(("Created", format_iso8601(ts)));
+    }
+    if let Some(ts) = meta.last_active_at {
+        rows.push(("Last active", format_iso8601(ts)));
+    }
+    rows.push(("Messages", message_count.to_string()));
+    rows.push(("Source", meta.source_path.clone()));
+    rows
+}
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// 工具参数: 字符串原样输出, 其余格式化为 JSON
+fn tool_input_text(input: &Value) -> String {
+    match input {
+        Value::Null => String::new(),
+        Value::String(raw) => raw.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+fn attachment_label(kind: AttachmentKind, name: Option<&str>, uri: Option<&str>) -> String {
+    let kind = match kind {
+        AttachmentKind::Image => "Image",
+        AttachmentKind::File => "File",
+    };
+    match name.or(uri) {
+        Some(name) => format!("{}: {}", kind, name),
+        None => kind.to_string(),
+    }
+}
+
+// Markdown
+
+/// 围栏长度比内容中最长的连续反引号多一个, 保证内容中的代码块不会提前闭合围栏
+fn fenced(text: &str, lang: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!(
+        "{}{}\n{}\n{}",
+        fence,
+        lang,
+        text.trim_end_matches('\n'),
+        fence
+    )
+}
+
+/// 可折叠区块 (GitHub 等渲染器支持 <details>)
+fn collapsible(summary: &str, body: &str) -> String {
+    format!(
+        "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+        escape_html(summary),
+        body
+    )
+}
+
+fn render_markdown(meta: &SessionMeta, messages: &[SessionMessage]) -> String {
+    let mut out = format!("# {}\n\n", display_title(meta));
+    for (label, value) in meta_rows(meta, messages.len()) {
+        out.push_str(&format!("- **{}**: {}\n", label, value));
+    }
+
+    for message in messages {
+        out.push_str("\n---\n\n");
+        out.push_str(&format!("## {}", role_label(&message.role)));
+        if let Some(ts) = message.ts {
+            out.push_str(&format!(" · {}", format_iso8601(ts)));
+        }
+        out.push_str("\n\n");
+
+        let blocks: Vec<String> = message
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } if !text.is_empty() => Some(text.clone()),
+                ContentBlock::Reasoning { text } if !text.is_empty() => {
+                    Some(collapsible("Thinking", text))
+                }
+                ContentBlock::ToolCall { name, input, .. } => {
+                    let lang = if input.is_string() { "" } else { "json" };
+                    Some(collapsible(
+                        &format!("Tool call: {}", name),
+                        &fenced(&tool_input_text(input), lang),
+                    ))
+                }
+                ContentBlock::ToolResult {
+                    output, is_error, ..
+                } => {
+                    let summary = if *is_error {
+                        "Tool result (error)"
+                    } else {
+                        "Tool result"
+                    };
+                    Some(collapsible(summary, &fenced(output, "")))
+                }
+                ContentBlock::Attachment {
+                    kind, name, uri, ..
+                } => Some(format!(
+                    "*[{}]*",
+                    attachment_label(*kind, name.as_deref(), uri.as_deref())
+                )),
+                ContentBlock::SystemEvent { text } if !text.is_empty() => Some(
+                    text.lines()
+                        .map(|line| format!("> {}", line))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                _ => None,
+            })
+            .collect();
+        out.push_str(&blocks.join("\n\n"));
+        out.push('\n');
+    }
+
+    out
+}
+
+// HTML
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 内联样式, 导出的文件不引用任何外部资源
+const HTML_STYLE: &str = r#"
+body { margin: 0; background: #f6f7f9; color: #1f2328; font: 14px/1.6 -apple-system, "Segoe UI", "Microsoft YaHei", sans-serif; }
+main { max-width: 900px; margin: 0 auto; padding: 32px 20px; }
+h1 { font-size: 22px; margin: 0 0 12px; }
+table.meta { border-collapse: collapse; margin-bottom: 24px; font-size: 13px; }
+table.meta th { text-align: left; padding: 2px 16px 2px 0; color: #656d76; font-weight: 500; }
+table.meta td { word-break: break-all; }
+.message { background: #fff; border: 1px solid #d0d7de; border-radius: 8px; padding: 12px 16px; margin-bottom: 12px; }
+.message.user { border-left: 4px solid #0969da; }
+.message.assistant { border-left: 4px solid #8250df; }
+.message.tool, .message.system { background: #f6f8fa; }
+.role { font-weight: 600; margin-bottom: 6px; }
+.role time { font-weight: 400; color: #656d76; font-size: 12px; margin-left: 8px; }
+.text { white-space: pre-wrap; word-wrap: break-word; }
+.system-event { color: #656d76; font-style: italic; white-space: pre-wrap; }
+.attachment { color: #656d76; }
+details { margin: 6px 0; }
+summary { cursor: pointer; color: #656d76; }
+details.error summary { color: #cf222e; }
+pre { background: #f6f8fa; border: 1px solid #d0d7de; border-radius: 6px; padding: 8px 12px; overflow-x: auto; font: 12px/1.5 ui-monospace, Consolas, monospace; }
+"#;
+
+fn render_html(meta: &SessionMeta, messages: &[SessionMessage]) -> String {
+    let title = escape_html(&display_title(meta));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n<h1>{}</h1>\n<table class=\"meta\">\n",
+        title, HTML_STYLE, title
+    );
+    for (label, value) in meta_rows(meta, messages.len()) {
+        out.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            label,
+            escape_html(&value)
+        ));
+    }
+    out.push_str("</table>\n");
+
+    for message in messages {
+        out.push_str(&format!(
+            "<section class=\"message {}\">\n<div class=\"role\">{}",
+            escape_html(&message.role),
+            escape_html(&role_label(&message.role))
+        ));
+        if let Some(ts) = message.ts {
+            let ts = format_iso8601(ts);
+            out.push_str(&format!("<time datetime=\"{}\">{}</time>", ts, ts));
+        }
+        out.push_str("</div>\n");
+
+        for block in &message.blocks {
+            let html = match block {
+                ContentBlock::Text { text } => {
+                    format!("<div class=\"text\">{}</div>", escape_html(text))
+                }
+                ContentBlock::Reasoning { text } => format!(
+                    "<details><summary>Thinking</summary><div class=\"text\">{}</div></details>",
+                    escape_html(text)
+                ),
+                ContentBlock::ToolCall { name, input, .. } => format!(
+                    "<details><summary>Tool call: {}</summary><pre>{}</pre></details>",
+                    escape_html(name),
+                    escape_html(&tool_input_text(input))
+                ),
+                ContentBlock::ToolResult {
+                    output, is_error, ..
+                } => format!(
+                    "<details{}><summary>{}</summary><pre>{}</pre></details>",
+                    if *is_error { " class=\"error\"" } else { "" },
+                    if *is_error {
+                        "Tool result (error)"
+                    } else {
+                        "Tool result"
+                    },
+                    escape_html(output)
+                ),
+                ContentBlock::Attachment {
+                    kind, name, uri, ..
+                } => format!(
+                    "<div class=\"attachment\">[{}]</div>",
+                    escape_html(&attachment_label(*kind, name.as_deref(), uri.as_deref()))
+                ),
+                ContentBlock::SystemEvent { text } => {
+                    format!("<div class=\"system-event\">{}</div>", escape_html(text))
+                }
+            };
+            out.push_str(&html);
+            out.push('\n');
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+// 文件输出
+
+/// 导出文件名: <provider>-<session_id>.<ext>, 去掉文件系统不允许的字符
+/// 截断或替换字符后可能与本次已导出的文件重名 (不区分大小写), 此时追加序号
+fn export_file_name(
+    meta: &SessionMeta,
+    format: ExportFormat,
+    taken: &mut HashSet<String>,
+) -> String {
+    let stem: String = format!("{}-{}", meta.provider_id, meta.session_id)
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+    let mut name = format!("{}.{}", stem, format.extension());
+    let mut counter = 2;
+    while !taken.insert(name.to_lowercase()) {
+        name = format!("{}-{}.{}", stem, counter, format.extension());
+        counter += 1;
+    }
+    name
+}
+
+/// 查找 source_path 对应的元数据, 缓存中没有时重新解析, 无法解析时只填充能确定的字段
+fn find_meta(provider: &dyn SessionProvider, source_path: &str) -> SessionMeta {
+    cached_meta(provider, source_path).unwrap_or_else(|| SessionMeta {
+        provider_id: provider.id().to_string(),
+        session_id: Path::new(source_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        title: None,
+        summary: None,
+        project_dir: None,
+        project_name: None,
+        created_at: None,
+        last_active_at: None,
+        source_path: source_path.to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+/// 写入文件, 自动创建上级目录
+fn write_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("写入文件失败: {}", e))
+}
+
+/// 将多个对话导出到目录或 tar 文件, 按顺序返回每个对话的导出文件名或错误
+/// 只有创建输出位置或写入 tar 结尾失败时整体返回错误
+pub fn export_to(
+    sessions: &[SessionMeta],
+    format: ExportFormat,
+    output: &Path,
+    archive: bool,
+) -> Result<Vec<Result<String, String>>, String> {
+    let mut tar = if archive {
+        if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        let file = fs::File::create(output).map_err(|e| format!("创建归档失败: {}", e))?;
+        Some(tar::Builder::new(std::io::BufWriter::new(file)))
+    } else {
+        fs::create_dir_all(output).map_err(|e| format!("创建目录失败: {}", e))?;
+        None
+    };
+
+    let mtime = now_ms() / 1000;
+    let mut taken = HashSet::new();
+    let results = sessions
+        .iter()
+        .map(|meta| {
+            let messages = find_provider(&meta.provider_id)
+                .and_then(|provider| provider.load_messages(Path::new(&meta.source_path)))?;
+            let content = render(meta, &messages, format);
+            let name = export_file_name(meta, format, &mut taken);
+            match tar.as_mut() {
+                Some(tar) => tar
+                    .append_data(
+                        &mut tar_header(content.len() as u64, mtime),
+                        &name,
+                        content.as_bytes(),
+                    )
+                    .map_err(|e| format!("写入归档失败: {}", e))?,
+                None => write_file(&output.join(&name), content.as_bytes())?,
+            }
+            Ok(name)
+        })
+        .collect();
+
+    if let Some(tar) = tar {
+        tar.into_inner()
+            .and_then(|mut file| file.flush())
+            .map_err(|e| format!("写入归档失败: {}", e))?;
+    }
+    Ok(results)
+}
+
+// Tauri 命令
+
+/// 导出单个对话
+/// output_path 为空时返回渲染后的内容 (供复制到剪贴板), 否则写入该文件并返回其路径
+#[tauri::command]
+pub fn export_session(
+    provider_id: String,
+    source_path: String,
+    format: ExportFormat,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let provider = find_provider(&provider_id)?;
+    if !provider.source_exists(Path::new(&source_path)) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+    let messages = provider.load_messages(Path::new(&source_path))?;
+    let meta = find_meta(provider, &source_path);
+    let content = render(&meta, &messages, format);
+
+    match output_path.filter(|path| !path.is_empty()) {
+        Some(path) => {
+            write_file(Path::new(&path), content.as_bytes())?;
+            Ok(path)
+        }
+        None => Ok(content),
+    }
+}
+
+/// 按筛选条件批量导出对话, 单个对话失败不影响其余对话
+#[tauri::command]
+pub fn export_sessions(request: SessionExportRequest) -> Result<ExportSummary, String> {
+    let sessions = matching_sessions(&request.query)?;
+    let results = export_to(
+        &sessions,
+        request.format,
+        Path::new(&request.output_path),
+        request.archive,
+    )?;
+
+    let mut exported = 0;
+    let mut failed = Vec::new();
+    for (meta, result) in sessions.iter().zip(results) {
+        match result {
+            Ok(_) => exported += 1,
+            Err(error) => failed.push(ExportFailure {
+                source_path: meta.source_path.clone(),
+                error,
+            }),
+        }
+    }
+
+    Ok(ExportSummary {
+        output_path: request.output_path,
+        exported,
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(session_id: &str) -> SessionMeta {
+        SessionMeta {
+            provider_id: "codex".to_string(),
+            session_id: session_id.to_string(),
+            title: None,
+            summary: None,
+            project_dir: None,
+            project_name: None,
+            created_at: None,
+            last_active_at: None,
+            source_path: String::new(),
+            message_count: None,
+            usage: None,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn fenced_outgrows_backtick_runs_in_content() {
+        assert_eq!(fenced("let x = 1;", "rust"), "```rust\nlet x = 1;\n```");
+        assert_eq!(
+            fenced("use ```sh\nls\n```\n", ""),
+            "````\nuse ```sh\nls\n```\n````"
+        );
+        let text = "a ````` b";
+        assert!(fenced(text, "").starts_with("``````\n"));
+    }
+
+    #[test]
+    fn escape_html_escapes_markup_and_quotes() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("纯文本"), "纯文本");
+    }
+
+    #[test]
+    fn export_file_name_sanitizes_and_stays_unique() {
+        let mut taken = HashSet::new();
+        assert_eq!(
+            export_file_name(&meta("a/b:c"), ExportFormat::Markdown, &mut taken),
+            "codex-a_b_c.md"
+        );
+        // 不同 id 替换字符后重名
+        assert_eq!(
+            export_file_name(&meta("a?b*c"), ExportFormat::Markdown, &mut taken),
+            "codex-a_b_c-2.md"
+        );
+        // 只在大小写上不同的名称在不区分大小写的文件系统上同样冲突
+        assert_eq!(
+            export_file_name(&meta("A_B_C"), ExportFormat::Markdown, &mut taken),
+            "codex-A_B_C-3.md"
+        );
+        // 截断后前 80 个字符相同
+        let prefix = "x".repeat(90);
+        let first = export_file_name(
+            &meta(&format!("{}1", prefix)),
+            ExportFormat::Json,
+            &mut taken,
+        );
+        let second = export_file_name(
+            &meta(&format!("{}2", prefix)),
+            ExportFormat::Json,
+            &mut taken,
+        );
+        assert_eq!(first.len(), 80 + ".json".len());
+        assert_ne!(first, second);
+        assert!(second.ends_with("-2.json"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Gemini CLI 对话记录 (~/.gemini/tmp/<项目哈希>/chats/*.json)
+
+use super::provider::{ProviderCapabilities, SessionProvider};
+use super::usage::TokenUsage;
+use super::util::{
+    extract_text, file_modified_ms, parse_timestamp, prompt_title, tool_output_text, truncate,
+};
+use super::{ContentBlock, SessionMessage, SessionMeta};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct GeminiProvider;
+
+impl SessionProvider for GeminiProvider {
+    fn id(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Gemini CLI"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        vec![home.join(".gemini").join("tmp")]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: false,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_gemini_sources(root)
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        read_gemini_meta(source)
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        parse_gemini_messages(source_path)
+    }
+
+    /// Gemini CLI 只能继续当前项目的对话, 必须在原项目目录中执行
+    /// 记录目录 tmp/<项目哈希> 无法反推出项目路径, 没有项目目录时不支持继续
+    fn resume_command(&self, meta: &SessionMeta) -> Option<Vec<String>> {
+        meta.project_dir.as_ref()?;
+        Some(vec![
+            "gemini".to_string(),
+            "--resume".to_string(),
+            meta.session_id.clone(),
+        ])
+    }
+
+    fn session_paths(&self, source_path: &Path, _session_id: &str) -> Result<Vec<PathBuf>, String> {
+        Ok(vec![source_path.to_path_buf()])
+    }
+}
+
+fn list_gemini_sources(tmp_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    // 遍历 tmp/<project_hash>/chats/session-*.json
+    if let Ok(hash_dirs) = fs::read_dir(tmp_dir) {
+        for hash_entry in hash_dirs.flatten() {
+            let chats_dir = hash_entry.path().join("chats");
+            if let Ok(chat_files) = fs::read_dir(&chats_dir) {
+                for chat_entry in chat_files.flatten() {
+                    let file_path = chat_entry.path();
+                    if file_path.extension().and_then(|e| e.to_str()) == Some("json") {
+                        files.push(file_path);
+                    }
+                }
+            }
+        }
+    }
+
+    files
+}
+
+fn read_gemini_meta(file_path: &Path) -> Option<SessionMeta> {
+    let content = fs::read_to_string(file_path).ok()?;
+    let obj: Value = serde_json::from_str(&content).ok()?;
+
+    let session_id = obj
+        .get("sessionId")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let created_at = parse_timestamp(obj.get("startTime").unwrap_or(&Value::Null));
+    let last_active_at = parse_timestamp(obj.get("lastUpdated").unwrap_or(&Value::Null))
+        .or_else(|| file_modified_ms(file_path));
+
+    // 从第一条用户消息获取 summary, 标题取第一条可作为标题的用户输入
+    let mut title = None;
+    let mut summary = None;
+    let user_texts = obj
+        .get("messages")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|msg| msg.get("type").and_then(|v| v.as_str()) == Some("user"))
+        .filter_map(|msg| msg.get("content").and_then(|v| v.as_str()))
+        .filter(|text| !text.is_empty());
+    for text in user_texts {
+        if summary.is_none() {
+            summary = Some(truncate(text, 160));
+        }
+        title = prompt_title(text);
+        if title.is_some() {
+            break;
+        }
+    }
+
+    Some(SessionMeta {
+        provider_id: "gemini".to_string(),
+        session_id,
+        title,
+        summary,
+        project_dir: None,
+        project_name: None,
+        created_at,
+        last_active_at,
+        source_path: file_path.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+fn parse_gemini_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let obj: Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let mut messages = Vec::new();
+
+    if let Some(msgs) = obj.get("messages").and_then(|v| v.as_array()) {
+        for msg in msgs {
+            let msg_type = msg
+                .get("type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            let text = extract_text(msg.get("content").unwrap_or(&Value::Null));
+            let ts = parse_timestamp(msg.get("timestamp").unwrap_or(&Value::Null));
+
+            // info / warning / error 为 CLI 输出的提示信息
+            let (role, blocks) = match msg_type {
+                "info" | "warning" | "error" => (
+                    "system".to_string(),
+                    vec![ContentBlock::SystemEvent { text }],
+                ),
+                "gemini" => ("assistant".to_string(), parse_gemini_blocks(msg, text)),
+                other => (other.to_string(), vec![ContentBlock::Text { text }]),
+            };
+            if let Some(mut message) = SessionMessage::from_blocks(role, ts, blocks) {
+                message.model = msg
+                    .get("model")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                message.usage = msg.get("tokens").map(parse_gemini_usage);
+                messages.push(message);
+            }
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Gemini 的 tokens 字段: input 含缓存命中, thoughts 为思考部分, tool 为工具调用的提示词
+fn parse_gemini_usage(tokens: &Value) -> TokenUsage {
+    let count = |key: &str| tokens.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let cached = count("cached");
+    TokenUsage {
+        input: count("input").saturating_sub(cached) + count("tool"),
+        output: count("output"),
+        cache_read: cached,
+        cache_write: 0,
+        reasoning: count("thoughts"),
+    }
+}
+
+/// 模型消息: 思考过程、正文, 以及同一条消息内的工具调用与结果
+fn parse_gemini_blocks(msg: &Value, text: String) -> Vec<ContentBlock> {
+    let mut blocks = Vec::new();
+
+    for thought in msg
+        .get("thoughts")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let parts: Vec<&str> = ["subject", "description"]
+            .iter()
+            .filter_map(|key| thought.get(*key).and_then(|v| v.as_str()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        blocks.push(ContentBlock::Reasoning {
+            text: parts.join("\n"),
+        });
+    }
+
+    blocks.push(ContentBlock::Text { text });
+
+    for call in msg
+        .get("toolCalls")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let id = call
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let Some(name) = call.get("name").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        blocks.push(ContentBlock::ToolCall {
+            id: id.clone(),
+            name: name.to_string(),
+            input: call.get("args").cloned().unwrap_or(Value::Null),
+        });
+
+        // resultDisplay 为 CLI 展示给用户的结果, 缺失时取 functionResponse 中的输出
+        let output = match call.get("resultDisplay") {
+            Some(Value::String(display)) => display.clone(),
+            _ => call
+                .get("result")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|part| part.pointer("/functionResponse/response"))
+                .map(|response| tool_output_text(response.get("output").unwrap_or(response)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        let status = call.get("status").and_then(|v| v.as_str());
+        if output.is_empty() && status.is_none() {
+            continue;
+        }
+        blocks.push(ContentBlock::ToolResult {
+            call_id: id,
+            output,
+            is_error: matches!(status, Some("error" | "cancelled")),
+        });
+    }
+
+    blocks
+}
@@ -0,0 +1,356 @@
+//! 对话记录浏览模块
+//!
+//! 提供扫描和预览 Claude Code / Codex / Gemini CLI / OpenCode / OpenClaw / Antigravity 对话记录的功能
+//! 各工具的解析逻辑实现为 SessionProvider, 见 provider 模块的注册表
+
+mod antigravity;
+mod archive;
+mod bulk;
+mod cache;
+mod claude;
+mod codex;
+mod export;
+mod gemini;
+mod openclaw;
+mod opencode;
+mod page;
+mod project;
+mod provider;
+mod query;
+mod resume;
+mod scan;
+mod search;
+mod secrets;
+mod stats;
+mod trash;
+mod usage;
+mod util;
+mod watch;
+
+use super::audit::{self, AuditRecord};
+use project::ProjectInfo;
+use provider::{all_providers, find_provider, ProviderCapabilities};
+use query::{SessionPage, SessionQuery};
+use scan::ScanControl;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use usage::{SessionUsage, TokenUsage};
+
+pub use archive::{
+    archive_sessions, list_archives, load_archived_session, restore_archived_session,
+};
+pub use bulk::bulk_session_action;
+pub use export::{export_session, export_sessions};
+pub use page::{
+    cancel_message_stream, load_message_block, load_session_page, start_message_stream,
+};
+pub use project::list_session_projects;
+pub use resume::{launch_session_resume, session_resume_command};
+pub use scan::{cancel_session_scan, start_session_scan};
+pub use search::search_sessions;
+pub use secrets::{redact_session, scan_session_secrets};
+pub use trash::{empty_trash, list_trash, restore_session};
+pub use usage::session_usage_report;
+pub use watch::{start_session_tail, start_session_watch, stop_session_watch};
+
+/// 对话元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionMeta {
+    pub provider_id: String,
+    pub session_id: String,
+    /// 对话标题, 按 provider 的解析顺序取最具描述性的一个, 都没有时为项目目录名
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub project_dir: Option<String>,
+    /// 项目目录的最后一级名称
+    #[serde(default)]
+    pub project_name: Option<String>,
+    pub created_at: Option<u64>,
+    pub last_active_at: Option<u64>,
+    pub source_path: String,
+    /// 消息数, 扫描时不统计, 按消息数过滤或排序时由统计缓存填充
+    #[serde(default)]
+    pub message_count: Option<usize>,
+    /// Token 用量, 与 message_count 一同由统计缓存填充
+    #[serde(default)]
+    pub usage: Option<SessionUsage>,
+    /// 工作目录所属的项目, 不缓存, 在扫描或查询结果返回前解析
+    #[serde(default)]
+    pub project: Option<ProjectInfo>,
+}
+
+/// 单条消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionMessage {
+    /// provider 内的消息 id, 用于定位分支的分叉处
+    pub id: Option<String>,
+    pub role: String,
+    /// 各内容块的纯文本投影, 供搜索与只显示文本的调用方使用
+    pub content: String,
+    pub ts: Option<u64>,
+    pub blocks: Vec<ContentBlock>,
+    /// 生成该消息的模型
+    pub model: Option<String>,
+    /// 该消息消耗的 token
+    pub usage: Option<TokenUsage>,
+    /// 按行存储的记录中该消息所在行的字节偏移, 用于建立分页索引
+    #[serde(skip)]
+    pub line_offset: Option<u64>,
+}
+
+/// 消息内容块
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    /// 工具调用, input 为工具参数 (无法解析为 JSON 时保留原始字符串)
+    ToolCall {
+        id: Option<String>,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        /// 对应的 ToolCall id
+        call_id: Option<String>,
+        output: String,
+        is_error: bool,
+    },
+    /// 模型的思考/推理过程
+    Reasoning {
+        text: String,
+    },
+    /// 图片或文件引用, 内联数据不保留
+    Attachment {
+        kind: AttachmentKind,
+        name: Option<String>,
+        media_type: Option<String>,
+        /// 文件路径或 URL
+        uri: Option<String>,
+    },
+    /// CLI 产生的系统事件 (提示、错误、上下文压缩等)
+    SystemEvent {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Image,
+    File,
+}
+
+impl ContentBlock {
+    /// 内容块的纯文本形式
+    fn plain_text(&self) -> String {
+        match self {
+            ContentBlock::Text { text }
+            | ContentBlock::Reasoning { text }
+            | ContentBlock::SystemEvent { text } => text.clone(),
+            ContentBlock::ToolCall { name, input, .. } => match input {
+                Value::Null => format!("[{}]", name),
+                Value::String(raw) => format!("[{}] {}", name, raw),
+                other => format!("[{}] {}", name, other),
+            },
+            ContentBlock::ToolResult { output, .. } => output.clone(),
+            ContentBlock::Attachment {
+                kind, name, uri, ..
+            } => {
+                let label = match kind {
+                    AttachmentKind::Image => "image",
+                    AttachmentKind::File => "file",
+                };
+                match name.as_deref().or(uri.as_deref()) {
+                    Some(name) => format!("[{}: {}]", label, name),
+                    None => format!("[{}]", label),
+                }
+            }
+        }
+    }
+}
+
+impl SessionMessage {
+    /// 由内容块构造消息, 没有任何内容时返回 None
+    pub fn from_blocks(role: String, ts: Option<u64>, blocks: Vec<ContentBlock>) -> Option<Self> {
+        let content = blocks
+            .iter()
+            .map(ContentBlock::plain_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if content.is_empty() {
+            return None;
+        }
+        Some(SessionMessage {
+            id: None,
+            role,
+            content,
+            ts,
+            blocks,
+            model: None,
+            usage: None,
+            line_offset: None,
+        })
+    }
+}
+
+/// 树状的对话结构
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTree {
+    /// 主线消息, 与 load_session_messages 的结果一致
+    pub messages: Vec<SessionMessage>,
+    /// 编辑或重试消息后未被采用的分支
+    pub branches: Vec<MessageBranch>,
+    /// 由主线中的工具调用启动的子代理对话
+    pub subagents: Vec<SubagentSession>,
+}
+
+/// 从主线某条消息之后分出的另一条消息序列
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBranch {
+    /// 分叉处的消息 id, 从对话开头分叉时为 None
+    pub fork_id: Option<String>,
+    pub messages: Vec<SessionMessage>,
+}
+
+/// 子代理对话, 通过 load_session_tree 打开
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubagentSession {
+    pub agent_id: Option<String>,
+    /// 启动该子代理的 ToolCall id, 无法关联时为 None
+    pub tool_call_id: Option<String>,
+    pub description: Option<String>,
+    pub agent_type: Option<String>,
+    pub source_path: String,
+    /// 子代理消息写在主记录中时, 其第一条消息的 id
+    pub sidechain_root: Option<String>,
+    pub started_at: Option<u64>,
+    pub message_count: usize,
+}
+
+/// provider 信息, 供前端发现可用的对话来源
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionProviderInfo {
+    pub id: String,
+    pub display_name: String,
+    pub roots: Vec<String>,
+    /// 默认目录存在, 即本机使用过该工具
+    pub installed: bool,
+    pub capabilities: ProviderCapabilities,
+}
+
+// Tauri 命令
+
+/// 列出所有已注册的 provider 及其安装状态
+#[tauri::command]
+pub fn list_session_providers() -> Result<Vec<SessionProviderInfo>, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+
+    Ok(all_providers()
+        .iter()
+        .map(|provider| SessionProviderInfo {
+            id: provider.id().to_string(),
+            display_name: provider.display_name().to_string(),
+            roots: provider
+                .default_roots(&home)
+                .iter()
+                .map(|root| root.to_string_lossy().to_string())
+                .collect(),
+            installed: provider.is_installed(&home),
+            capabilities: provider.capabilities(),
+        })
+        .collect())
+}
+
+/// 扫描对话列表（只读元数据，不加载消息体），按条件过滤、排序并分页返回
+/// 元数据按文件修改时间与大小缓存, query.rebuild 为 true 时强制全部重新解析
+#[tauri::command]
+pub fn scan_sessions(query: Option<SessionQuery>) -> Result<SessionPage, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let query = query.unwrap_or_default();
+    let providers = if query.providers.is_empty() {
+        all_providers().to_vec()
+    } else {
+        query
+            .providers
+            .iter()
+            .map(|id| find_provider(id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let sessions = cache::scan_cached(&providers, &home, query.rebuild, &ScanControl::default())?
+        .unwrap_or_default();
+    let provider_ids: Vec<&str> = providers.iter().map(|provider| provider.id()).collect();
+    query::query_sessions(sessions, &provider_ids, &query)
+}
+
+/// 按需加载指定对话的全部消息 (树状对话只含主线)
+#[tauri::command]
+pub fn load_session_messages(
+    provider_id: String,
+    source_path: String,
+) -> Result<Vec<SessionMessage>, String> {
+    let provider = find_provider(&provider_id)?;
+    let path = Path::new(&source_path);
+    if !provider.source_exists(path) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+
+    provider.load_messages(path)
+}
+
+/// 加载对话的树状结构: 主线消息、分支与子代理
+/// 打开子代理时传入其 source_path 与 sidechain_root;
+/// 主线消息改由分页接口加载时, include_messages 传 false 以省去传输
+#[tauri::command]
+pub fn load_session_tree(
+    provider_id: String,
+    source_path: String,
+    sidechain_root: Option<String>,
+    include_messages: Option<bool>,
+) -> Result<SessionTree, String> {
+    let provider = find_provider(&provider_id)?;
+    let path = Path::new(&source_path);
+    if !provider.source_exists(path) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+
+    let mut tree = provider.load_tree(path, sidechain_root.as_deref())?;
+    if include_messages == Some(false) {
+        tree.messages.clear();
+    }
+    Ok(tree)
+}
+
+/// 删除指定的单个对话: 将其全部文件移入回收站, 返回回收站 id
+#[tauri::command]
+pub fn delete_session(
+    provider_id: String,
+    source_path: String,
+    session_id: String,
+) -> Result<String, String> {
+    let audit = AuditRecord::new("delete_session").with_config(serde_json::json!({
+        "providerId": &provider_id,
+        "sessionId": &session_id,
+        "sourcePath": &source_path,
+    }));
+
+    let result = find_provider(&provider_id).and_then(|provider| {
+        trash::move_to_trash(provider, Path::new(&source_path), &session_id).map(|entry| entry.id)
+    });
+
+    audit.finish_with_message(result)
+}
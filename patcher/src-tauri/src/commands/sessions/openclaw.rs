@@ -0,0 +1,299 @@
+//! OpenClaw 对话记录 (~/.openclaw/agents/<agent>/sessions/*.jsonl)
+
+use super::provider::{LineParser, ProviderCapabilities, SessionProvider};
+use super::usage::TokenUsage;
+use super::util::{
+    collect_files_recursive, extract_text, file_modified_ms, lines_with_offsets, parse_timestamp,
+    parse_tool_input, path_basename, prompt_title, read_head_lines, read_tail_lines,
+    tool_output_text, truncate,
+};
+use super::{AttachmentKind, ContentBlock, SessionMessage, SessionMeta};
+use serde_json::Value;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+pub struct OpenClawProvider;
+
+impl SessionProvider for OpenClawProvider {
+    fn id(&self) -> &'static str {
+        "openclaw"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "OpenClaw"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        vec![home.join(".openclaw").join("agents")]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: true,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_openclaw_sources(root)
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        read_openclaw_meta(source)
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        parse_openclaw_messages(source_path)
+    }
+
+    fn line_parser(&self) -> Option<LineParser> {
+        Some(parse_openclaw_record)
+    }
+
+    /// OpenClaw 按会话键打开对话, 键记录在同目录的 sessions.json 中
+    fn resume_command(&self, meta: &SessionMeta) -> Option<Vec<String>> {
+        let key = find_session_key(Path::new(&meta.source_path), &meta.session_id)?;
+        Some(vec![
+            "openclaw".to_string(),
+            "tui".to_string(),
+            "--session".to_string(),
+            key,
+        ])
+    }
+
+    fn session_paths(&self, source_path: &Path, _session_id: &str) -> Result<Vec<PathBuf>, String> {
+        Ok(vec![source_path.to_path_buf()])
+    }
+}
+
+fn list_openclaw_sources(agents_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    if let Ok(agent_entries) = fs::read_dir(agents_dir) {
+        for agent_entry in agent_entries.flatten() {
+            let sessions_dir = agent_entry.path().join("sessions");
+            if !sessions_dir.is_dir() {
+                continue;
+            }
+
+            // 跳过 sessions.json（collect_files_recursive 已按扩展名过滤，但以防万一）
+            files.extend(
+                collect_files_recursive(&sessions_dir, "jsonl")
+                    .into_iter()
+                    .filter(|file_path| {
+                        file_path.file_name().and_then(|n| n.to_str()) != Some("sessions.json")
+                    }),
+            );
+        }
+    }
+
+    files
+}
+
+/// 在 sessions.json 中查找对话 id 对应的会话键
+fn find_session_key(source_path: &Path, session_id: &str) -> Option<String> {
+    let index = fs::read_to_string(source_path.parent()?.join("sessions.json")).ok()?;
+    let index: Value = serde_json::from_str(&index).ok()?;
+    index
+        .as_object()?
+        .iter()
+        .find(|(_, entry)| entry.get("sessionId").and_then(|v| v.as_str()) == Some(session_id))
+        .map(|(key, _)| key.clone())
+}
+
+fn read_openclaw_meta(file_path: &Path) -> Option<SessionMeta> {
+    // 头部多读一些行, 以找到第一条用户输入
+    let head = read_head_lines(file_path, 50);
+    let tail = read_tail_lines(file_path, 30);
+
+    let mut session_id = None;
+    let mut cwd = None;
+    let mut created_at = None;
+    let mut summary = None;
+    let mut first_prompt = None;
+
+    for line in &head {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            if obj.get("type").and_then(|v| v.as_str()) == Some("session") {
+                session_id = obj
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                cwd = obj
+                    .get("cwd")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                created_at = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+            }
+            if summary.is_none() && obj.get("type").and_then(|v| v.as_str()) == Some("message") {
+                if let Some(msg) = obj.get("message") {
+                    let text = extract_text(msg.get("content").unwrap_or(&Value::Null));
+                    if !text.is_empty() {
+                        summary = Some(truncate(&text, 160));
+                    }
+                }
+            }
+            if first_prompt.is_none() && obj.get("type").and_then(|v| v.as_str()) == Some("message")
+            {
+                first_prompt = obj
+                    .get("message")
+                    .filter(|msg| msg.get("role").and_then(|v| v.as_str()) == Some("user"))
+                    .map(|msg| extract_text(msg.get("content").unwrap_or(&Value::Null)))
+                    .and_then(|text| prompt_title(&text));
+            }
+        }
+    }
+
+    let mut last_active_at = None;
+    for line in tail.iter().rev() {
+        if let Ok(obj) = serde_json::from_str::<Value>(line) {
+            last_active_at = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+            if last_active_at.is_some() {
+                break;
+            }
+        }
+    }
+
+    if last_active_at.is_none() {
+        last_active_at = file_modified_ms(file_path);
+    }
+
+    let sid = session_id.unwrap_or_else(|| {
+        file_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+    let project_name = cwd.as_deref().and_then(path_basename);
+
+    Some(SessionMeta {
+        provider_id: "openclaw".to_string(),
+        session_id: sid,
+        title: first_prompt.or_else(|| project_name.clone()),
+        summary,
+        project_dir: cwd,
+        project_name,
+        created_at,
+        last_active_at,
+        source_path: file_path.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+fn parse_openclaw_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(file);
+    let mut messages = Vec::new();
+
+    for line in lines_with_offsets(reader) {
+        let (offset, line) = line.map_err(|e| e.to_string())?;
+        if let Some(mut message) = parse_openclaw_record(&line) {
+            message.line_offset = Some(offset);
+            messages.push(message);
+        }
+    }
+
+    Ok(messages)
+}
+
+fn parse_openclaw_record(line: &str) -> Option<SessionMessage> {
+    let obj = serde_json::from_str::<Value>(line).ok()?;
+    if obj.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return None;
+    }
+    let msg = obj.get("message")?;
+    let raw_role = msg
+        .get("role")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let ts = parse_timestamp(obj.get("timestamp").unwrap_or(&Value::Null));
+    let content = msg.get("content").unwrap_or(&Value::Null);
+
+    // 工具结果单独成一条消息, 通过 toolCallId 关联调用
+    let (role, blocks) = match raw_role {
+        "toolResult" => {
+            let block = ContentBlock::ToolResult {
+                call_id: msg
+                    .get("toolCallId")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+                output: tool_output_text(content),
+                is_error: msg
+                    .get("isError")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            };
+            ("tool".to_string(), vec![block])
+        }
+        other => (other.to_string(), parse_openclaw_blocks(content)),
+    };
+    let mut message = SessionMessage::from_blocks(role, ts, blocks)?;
+    message.model = msg
+        .get("model")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    message.usage = msg.get("usage").map(parse_openclaw_usage);
+    Some(message)
+}
+
+/// OpenClaw 的 usage 字段, 缓存读写与输入分开记录
+fn parse_openclaw_usage(usage: &Value) -> TokenUsage {
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    TokenUsage {
+        input: count("input"),
+        output: count("output"),
+        cache_read: count("cacheRead"),
+        cache_write: count("cacheWrite"),
+        reasoning: 0,
+    }
+}
+
+fn parse_openclaw_blocks(content: &Value) -> Vec<ContentBlock> {
+    let items = match content {
+        Value::Array(items) => items,
+        other => {
+            return vec![ContentBlock::Text {
+                text: extract_text(other),
+            }];
+        }
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let str_field = |key: &str| {
+                item.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+            };
+            let block = match item.get("type").and_then(|v| v.as_str())? {
+                "text" => ContentBlock::Text {
+                    text: str_field("text")?,
+                },
+                "thinking" => ContentBlock::Reasoning {
+                    text: str_field("thinking")?,
+                },
+                "toolCall" => ContentBlock::ToolCall {
+                    id: str_field("id"),
+                    name: str_field("name")?,
+                    input: item
+                        .get("arguments")
+                        .map(parse_tool_input)
+                        .unwrap_or(Value::Null),
+                },
+                "image" => ContentBlock::Attachment {
+                    kind: AttachmentKind::Image,
+                    name: None,
+                    media_type: str_field("mimeType"),
+                    uri: None,
+                },
+                _ => return None,
+            };
+            Some(block)
+        })
+        .collect()
+}
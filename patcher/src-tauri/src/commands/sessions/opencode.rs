@@ -0,0 +1,961 @@
+//! OpenCode 对话记录 ($XDG_DATA_HOME/opencode/storage, 消息按 message/<session_id>/ 分目录存放)
+//!
+//! 新版本改为写入同目录下的 opencode.db, 数据库存在时以其中的对话为准,
+//! 数据源路径为 <opencode.db>/<session_id>; 只存在于 storage 中的旧对话仍按文件读取
+
+use super::provider::{ProviderCapabilities, SessionProvider};
+use super::usage::TokenUsage;
+use super::util::{
+    collect_files_recursive, decode_base64, encode_base64, file_modified_ms, parse_timestamp,
+    path_basename, tool_output_text, Fingerprint,
+};
+use super::{AttachmentKind, ContentBlock, SessionMessage, SessionMeta};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, OpenFlags, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DB_FILE_NAME: &str = "opencode.db";
+/// 快照中 BLOB 值的键, 值为 base64
+const BLOB_KEY: &str = "$blob";
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 数据库中各对话的指纹 (更新时间与消息数), 按数据库文件与 WAL 文件的指纹缓存
+struct DbIndex {
+    db: PathBuf,
+    fingerprint: (Fingerprint, Fingerprint),
+    sessions: HashMap<String, Fingerprint>,
+}
+
+static DB_INDEX: Mutex<Option<DbIndex>> = Mutex::new(None);
+
+/// detach 时保存的数据库记录
+#[derive(Debug, Serialize, Deserialize)]
+struct DbSnapshot {
+    db: String,
+    /// 按外键依赖排序, 被引用的表在前
+    tables: Vec<TableRows>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TableRows {
+    name: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+pub struct OpenCodeProvider;
+
+impl SessionProvider for OpenCodeProvider {
+    fn id(&self) -> &'static str {
+        "opencode"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "OpenCode"
+    }
+
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf> {
+        vec![resolve_opencode_storage(home)]
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            can_delete: true,
+            has_project_dir: true,
+        }
+    }
+
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf> {
+        list_opencode_sources(root)
+    }
+
+    /// opencode.db 与 storage 位于同一目录
+    fn watch_roots(&self, home: &Path) -> Vec<PathBuf> {
+        let storage = resolve_opencode_storage(home);
+        vec![storage.parent().map_or(storage.clone(), Path::to_path_buf)]
+    }
+
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta> {
+        match db_source(source) {
+            Some((db, session_id)) => read_db_meta(db, session_id),
+            None => read_opencode_meta(source),
+        }
+    }
+
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String> {
+        match db_source(source_path) {
+            Some((db, session_id)) => load_db_messages(db, session_id)
+                .map_err(|e| format!("读取 {} 失败: {}", DB_FILE_NAME, e)),
+            None => parse_opencode_messages(source_path),
+        }
+    }
+
+    fn source_exists(&self, source: &Path) -> bool {
+        match db_source(source) {
+            Some((db, session_id)) => {
+                with_db_index(db, |sessions| sessions.contains_key(session_id)).unwrap_or(false)
+            }
+            None => source.exists(),
+        }
+    }
+
+    fn fingerprint(&self, source: &Path) -> Fingerprint {
+        match db_source(source) {
+            Some((db, session_id)) => {
+                with_db_index(db, |sessions| sessions.get(session_id).copied())
+                    .flatten()
+                    .unwrap_or(Fingerprint::new(0, 0))
+            }
+            None => Fingerprint::of(source),
+        }
+    }
+
+    /// 数据库中的对话只包含 storage 中可能残留的旧文件
+    fn session_paths(&self, source_path: &Path, session_id: &str) -> Result<Vec<PathBuf>, String> {
+        match db_source(source_path) {
+            Some((db, _)) => {
+                let storage = db.parent().unwrap_or(Path::new("")).join("storage");
+                opencode_session_paths(&storage.join("message").join(session_id), session_id)
+            }
+            None => opencode_session_paths(source_path, session_id),
+        }
+    }
+
+    /// 数据库与 storage 中的对话都通过 --session 按 id 继续
+    fn resume_command(&self, meta: &SessionMeta) -> Option<Vec<String>> {
+        Some(vec![
+            "opencode".to_string(),
+            "--session".to_string(),
+            meta.session_id.clone(),
+        ])
+    }
+
+    /// 在一个事务中删除对话在 opencode.db 中的全部记录, 返回删除前的记录
+    fn detach(&self, source_path: &Path, session_id: &str) -> Result<Option<Value>, String> {
+        let Some(db) = db_path_for(source_path).filter(|db| db.is_file()) else {
+            return Ok(None);
+        };
+        let snapshot = remove_session_rows(&db, session_id)
+            .map_err(|e| format!("更新 {} 失败: {}", DB_FILE_NAME, e))?;
+        Ok(snapshot
+            .filter(|snapshot| !snapshot.tables.is_empty())
+            .and_then(|snapshot| serde_json::to_value(snapshot).ok()))
+    }
+
+    fn reattach(&self, detached: &Value) -> Result<(), String> {
+        let snapshot: DbSnapshot = serde_json::from_value(detached.clone())
+            .map_err(|_| format!("{} 记录无效", DB_FILE_NAME))?;
+        restore_session_rows(&snapshot).map_err(|e| format!("更新 {} 失败: {}", DB_FILE_NAME, e))
+    }
+
+    fn load_detached(&self, detached: &Value) -> Result<Vec<SessionMessage>, String> {
+        let snapshot: DbSnapshot = serde_json::from_value(detached.clone())
+            .map_err(|_| format!("{} 记录无效", DB_FILE_NAME))?;
+        Ok(snapshot_messages(&snapshot))
+    }
+
+    /// 脱敏 opencode.db 中对话的 message 与 part 记录, 返回修改前的 data 列
+    fn redact_records(
+        &self,
+        source_path: &Path,
+        session_id: &str,
+        redact: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Option<Value>, String> {
+        let Some(db) = db_path_for(source_path).filter(|db| db.is_file()) else {
+            return Ok(None);
+        };
+        let original = redact_session_rows(&db, session_id, redact)
+            .map_err(|e| format!("更新 {} 失败: {}", DB_FILE_NAME, e))?;
+        Ok((!original.is_empty()).then_some(Value::Array(original)))
+    }
+
+    fn is_installed(&self, home: &Path) -> bool {
+        let storage = resolve_opencode_storage(home);
+        storage.is_dir() || storage.with_file_name(DB_FILE_NAME).is_file()
+    }
+}
+
+fn resolve_opencode_storage(home: &Path) -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local").join("share"))
+        .join("opencode")
+        .join("storage")
+}
+
+/// 数据库中的对话, 以及 storage 中尚未迁移到数据库的对话
+fn list_opencode_sources(storage: &Path) -> Vec<PathBuf> {
+    let db = storage.with_file_name(DB_FILE_NAME);
+    let db_ids: HashSet<String> =
+        with_db_index(&db, |sessions| sessions.keys().cloned().collect()).unwrap_or_default();
+    let mut sources: Vec<PathBuf> = db_ids.iter().map(|id| db.join(id)).collect();
+
+    let session_dir = storage.join("session");
+    if session_dir.is_dir() {
+        sources.extend(
+            collect_files_recursive(&session_dir, "json")
+                .into_iter()
+                .filter(|file| {
+                    file.file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .is_none_or(|id| !db_ids.contains(id))
+                }),
+        );
+    }
+    sources
+}
+
+/// OpenCode 在生成标题前使用的默认标题
+const DEFAULT_TITLE_PREFIXES: &[&str] = &["New session - ", "Child session - "];
+
+/// 去掉空标题与默认标题, 此时由第一条用户输入生成标题
+fn native_title(title: Option<String>) -> Option<String> {
+    title.filter(|title| {
+        !title.trim().is_empty()
+            && !DEFAULT_TITLE_PREFIXES
+                .iter()
+                .any(|prefix| title.starts_with(prefix))
+    })
+}
+
+/// 解析 session/<project>/<session_id>.json, source_path 为对应的 message 目录
+fn read_opencode_meta(file_path: &Path) -> Option<SessionMeta> {
+    let content = fs::read_to_string(file_path).ok()?;
+    let obj: Value = serde_json::from_str(&content).ok()?;
+
+    let session_id = obj
+        .get("id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    if session_id.is_empty() {
+        return None;
+    }
+
+    let title = obj
+        .get("title")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let project_dir = obj
+        .get("directory")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let created_at = obj
+        .get("time")
+        .and_then(|t| t.get("created"))
+        .and_then(|v| parse_timestamp(v));
+    let last_active_at = obj
+        .get("time")
+        .and_then(|t| t.get("updated"))
+        .and_then(|v| parse_timestamp(v))
+        .or_else(|| file_modified_ms(file_path));
+
+    let summary = title
+        .clone()
+        .or_else(|| project_dir.as_deref().and_then(path_basename));
+    let project_name = project_dir.as_deref().and_then(path_basename);
+    let title = native_title(title).or_else(|| project_name.clone());
+
+    // source_path 存储为 message 目录的路径（用于 load_session_messages）
+    let storage = file_path
+        .ancestors()
+        .find(|dir| dir.file_name().and_then(|n| n.to_str()) == Some("session"))?
+        .parent()?;
+    let msg_dir = storage.join("message").join(&session_id);
+
+    Some(SessionMeta {
+        provider_id: "opencode".to_string(),
+        session_id,
+        title,
+        summary,
+        project_dir,
+        project_name,
+        created_at,
+        last_active_at,
+        source_path: msg_dir.to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+fn parse_opencode_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
+    // path 是 message/<session_id>/ 目录
+    if !path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let storage = path
+        .parent()
+        .and_then(|p| p.parent())
+        .ok_or("无法确定 storage 目录")?;
+    let parts_root = storage.join("part");
+
+    let msg_files = collect_files_recursive(path, "json");
+    let mut messages = Vec::new();
+    let mut parts = Vec::new();
+
+    for msg_file in msg_files {
+        let content = match fs::read_to_string(&msg_file) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let obj: Value = match serde_json::from_str(&content) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+
+        let msg_id = obj
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        // 读取 parts, 文件名按创建顺序递增
+        let part_dir = parts_root.join(&msg_id);
+        if part_dir.is_dir() {
+            for part_file in collect_files_recursive(&part_dir, "json") {
+                let part_content = match fs::read_to_string(&part_file) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let part_obj: Value = match serde_json::from_str(&part_content) {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                };
+                let order = part_file.to_string_lossy().to_string();
+                parts.push((msg_id.clone(), order, part_obj));
+            }
+        }
+        messages.push((msg_id, obj));
+    }
+
+    Ok(build_opencode_messages(messages, parts))
+}
+
+/// 由消息与 part 组装对话, parts 为 (消息 id, 排序键, part) 且按排序键决定顺序
+/// 结果按消息创建时间排序
+fn build_opencode_messages(
+    messages: Vec<(String, Value)>,
+    mut parts: Vec<(String, String, Value)>,
+) -> Vec<SessionMessage> {
+    parts.sort_by(|a, b| a.1.cmp(&b.1));
+    let mut blocks_by_message: HashMap<String, Vec<ContentBlock>> = HashMap::new();
+    for (msg_id, _, part) in &parts {
+        blocks_by_message
+            .entry(msg_id.clone())
+            .or_default()
+            .extend(parse_opencode_part(part));
+    }
+
+    let mut result: Vec<SessionMessage> = messages
+        .into_iter()
+        .filter_map(|(msg_id, obj)| {
+            let role = obj
+                .get("role")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            let ts = obj
+                .get("time")
+                .and_then(|t| t.get("created"))
+                .and_then(|v| parse_timestamp(v));
+            let blocks = blocks_by_message.remove(&msg_id).unwrap_or_default();
+            let mut message = SessionMessage::from_blocks(role, ts, blocks)?;
+            message.model = obj
+                .get("modelID")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            message.usage = obj.get("tokens").map(parse_opencode_usage);
+            Some(message)
+        })
+        .collect();
+
+    // 按时间排序
+    result.sort_by_key(|message| message.ts);
+    result
+}
+
+/// OpenCode 的 tokens 字段, 缓存部分单独记录在 cache 下
+fn parse_opencode_usage(tokens: &Value) -> TokenUsage {
+    let count = |pointer: &str| {
+        tokens
+            .pointer(pointer)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    TokenUsage {
+        input: count("/input"),
+        output: count("/output"),
+        cache_read: count("/cache/read"),
+        cache_write: count("/cache/write"),
+        reasoning: count("/reasoning"),
+    }
+}
+
+/// 单个 part 转为内容块, tool part 同时包含调用与结果
+fn parse_opencode_part(part: &Value) -> Vec<ContentBlock> {
+    let str_field = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+
+    match part.get("type").and_then(|v| v.as_str()) {
+        Some("text") => str_field(part, "text")
+            .map(|text| ContentBlock::Text { text })
+            .into_iter()
+            .collect(),
+        Some("reasoning") => str_field(part, "text")
+            .map(|text| ContentBlock::Reasoning { text })
+            .into_iter()
+            .collect(),
+        Some("tool") => {
+            let Some(name) = str_field(part, "tool") else {
+                return Vec::new();
+            };
+            let id = str_field(part, "callID");
+            let state = part.get("state").unwrap_or(&Value::Null);
+            let mut blocks = vec![ContentBlock::ToolCall {
+                id: id.clone(),
+                name,
+                input: state.get("input").cloned().unwrap_or(Value::Null),
+            }];
+            match state.get("status").and_then(|v| v.as_str()) {
+                Some("completed") => blocks.push(ContentBlock::ToolResult {
+                    call_id: id,
+                    output: tool_output_text(state.get("output").unwrap_or(&Value::Null)),
+                    is_error: false,
+                }),
+                Some("error") => blocks.push(ContentBlock::ToolResult {
+                    call_id: id,
+                    output: tool_output_text(state.get("error").unwrap_or(&Value::Null)),
+                    is_error: true,
+                }),
+                _ => {}
+            }
+            blocks
+        }
+        Some("file") => {
+            let mime = str_field(part, "mime");
+            let kind = if mime.as_deref().is_some_and(|m| m.starts_with("image/")) {
+                AttachmentKind::Image
+            } else {
+                AttachmentKind::File
+            };
+            vec![ContentBlock::Attachment {
+                kind,
+                name: str_field(part, "filename"),
+                media_type: mime,
+                // 内联的 data URL 不保留
+                uri: str_field(part, "url").filter(|url| !url.starts_with("data:")),
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// OpenCode 对话的全部文件: message 目录、各消息的 part 目录与 session 文件
+/// opencode.db 中的对应记录由 detach 在删除时一并清理
+fn opencode_session_paths(msg_dir: &Path, session_id: &str) -> Result<Vec<PathBuf>, String> {
+    let storage = msg_dir
+        .parent()
+        .and_then(|p| p.parent())
+        .ok_or("无法确定 storage 目录")?;
+    let mut paths = Vec::new();
+
+    // 1. 按 message 文件中的 id 收集 part/<message_id>/ 目录
+    if msg_dir.is_dir() {
+        let parts_root = storage.join("part");
+        for msg_file in collect_files_recursive(msg_dir, "json") {
+            if let Ok(content) = fs::read_to_string(&msg_file) {
+                if let Ok(obj) = serde_json::from_str::<Value>(&content) {
+                    if let Some(id) = obj.get("id").and_then(|v| v.as_str()) {
+                        let part_dir = parts_root.join(id);
+                        if part_dir.is_dir() {
+                            paths.push(part_dir);
+                        }
+                    }
+                }
+            }
+        }
+        // 2. message/<session_id>/ 目录
+        paths.push(msg_dir.to_path_buf());
+    }
+
+    // 3. session/<project>/<session_id>.json
+    let file_name = format!("{}.json", session_id);
+    paths.extend(
+        collect_files_recursive(&storage.join("session"), "json")
+            .into_iter()
+            .filter(|file| {
+                file.file_name()
+                    .is_some_and(|name| name == file_name.as_str())
+            }),
+    );
+
+    Ok(paths)
+}
+
+// opencode.db
+
+/// 数据库中的对话: 数据源路径为 <opencode.db>/<session_id>
+fn db_source(source: &Path) -> Option<(&Path, &str)> {
+    let db = source.parent()?;
+    if db.file_name()? != DB_FILE_NAME {
+        return None;
+    }
+    Some((db, source.file_name()?.to_str()?))
+}
+
+/// 数据源所属的数据库, storage 中的对话为 storage 上级目录中的 opencode.db
+fn db_path_for(source: &Path) -> Option<PathBuf> {
+    match db_source(source) {
+        Some((db, _)) => Some(db.to_path_buf()),
+        // source 为 storage/message/<session_id>
+        None => source
+            .parent()?
+            .parent()
+            .map(|storage| storage.with_file_name(DB_FILE_NAME)),
+    }
+}
+
+fn open_db(db: &Path, writable: bool) -> rusqlite::Result<Connection> {
+    let flags = if writable {
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    };
+    let conn = Connection::open_with_flags(db, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// 读取数据库中各对话的指纹, 数据库不存在或无法读取时返回 None
+fn with_db_index<T>(db: &Path, op: impl FnOnce(&HashMap<String, Fingerprint>) -> T) -> Option<T> {
+    if !db.is_file() {
+        return None;
+    }
+    let mut wal = db.as_os_str().to_owned();
+    wal.push("-wal");
+    let fingerprint = (Fingerprint::of(db), Fingerprint::of(Path::new(&wal)));
+
+    let mut guard = DB_INDEX
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let fresh = guard
+        .as_ref()
+        .is_some_and(|index| index.db == db && index.fingerprint == fingerprint);
+    if !fresh {
+        *guard = Some(DbIndex {
+            db: db.to_path_buf(),
+            fingerprint,
+            sessions: read_db_index(db).ok()?,
+        });
+    }
+    guard.as_ref().map(|index| op(&index.sessions))
+}
+
+fn read_db_index(db: &Path) -> rusqlite::Result<HashMap<String, Fingerprint>> {
+    let conn = open_db(db, false)?;
+    let mut stmt = conn.prepare(
+        "select s.id, s.time_updated, \
+         (select count(*) from message m where m.session_id = s.id) from session s",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let updated: i64 = row.get(1)?;
+        let count: i64 = row.get(2)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            Fingerprint::new(updated.max(0) as u64, count.max(0) as u64),
+        ))
+    })?;
+    rows.collect()
+}
+
+fn read_db_meta(db: &Path, session_id: &str) -> Option<SessionMeta> {
+    let conn = open_db(db, false).ok()?;
+    let (title, project_dir, created_at, updated_at) = conn
+        .query_row(
+            "select title, directory, time_created, time_updated from session where id = ?1",
+            [session_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            },
+        )
+        .ok()?;
+
+    let title = title.filter(|title| !title.is_empty());
+    let summary = title
+        .clone()
+        .or_else(|| project_dir.as_deref().and_then(path_basename));
+    let project_name = project_dir.as_deref().and_then(path_basename);
+    let title = native_title(title).or_else(|| project_name.clone());
+    Some(SessionMeta {
+        provider_id: "opencode".to_string(),
+        session_id: session_id.to_string(),
+        title,
+        summary,
+        project_dir,
+        project_name,
+        created_at: created_at.and_then(|ts| u64::try_from(ts).ok()),
+        last_active_at: updated_at.and_then(|ts| u64::try_from(ts).ok()),
+        source_path: db.join(session_id).to_string_lossy().to_string(),
+        message_count: None,
+        usage: None,
+        project: None,
+    })
+}
+
+/// 读取对话的消息, message 与 part 表的 data 列为去掉 id 字段的原 JSON
+fn load_db_messages(db: &Path, session_id: &str) -> rusqlite::Result<Vec<SessionMessage>> {
+    let conn = open_db(db, false)?;
+    let json = |text: String| serde_json::from_str::<Value>(&text).unwrap_or(Value::Null);
+
+    let messages = conn
+        .prepare("select id, data from message where session_id = ?1")?
+        .query_map([session_id], |row| {
+            Ok((row.get::<_, String>(0)?, json(row.get(1)?)))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let parts = conn
+        .prepare("select message_id, id, data from part where session_id = ?1")?
+        .query_map([session_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, json(row.get(2)?)))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(build_opencode_messages(messages, parts))
+}
+
+/// 由快照中的 message 与 part 记录组装对话
+fn snapshot_messages(snapshot: &DbSnapshot) -> Vec<SessionMessage> {
+    let rows = |table: &str, columns: &[&str]| -> Vec<Vec<Value>> {
+        let Some(table) = snapshot.tables.iter().find(|t| t.name == table) else {
+            return Vec::new();
+        };
+        let indexes: Vec<Option<usize>> = columns
+            .iter()
+            .map(|column| table.columns.iter().position(|c| c == column))
+            .collect();
+        table
+            .rows
+            .iter()
+            .map(|row| {
+                indexes
+                    .iter()
+                    .map(|index| {
+                        index
+                            .and_then(|i| row.get(i))
+                            .cloned()
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let json = |value: &Value| {
+        serde_json::from_str(value.as_str().unwrap_or_default()).unwrap_or(Value::Null)
+    };
+
+    let messages = rows("message", &["id", "data"])
+        .iter()
+        .map(|row| (text(&row[0]), json(&row[1])))
+        .collect();
+    let parts = rows("part", &["message_id", "id", "data"])
+        .iter()
+        .map(|row| (text(&row[0]), text(&row[1]), json(&row[2])))
+        .collect();
+    build_opencode_messages(messages, parts)
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// 与对话相关的表及其筛选条件 (?1 为对话 id): session 表本身、含 session_id 列的表,
+/// 以及通过外键间接引用这些表的表; 按外键依赖排序, 被引用的表在前
+fn session_tables(conn: &Connection) -> rusqlite::Result<Vec<(String, String)>> {
+    let tables: Vec<String> = conn
+        .prepare(
+            "select name from sqlite_master where type = 'table' and name not like 'sqlite_%'",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    if !tables.iter().any(|table| table == "session") {
+        return Ok(Vec::new());
+    }
+
+    // 表名 -> (被引用的表, 本表列, 被引用列)
+    let mut foreign_keys: HashMap<&str, Vec<(String, String, String)>> = HashMap::new();
+    let mut selected: Vec<(String, String)> = vec![("session".to_string(), "id = ?1".to_string())];
+    for table in &tables {
+        let keys = conn
+            .prepare("select \"table\", \"from\", \"to\" from pragma_foreign_key_list(?1)")?
+            .query_map([table], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?
+                        .unwrap_or_else(|| "rowid".to_string()),
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        foreign_keys.insert(table, keys);
+
+        let has_session_id: bool = conn.query_row(
+            "select count(*) > 0 from pragma_table_info(?1) where name = 'session_id'",
+            [table],
+            |row| row.get(0),
+        )?;
+        if has_session_id && table != "session" {
+            selected.push((table.clone(), "session_id = ?1".to_string()));
+        }
+    }
+
+    loop {
+        let mut added = false;
+        for table in &tables {
+            if selected.iter().any(|(name, _)| name == table) {
+                continue;
+            }
+            let condition = foreign_keys[table.as_str()]
+                .iter()
+                .find_map(|(parent, from, to)| {
+                    let (_, parent_condition) = selected.iter().find(|(name, _)| name == parent)?;
+                    Some(format!(
+                        "{} in (select {} from {} where {})",
+                        quote_identifier(from),
+                        quote_identifier(to),
+                        quote_identifier(parent),
+                        parent_condition
+                    ))
+                });
+            if let Some(condition) = condition {
+                selected.push((table.clone(), condition));
+                added = true;
+            }
+        }
+        if !added {
+            break;
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(selected.len());
+    while !selected.is_empty() {
+        let ready = selected
+            .iter()
+            .position(|(name, _)| {
+                foreign_keys[name.as_str()].iter().all(|(parent, ..)| {
+                    parent == name || !selected.iter().any(|(other, _)| other == parent)
+                })
+            })
+            // 存在循环引用时按原顺序
+            .unwrap_or(0);
+        ordered.push(selected.remove(ready));
+    }
+    Ok(ordered)
+}
+
+fn sql_to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(n) => n.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).to_string()),
+        ValueRef::Blob(data) => serde_json::json!({ BLOB_KEY: encode_base64(data) }),
+    }
+}
+
+fn json_to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => n
+            .as_i64()
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default())),
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => other
+            .get(BLOB_KEY)
+            .and_then(|v| v.as_str())
+            .and_then(decode_base64)
+            .map(SqlValue::Blob)
+            .unwrap_or_else(|| SqlValue::Text(other.to_string())),
+    }
+}
+
+/// 在一个事务中读取并删除对话的全部记录, 子表先删除
+/// 事务开始前关闭本连接的外键约束 (内置的 SQLite 默认开启), 以免级联删除未保存到快照中的记录
+fn remove_session_rows(db: &Path, session_id: &str) -> rusqlite::Result<Option<DbSnapshot>> {
+    let mut conn = open_db(db, true)?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let tables = session_tables(&tx)?;
+    if tables.is_empty() {
+        return Ok(None);
+    }
+
+    let mut snapshot = DbSnapshot {
+        db: db.to_string_lossy().to_string(),
+        tables: Vec::new(),
+    };
+    for (name, condition) in &tables {
+        let mut stmt = tx.prepare(&format!(
+            "select * from {} where {}",
+            quote_identifier(name),
+            condition
+        ))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let rows = stmt
+            .query_map([session_id], |row| {
+                (0..columns.len())
+                    .map(|i| row.get_ref(i).map(sql_to_json))
+                    .collect()
+            })?
+            .collect::<rusqlite::Result<Vec<Vec<Value>>>>()?;
+        if !rows.is_empty() {
+            snapshot.tables.push(TableRows {
+                name: name.clone(),
+                columns,
+                rows,
+            });
+        }
+    }
+
+    for (name, condition) in tables.iter().rev() {
+        tx.execute(
+            &format!("delete from {} where {}", quote_identifier(name), condition),
+            [session_id],
+        )?;
+    }
+    tx.commit()?;
+    Ok(Some(snapshot))
+}
+
+/// 在一个事务中替换对话的 message 与 part 记录的 data 列, 返回被修改行的原内容
+/// 替换后不再是合法 JSON 的行保持不变
+fn redact_session_rows(
+    db: &Path,
+    session_id: &str,
+    redact: &dyn Fn(&str) -> Option<String>,
+) -> rusqlite::Result<Vec<Value>> {
+    let mut conn = open_db(db, true)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let mut original = Vec::new();
+    for table in ["message", "part"] {
+        let rows: Vec<(String, String)> = tx
+            .prepare(&format!(
+                "select id, data from {} where session_id = ?1",
+                table
+            ))?
+            .query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        for (id, data) in rows {
+            let Some(redacted) = redact(&data) else {
+                continue;
+            };
+            if serde_json::from_str::<Value>(&redacted).is_err() {
+                continue;
+            }
+            tx.execute(
+                &format!("update {} set data = ?1 where id = ?2", table),
+                [&redacted, &id],
+            )?;
+            original.push(serde_json::json!({ "table": table, "id": id, "data": data }));
+        }
+    }
+    tx.commit()?;
+    Ok(original)
+}
+
+/// 在一个事务中写回快照中的记录, 被引用的表先写入
+/// 同样关闭外键约束, insert or replace 替换已有记录时不会级联删除其子记录
+fn restore_session_rows(snapshot: &DbSnapshot) -> rusqlite::Result<()> {
+    let mut conn = open_db(Path::new(&snapshot.db), true)?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    for table in &snapshot.tables {
+        let columns: Vec<String> = table.columns.iter().map(|c| quote_identifier(c)).collect();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        let mut stmt = tx.prepare(&format!(
+            "insert or replace into {} ({}) values ({})",
+            quote_identifier(&table.name),
+            columns.join(", "),
+            placeholders.join(", ")
+        ))?;
+        for row in &table.rows {
+            stmt.execute(rusqlite::params_from_iter(row.iter().map(json_to_sql)))?;
+        }
+    }
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn row_count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("select count(*) from {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn session_rows_round_trip_with_foreign_keys() {
+        let dir = env::temp_dir().join(format!("anti-power-opencode-db-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let db = dir.join(DB_FILE_NAME);
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "create table session (id text primary key, title text);
+             create table message (id text primary key, session_id text not null
+                 references session(id) on delete cascade, data text);
+             create table part (id text primary key, message_id text not null
+                 references message(id) on delete cascade, data text);
+             insert into session values ('s1', 'one'), ('s2', 'two');
+             insert into message values ('m1', 's1', '{}'), ('m2', 's2', '{}');
+             insert into part values ('p1', 'm1', '{}'), ('p2', 'm2', '{}');",
+        )
+        .unwrap();
+
+        let snapshot = remove_session_rows(&db, "s1").unwrap().unwrap();
+        let tables: Vec<(&str, usize)> = snapshot
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.rows.len()))
+            .collect();
+        assert_eq!(tables, vec![("session", 1), ("message", 1), ("part", 1)]);
+        for table in ["session", "message", "part"] {
+            assert_eq!(row_count(&conn, table), 1);
+        }
+
+        restore_session_rows(&snapshot).unwrap();
+        // 再次写回时替换已有记录, 不应级联删除子记录
+        restore_session_rows(&snapshot).unwrap();
+        for table in ["session", "message", "part"] {
+            assert_eq!(row_count(&conn, table), 2);
+        }
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
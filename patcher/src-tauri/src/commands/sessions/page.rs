@@ -0,0 +1,602 @@
+//! 对话消息分页加载
+//!
+//! 首次访问对话时完整解析一次, 为按行存储的记录建立消息到行偏移的索引,
+//! 之后按下标随机读取只需 seek 到对应行; 其余 provider 缓存完整解析结果.
+//! 超长的内容块在返回时截断, 前端按需通过 load_message_block 读取完整内容
+
+use super::provider::{find_provider, LineParser, SessionProvider};
+use super::usage::TokenUsage;
+use super::util::{run_blocking, Fingerprint};
+use super::{ContentBlock, SessionMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+/// 消息分块事件
+pub const MESSAGES_CHUNK_EVENT: &str = "session-messages:chunk";
+/// 消息推送结束事件 (完成、失败或取消)
+pub const MESSAGES_DONE_EVENT: &str = "session-messages:done";
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+/// 每个分块事件携带的消息数
+const STREAM_CHUNK_SIZE: usize = 100;
+/// 内容块默认的最大字符数
+pub const DEFAULT_MAX_BLOCK_CHARS: usize = 20_000;
+/// 内存中保留索引的对话数
+const MAX_INDEXED_SESSIONS: usize = 8;
+
+/// 最近访问的对话索引, 按访问顺序排列
+static INDEXES: Mutex<Vec<(String, Fingerprint, Arc<MessageIndex>)>> = Mutex::new(Vec::new());
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 进行中的推送及其取消标记
+static ACTIVE_STREAMS: Mutex<Option<HashMap<u64, Arc<AtomicBool>>>> = Mutex::new(None);
+
+enum MessageIndex {
+    /// 各消息所在行的偏移, 以及依赖其他行的字段
+    Lines(Vec<LineEntry>),
+    /// 不支持逐行读取的 provider 保留完整消息
+    Loaded(Vec<SessionMessage>),
+}
+
+struct LineEntry {
+    offset: u64,
+    model: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+impl MessageIndex {
+    fn len(&self) -> usize {
+        match self {
+            MessageIndex::Lines(entries) => entries.len(),
+            MessageIndex::Loaded(messages) => messages.len(),
+        }
+    }
+}
+
+/// 分页请求
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessagePageRequest {
+    pub provider_id: String,
+    pub source_path: String,
+    /// 起始下标; from_end 为 true 时为距末尾的消息数
+    #[serde(default)]
+    pub offset: usize,
+    /// 分页默认 200 条, 推送默认到末尾
+    pub limit: Option<usize>,
+    /// 从末尾计算范围, 用于加载最近的 N 条
+    #[serde(default)]
+    pub from_end: bool,
+    /// 内容块超过该字符数时截断
+    pub max_block_chars: Option<usize>,
+}
+
+/// 一页消息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessagePage {
+    /// 对话的消息总数
+    pub total: usize,
+    /// 本页第一条消息的下标
+    pub offset: usize,
+    pub messages: Vec<PagedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PagedMessage {
+    /// 消息在对话中的下标
+    pub index: usize,
+    #[serde(flatten)]
+    pub message: SessionMessage,
+    /// 被截断的内容块下标
+    pub truncated_blocks: Vec<usize>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessagesChunkEvent {
+    stream_id: u64,
+    total: usize,
+    messages: Vec<PagedMessage>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessagesDoneEvent {
+    stream_id: u64,
+    canceled: bool,
+    error: Option<String>,
+}
+
+impl MessagePageRequest {
+    fn range(&self, total: usize, default_limit: usize) -> Range<usize> {
+        let limit = self.limit.unwrap_or(default_limit);
+        if self.from_end {
+            let end = total.saturating_sub(self.offset);
+            end.saturating_sub(limit)..end
+        } else {
+            let start = self.offset.min(total);
+            start..start.saturating_add(limit).min(total)
+        }
+    }
+}
+
+/// 获取对话的消息索引, 数据源未变化时复用
+fn message_index(provider: &dyn SessionProvider, path: &Path) -> Result<Arc<MessageIndex>, String> {
+    let key = format!("{}:{}", provider.id(), path.to_string_lossy());
+    let fingerprint = provider.fingerprint(path);
+    {
+        let mut indexes = INDEXES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(position) = indexes.iter().position(|(k, _, _)| *k == key) {
+            let entry = indexes.remove(position);
+            if entry.1 == fingerprint {
+                let index = entry.2.clone();
+                indexes.push(entry);
+                return Ok(index);
+            }
+        }
+    }
+
+    let messages = provider.load_messages(path)?;
+    let index = if provider.line_parser().is_some()
+        && messages.iter().all(|message| message.line_offset.is_some())
+    {
+        MessageIndex::Lines(
+            messages
+                .into_iter()
+                .map(|message| LineEntry {
+                    offset: message.line_offset.unwrap_or_default(),
+                    model: message.model,
+                    usage: message.usage,
+                })
+                .collect(),
+        )
+    } else {
+        MessageIndex::Loaded(messages)
+    };
+    let index = Arc::new(index);
+
+    let mut indexes = INDEXES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    indexes.retain(|(k, _, _)| *k != key);
+    if indexes.len() >= MAX_INDEXED_SESSIONS {
+        indexes.remove(0);
+    }
+    indexes.push((key, fingerprint, index.clone()));
+    Ok(index)
+}
+
+/// 读取下标范围内的完整消息
+fn read_messages(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    index: &MessageIndex,
+    range: Range<usize>,
+) -> Result<Vec<SessionMessage>, String> {
+    let entries = match index {
+        MessageIndex::Loaded(messages) => return Ok(messages[range].to_vec()),
+        MessageIndex::Lines(entries) => &entries[range],
+    };
+    let parse = provider.line_parser().ok_or("该 provider 不支持逐行读取")?;
+    let file = fs::File::open(path).map_err(|e| format!("读取记录失败: {}", e))?;
+    let mut reader = BufReader::new(file);
+    let mut messages = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let mut message = parse_line_at(&mut reader, parse, entry.offset)?;
+        message.model = entry.model.clone();
+        message.usage = entry.usage;
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
+
+/// 解析 offset 处的一行记录
+fn parse_line_at(
+    reader: &mut BufReader<fs::File>,
+    parse: LineParser,
+    offset: u64,
+) -> Result<SessionMessage, String> {
+    reader
+        .seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("读取记录失败: {}", e))?;
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("读取记录失败: {}", e))?;
+    let mut message = parse(line.trim_end()).ok_or("记录已变化, 请重新加载对话")?;
+    message.line_offset = Some(offset);
+    Ok(message)
+}
+
+/// 读取按行存储的记录中 offset 处的单条消息 (不含依赖其他行的 model 与 usage)
+pub fn read_message_at(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    offset: u64,
+) -> Result<SessionMessage, String> {
+    let parse = provider.line_parser().ok_or("该 provider 不支持逐行读取")?;
+    let file = fs::File::open(path).map_err(|e| format!("读取记录失败: {}", e))?;
+    parse_line_at(&mut BufReader::new(file), parse, offset)
+}
+
+/// 跟随对话的起点: 当前的消息数, 以及按行存储的记录中索引覆盖到的字节偏移
+/// (最后一条消息所在行之后); 不支持逐行读取的 provider 偏移为 None
+pub fn tail_start(
+    provider: &dyn SessionProvider,
+    path: &Path,
+) -> Result<(usize, Option<u64>), String> {
+    let index = message_index(provider, path)?;
+    let entries = match &*index {
+        MessageIndex::Loaded(messages) => return Ok((messages.len(), None)),
+        MessageIndex::Lines(entries) => entries,
+    };
+    let Some(last) = entries.last() else {
+        return Ok((0, Some(0)));
+    };
+    let file = fs::File::open(path).map_err(|e| format!("读取记录失败: {}", e))?;
+    let mut reader = BufReader::new(file);
+    reader
+        .seek(SeekFrom::Start(last.offset))
+        .map_err(|e| format!("读取记录失败: {}", e))?;
+    let mut line = Vec::new();
+    let read = reader
+        .read_until(b'\n', &mut line)
+        .map_err(|e| format!("读取记录失败: {}", e))?;
+    Ok((entries.len(), Some(last.offset + read as u64)))
+}
+
+/// 读取下标范围内的消息, 与分页共用索引; 超出消息数的部分被忽略
+pub fn read_range(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    range: Range<usize>,
+) -> Result<Vec<SessionMessage>, String> {
+    let index = message_index(provider, path)?;
+    let end = range.end.min(index.len());
+    read_messages(provider, path, &index, range.start.min(end)..end)
+}
+
+/// 从 offset 开始逐行解析追加的记录, 返回解析出的消息与下次读取的位置;
+/// 末尾尚未写完 (没有换行符) 的行留到下次读取.
+/// 记录比 offset 短或 offset 不在行首时返回 None, 表示记录已被改写
+pub fn read_appended(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    offset: u64,
+) -> Result<Option<(Vec<SessionMessage>, u64)>, String> {
+    let parse = provider.line_parser().ok_or("该 provider 不支持逐行读取")?;
+    let read_err = |e: std::io::Error| format!("读取记录失败: {}", e);
+    let file = fs::File::open(path).map_err(read_err)?;
+    let len = file.metadata().map_err(read_err)?.len();
+    if len < offset {
+        return Ok(None);
+    }
+    let mut reader = BufReader::new(file);
+    if offset > 0 {
+        reader.seek(SeekFrom::Start(offset - 1)).map_err(read_err)?;
+        let mut previous = [0u8; 1];
+        reader.read_exact(&mut previous).map_err(read_err)?;
+        if previous[0] != b'\n' {
+            return Ok(None);
+        }
+    }
+
+    let mut messages = Vec::new();
+    let mut end = offset;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line).map_err(read_err)?;
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+        if let Some(mut message) = parse(text.trim_end()) {
+            message.line_offset = Some(end);
+            messages.push(message);
+        }
+        end += read as u64;
+    }
+    Ok(Some((messages, end)))
+}
+
+/// 截断超长的内容块并重新生成纯文本投影, 返回被截断的块下标
+fn truncate_blocks(message: &mut SessionMessage, max_chars: usize) -> Vec<usize> {
+    let cut = |text: &mut String| match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            text.truncate(end);
+            true
+        }
+        None => false,
+    };
+
+    let mut truncated = Vec::new();
+    for (index, block) in message.blocks.iter_mut().enumerate() {
+        let was_cut = match block {
+            ContentBlock::Text { text }
+            | ContentBlock::Reasoning { text }
+            | ContentBlock::SystemEvent { text } => cut(text),
+            ContentBlock::ToolResult { output, .. } => cut(output),
+            // 超长的参数以截断后的 JSON 文本代替
+            ContentBlock::ToolCall { input, .. } => {
+                let mut raw = match &*input {
+                    Value::String(raw) => raw.clone(),
+                    other => other.to_string(),
+                };
+                let was_cut = cut(&mut raw);
+                if was_cut {
+                    *input = Value::String(raw);
+                }
+                was_cut
+            }
+            ContentBlock::Attachment { .. } => false,
+        };
+        if was_cut {
+            truncated.push(index);
+        }
+    }
+
+    if !truncated.is_empty() {
+        message.content = message
+            .blocks
+            .iter()
+            .map(ContentBlock::plain_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+    truncated
+}
+
+pub fn to_paged(
+    messages: Vec<SessionMessage>,
+    start: usize,
+    max_block_chars: usize,
+) -> Vec<PagedMessage> {
+    messages
+        .into_iter()
+        .enumerate()
+        .map(|(offset, mut message)| {
+            let truncated_blocks = truncate_blocks(&mut message, max_block_chars);
+            PagedMessage {
+                index: start + offset,
+                message,
+                truncated_blocks,
+            }
+        })
+        .collect()
+}
+
+fn with_active_streams<T>(op: impl FnOnce(&mut HashMap<u64, Arc<AtomicBool>>) -> T) -> T {
+    let mut guard = ACTIVE_STREAMS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    op(guard.get_or_insert_with(HashMap::new))
+}
+
+fn check_source<'a>(
+    provider: &dyn SessionProvider,
+    source_path: &'a str,
+) -> Result<&'a Path, String> {
+    let path = Path::new(source_path);
+    if !provider.source_exists(path) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+    Ok(path)
+}
+
+// Tauri 命令
+
+/// 按下标范围加载一页消息, from_end 为 true 时从末尾计算
+/// 首次加载需要读取整个记录建立索引, 在后台线程中执行
+#[tauri::command]
+pub async fn load_session_page(request: MessagePageRequest) -> Result<MessagePage, String> {
+    run_blocking(move || load_page(&request)).await
+}
+
+fn load_page(request: &MessagePageRequest) -> Result<MessagePage, String> {
+    let provider = find_provider(&request.provider_id)?;
+    let path = check_source(provider, &request.source_path)?;
+    let index = message_index(provider, path)?;
+    let total = index.len();
+    let range = request.range(total, DEFAULT_PAGE_SIZE);
+    let start = range.start;
+    let messages = read_messages(provider, path, &index, range)?;
+
+    Ok(MessagePage {
+        total,
+        offset: start,
+        messages: to_paged(
+            messages,
+            start,
+            request.max_block_chars.unwrap_or(DEFAULT_MAX_BLOCK_CHARS),
+        ),
+    })
+}
+
+/// 在后台按顺序推送范围内的消息, 立即返回推送 id
+/// 消息通过 session-messages:chunk 事件分块推送, 结束时发送 session-messages:done
+#[tauri::command]
+pub fn start_message_stream(app: AppHandle, request: MessagePageRequest) -> Result<u64, String> {
+    let provider = find_provider(&request.provider_id)?;
+    check_source(provider, &request.source_path)?;
+
+    let stream_id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    let cancel = Arc::new(AtomicBool::new(false));
+    with_active_streams(|streams| streams.insert(stream_id, cancel.clone()));
+
+    std::thread::spawn(move || {
+        let path = Path::new(&request.source_path);
+        let max_block_chars = request.max_block_chars.unwrap_or(DEFAULT_MAX_BLOCK_CHARS);
+        let result = message_index(provider, path).and_then(|index| {
+            let total = index.len();
+            let range = request.range(total, usize::MAX);
+            let mut start = range.start;
+            while start < range.end {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let end = (start + STREAM_CHUNK_SIZE).min(range.end);
+                let messages = read_messages(provider, path, &index, start..end)?;
+                let _ = app.emit(
+                    MESSAGES_CHUNK_EVENT,
+                    MessagesChunkEvent {
+                        stream_id,
+                        total,
+                        messages: to_paged(messages, start, max_block_chars),
+                    },
+                );
+                start = end;
+            }
+            Ok(())
+        });
+        with_active_streams(|streams| streams.remove(&stream_id));
+
+        let _ = app.emit(
+            MESSAGES_DONE_EVENT,
+            MessagesDoneEvent {
+                stream_id,
+                canceled: cancel.load(Ordering::Relaxed),
+                error: result.err(),
+            },
+        );
+    });
+
+    Ok(stream_id)
+}
+
+/// 取消进行中的消息推送, 返回该推送是否仍在进行
+#[tauri::command]
+pub fn cancel_message_stream(stream_id: u64) -> Result<bool, String> {
+    Ok(with_active_streams(|streams| {
+        match streams.get(&stream_id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }))
+}
+
+/// 读取单个内容块的完整内容, 用于展开被截断的块
+#[tauri::command]
+pub async fn load_message_block(
+    provider_id: String,
+    source_path: String,
+    message_index: usize,
+    block_index: usize,
+) -> Result<ContentBlock, String> {
+    run_blocking(move || {
+        let provider = find_provider(&provider_id)?;
+        let path = check_source(provider, &source_path)?;
+        let index = self::message_index(provider, path)?;
+        if message_index >= index.len() {
+            return Err("消息不存在, 请重新加载对话".to_string());
+        }
+        read_messages(provider, path, &index, message_index..message_index + 1)?
+            .pop()
+            .and_then(|message| message.blocks.into_iter().nth(block_index))
+            .ok_or_else(|| "内容块不存在".to_string())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::{env, process};
+
+    fn request(offset: usize, limit: Option<usize>, from_end: bool) -> MessagePageRequest {
+        MessagePageRequest {
+            provider_id: "claude".to_string(),
+            source_path: String::new(),
+            offset,
+            limit,
+            from_end,
+            max_block_chars: None,
+        }
+    }
+
+    #[test]
+    fn range_from_start_clamps_to_total() {
+        assert_eq!(request(0, None, false).range(500, 200), 0..200);
+        assert_eq!(request(450, None, false).range(500, 200), 450..500);
+        assert_eq!(request(600, Some(10), false).range(500, 200), 500..500);
+        assert_eq!(request(5, Some(usize::MAX), false).range(20, 200), 5..20);
+    }
+
+    #[test]
+    fn range_from_end_counts_back_from_total() {
+        assert_eq!(request(0, Some(50), true).range(500, 200), 450..500);
+        assert_eq!(request(100, Some(50), true).range(500, 200), 350..400);
+        assert_eq!(request(0, None, true).range(120, 200), 0..120);
+        assert_eq!(request(600, Some(50), true).range(500, 200), 0..0);
+    }
+
+    fn line(uuid: &str, text: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"{}","message":{{"role":"user","content":"{}"}},"sessionId":"s","timestamp":"2025-01-01T00:00:00Z"}}"#,
+            uuid, text
+        )
+    }
+
+    #[test]
+    fn read_appended_parses_only_complete_new_lines() {
+        let dir = env::temp_dir().join(format!("anti-power-page-append-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("s.jsonl");
+        let first = format!("{}\n", line("a", "one"));
+        fs::write(&path, &first).unwrap();
+        let provider = find_provider("claude").unwrap();
+        let start = first.len() as u64;
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        let second = line("b", "two");
+        write!(
+            file,
+            "{}\n{{\"type\":\"summary\"}}\n{}",
+            second,
+            &line("c", "three")[..10]
+        )
+        .unwrap();
+        let (messages, end) = read_appended(provider, &path, start).unwrap().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "two");
+        assert_eq!(messages[0].line_offset, Some(start));
+        // 写到一半的行留到下次读取
+        let summary_end = start + second.len() as u64 + 1 + "{\"type\":\"summary\"}\n".len() as u64;
+        assert_eq!(end, summary_end);
+
+        writeln!(file, "{}", &line("c", "three")[10..]).unwrap();
+        let (messages, end) = read_appended(provider, &path, end).unwrap().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "three");
+        assert_eq!(end, fs::metadata(&path).unwrap().len());
+        let (messages, same) = read_appended(provider, &path, end).unwrap().unwrap();
+        assert!(messages.is_empty());
+        assert_eq!(same, end);
+
+        // 偏移不在行首或超出文件长度表示记录已被改写
+        assert!(read_appended(provider, &path, 3).unwrap().is_none());
+        fs::write(&path, &first).unwrap();
+        assert!(read_appended(provider, &path, end).unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,498 @@
+//! 对话所属项目的识别
+//!
+//! 将对话的工作目录解析为项目标识: 目录所在的 git 仓库根目录, 以及直接从 .git 读取的
+//! 远程地址与当前分支 (不调用 git). 有远程地址的仓库以规范化后的地址作为标识,
+//! 同一仓库的子目录与不同位置的克隆归为同一项目.
+//! 每次解析到的仓库记录在应用数据目录中, 仓库移动或删除后仍可由旧路径找到其标识与新位置
+
+use super::query::{matching_sessions, SessionQuery};
+use super::util::path_basename;
+use super::SessionMeta;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const STORE_FILE_NAME: &str = "session-projects.json";
+
+/// 已加载的项目记录, 首次解析时从磁盘读取
+static STORE: Mutex<Option<ProjectStore>> = Mutex::new(None);
+
+/// 对话所属的项目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInfo {
+    /// 项目标识, 有远程地址时为 remote:<规范化地址>, 否则为 path:<根目录>
+    pub key: String,
+    pub name: String,
+    /// 仓库根目录, 不在 git 仓库中时为工作目录本身
+    pub root: String,
+    pub remote_url: Option<String>,
+    pub branch: Option<String>,
+    /// 工作目录当前的位置: 目录仍存在时即其本身, 仓库移动后为新位置中的对应目录,
+    /// 找不到时为 None
+    pub resolved_dir: Option<String>,
+    pub is_git: bool,
+}
+
+/// 解析过的仓库, 按工作目录记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepoRecord {
+    root: String,
+    remote_url: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectStore {
+    /// 工作目录 -> 所在仓库
+    dirs: HashMap<String, RepoRecord>,
+}
+
+impl ProjectStore {
+    fn load() -> Self {
+        fs::read_to_string(get_store_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// 先写临时文件再替换, 避免中断时留下损坏的记录
+    fn save(&self) -> Result<(), String> {
+        let path = get_store_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        let content =
+            serde_json::to_string(self).map_err(|e| format!("序列化项目记录失败: {}", e))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content).map_err(|e| format!("写入项目记录失败: {}", e))?;
+        fs::rename(&temp, &path).map_err(|e| format!("写入项目记录失败: {}", e))
+    }
+
+    /// 不存在的目录: 查找其本身或上级目录的记录
+    fn recorded(&self, dir: &Path) -> Option<(PathBuf, RepoRecord)> {
+        dir.ancestors().find_map(|ancestor| {
+            let record = self.dirs.get(ancestor.to_string_lossy().as_ref())?;
+            dir.starts_with(&record.root)
+                .then(|| (PathBuf::from(&record.root), record.clone()))
+        })
+    }
+
+    /// 同一远程地址当前仍存在的仓库根目录
+    fn current_root(&self, key: &str, old_root: &Path) -> Option<PathBuf> {
+        let mut roots: Vec<&str> = self
+            .dirs
+            .values()
+            .filter(|record| {
+                record.remote_url.as_deref().map(remote_key).as_deref() == Some(key)
+                    && Path::new(&record.root) != old_root
+            })
+            .map(|record| record.root.as_str())
+            .collect();
+        roots.sort_unstable();
+        roots.dedup();
+        roots
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|root| root.join(".git").exists())
+    }
+}
+
+fn get_store_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join(STORE_FILE_NAME)
+}
+
+/// 向上查找包含 .git 的目录, 返回 (仓库根目录, git 目录)
+/// 工作树与子模块的 .git 是指向实际 git 目录的文件
+fn find_git_dir(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    dir.ancestors().find_map(|ancestor| {
+        let dot_git = ancestor.join(".git");
+        if dot_git.is_dir() {
+            return Some((ancestor.to_path_buf(), dot_git));
+        }
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let target = content.trim().strip_prefix("gitdir:")?.trim();
+        Some((ancestor.to_path_buf(), ancestor.join(target)))
+    })
+}
+
+/// 工作树的配置保存在主仓库的 git 目录中
+fn common_git_dir(git_dir: &Path) -> PathBuf {
+    fs::read_to_string(git_dir.join("commondir"))
+        .map(|content| git_dir.join(content.trim()))
+        .unwrap_or_else(|_| git_dir.to_path_buf())
+}
+
+/// 当前分支, 分离 HEAD 时为 None
+fn read_branch(git_dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(|branch| branch.to_string())
+}
+
+/// 读取远程地址, 优先 origin
+fn read_remote_url(git_dir: &Path) -> Option<String> {
+    let config = fs::read_to_string(common_git_dir(git_dir).join("config")).ok()?;
+    let mut remotes: Vec<(String, String)> = Vec::new();
+    let mut section: Option<String> = None;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .strip_prefix("remote ")
+                .map(|name| name.trim().trim_matches('"').to_string());
+        } else if let (Some(name), Some((key, value))) = (&section, line.split_once('=')) {
+            if key.trim().eq_ignore_ascii_case("url") {
+                remotes.push((name.clone(), value.trim().to_string()));
+            }
+        }
+    }
+    remotes
+        .iter()
+        .find(|(name, _)| name == "origin")
+        .or(remotes.first())
+        .map(|(_, url)| url.clone())
+}
+
+/// 规范化远程地址, 使 https 与 ssh 形式的同一仓库得到相同的标识:
+/// 去掉协议、用户名、端口与 .git 后缀, 统一为小写的 host/path
+fn remote_key(url: &str) -> String {
+    let url = url.trim();
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp 形式: user@host:path
+        None => match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') && host.len() > 1 => {
+                format!("{}/{}", host, path)
+            }
+            _ => url.to_string(),
+        },
+    };
+    let (host, path) = rest.split_once('/').unwrap_or((&rest, ""));
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host);
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    format!("remote:{}/{}", host, path).to_lowercase()
+}
+
+/// 单次查询内的解析器, 同一目录只解析一次
+struct Resolver<'a> {
+    store: &'a mut ProjectStore,
+    resolved: HashMap<String, ProjectInfo>,
+    changed: bool,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, dir: &str) -> ProjectInfo {
+        if let Some(info) = self.resolved.get(dir) {
+            return info.clone();
+        }
+        let path = Path::new(dir);
+        let info = if path.is_dir() {
+            self.resolve_existing(dir, path)
+        } else {
+            self.resolve_missing(dir, path)
+        };
+        self.resolved.insert(dir.to_string(), info.clone());
+        info
+    }
+
+    fn resolve_existing(&mut self, dir: &str, path: &Path) -> ProjectInfo {
+        let Some((root, git_dir)) = find_git_dir(path) else {
+            return plain_project(dir, Some(dir.to_string()));
+        };
+        let root = root.to_string_lossy().to_string();
+        let remote_url = read_remote_url(&git_dir);
+        let record = RepoRecord {
+            root: root.clone(),
+            remote_url: remote_url.clone(),
+        };
+        let previous = self.store.dirs.insert(dir.to_string(), record);
+        self.changed |= previous
+            .is_none_or(|previous| previous.root != root || previous.remote_url != remote_url);
+
+        ProjectInfo {
+            key: match &remote_url {
+                Some(url) => remote_key(url),
+                None => format!("path:{}", root),
+            },
+            name: path_basename(&root).unwrap_or_else(|| root.clone()),
+            branch: read_branch(&git_dir),
+            root,
+            remote_url,
+            resolved_dir: Some(dir.to_string()),
+            is_git: true,
+        }
+    }
+
+    /// 目录已不存在: 由记录找回所在仓库, 再查找同一远程地址的仓库当前的位置
+    fn resolve_missing(&mut self, dir: &str, path: &Path) -> ProjectInfo {
+        let Some((old_root, record)) = self.store.recorded(path) else {
+            return plain_project(dir, None);
+        };
+        let Some(remote_url) = record.remote_url else {
+            let root = record.root;
+            return ProjectInfo {
+                key: format!("path:{}", root),
+                name: path_basename(&root).unwrap_or_else(|| root.clone()),
+                root,
+                remote_url: None,
+                branch: None,
+                resolved_dir: None,
+                is_git: true,
+            };
+        };
+        let key = remote_key(&remote_url);
+        let current = self.store.current_root(&key, &old_root);
+        let resolved_dir = current.as_ref().map(|root| {
+            let moved = path
+                .strip_prefix(&old_root)
+                .ok()
+                .filter(|relative| !relative.as_os_str().is_empty())
+                .map(|relative| root.join(relative))
+                .filter(|moved| moved.is_dir())
+                .unwrap_or_else(|| root.clone());
+            moved.to_string_lossy().to_string()
+        });
+        let branch = current
+            .as_ref()
+            .and_then(|root| find_git_dir(root))
+            .and_then(|(_, git_dir)| read_branch(&git_dir));
+        let root = current.unwrap_or(old_root).to_string_lossy().to_string();
+
+        ProjectInfo {
+            key,
+            name: path_basename(&root).unwrap_or_else(|| root.clone()),
+            root,
+            remote_url: Some(remote_url),
+            branch,
+            resolved_dir,
+            is_git: true,
+        }
+    }
+}
+
+/// 不在 git 仓库中 (或无从得知) 的目录以其本身为项目
+fn plain_project(dir: &str, resolved_dir: Option<String>) -> ProjectInfo {
+    ProjectInfo {
+        key: format!("path:{}", dir),
+        name: path_basename(dir).unwrap_or_else(|| dir.to_string()),
+        root: dir.to_string(),
+        remote_url: None,
+        branch: None,
+        resolved_dir,
+        is_git: false,
+    }
+}
+
+/// 为带工作目录的对话填充所属项目, 并保存新解析到的仓库记录
+/// 记录无法保存时返回错误 (sessions 已填充), 仓库移动后将无法找回
+pub fn attach_projects(sessions: &mut [SessionMeta]) -> Result<(), String> {
+    let mut guard = STORE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let store = guard.get_or_insert_with(ProjectStore::load);
+    let mut resolver = Resolver {
+        store,
+        resolved: HashMap::new(),
+        changed: false,
+    };
+    // 先解析仍存在的目录, 已移动的仓库才能找到本次扫描中的新位置
+    for dir in sessions
+        .iter()
+        .filter_map(|meta| meta.project_dir.as_deref())
+    {
+        if Path::new(dir).is_dir() {
+            resolver.resolve(dir);
+        }
+    }
+    for meta in sessions.iter_mut() {
+        meta.project = meta.project_dir.as_deref().map(|dir| resolver.resolve(dir));
+    }
+
+    if resolver.changed {
+        resolver.store.save()?;
+    }
+    Ok(())
+}
+
+/// 按项目归组的对话统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectGroup {
+    pub project: ProjectInfo,
+    /// 该项目下对话出现过的工作目录
+    pub dirs: Vec<String>,
+    pub provider_ids: Vec<String>,
+    pub session_count: usize,
+    pub last_active_at: Option<u64>,
+}
+
+/// 将已填充项目的对话按项目归组, 按最近活跃时间倒序
+pub fn group_by_project(sessions: &[SessionMeta]) -> Vec<ProjectGroup> {
+    let mut groups: Vec<ProjectGroup> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for meta in sessions {
+        let (Some(project), Some(dir)) = (&meta.project, &meta.project_dir) else {
+            continue;
+        };
+        let position = *index.entry(&project.key).or_insert_with(|| {
+            groups.push(ProjectGroup {
+                project: project.clone(),
+                dirs: Vec::new(),
+                provider_ids: Vec::new(),
+                session_count: 0,
+                last_active_at: None,
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[position];
+        // 同一项目的多个位置中, 以当前存在的为代表
+        if group.project.resolved_dir.is_none() && project.resolved_dir.is_some() {
+            group.project = project.clone();
+        }
+        if !group.dirs.contains(dir) {
+            group.dirs.push(dir.clone());
+        }
+        if !group.provider_ids.contains(&meta.provider_id) {
+            group.provider_ids.push(meta.provider_id.clone());
+        }
+        group.session_count += 1;
+        group.last_active_at = group.last_active_at.max(meta.last_active_at);
+    }
+
+    for group in &mut groups {
+        group.dirs.sort();
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.last_active_at));
+    groups
+}
+
+// Tauri 命令
+
+/// 将满足条件的对话按所属项目归组, 用于按项目浏览与筛选
+/// 返回的 project.key 可作为 SessionQuery.project 查询该项目的对话
+#[tauri::command]
+pub fn list_session_projects(query: Option<SessionQuery>) -> Result<Vec<ProjectGroup>, String> {
+    let sessions = matching_sessions(&query.unwrap_or_default())?;
+    Ok(group_by_project(&sessions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("anti-power-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn remote_key_normalizes_ssh_https_and_scp_urls() {
+        let expected = "remote:github.com/owner/repo";
+        for url in [
+            "https://github.com/owner/repo.git",
+            "https://github.com/owner/repo",
+            "https://user@github.com:443/owner/repo/",
+            "ssh://git@github.com:22/owner/repo.git",
+            "git@github.com:owner/repo.git",
+            "git@GitHub.com:Owner/Repo",
+            "  https://GITHUB.COM/owner/repo.git  ",
+        ] {
+            assert_eq!(remote_key(url), expected, "{}", url);
+        }
+        assert_ne!(
+            remote_key("git@github.com:owner/other.git"),
+            remote_key("git@gitlab.com:owner/other.git")
+        );
+    }
+
+    #[test]
+    fn remote_key_keeps_local_paths() {
+        assert_eq!(remote_key("/srv/git/repo.git"), "remote:/srv/git/repo");
+        assert_eq!(
+            remote_key("file:///srv/git/repo.git"),
+            "remote:/srv/git/repo"
+        );
+    }
+
+    #[test]
+    fn find_git_dir_walks_up_to_repository_root() {
+        let dir = temp_dir("project-git-dir");
+        let repo = dir.join("repo");
+        let nested = repo.join("src").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+
+        let found = find_git_dir(&nested);
+        let outside = find_git_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found, Some((repo.clone(), repo.join(".git"))));
+        assert_eq!(outside, None);
+    }
+
+    #[test]
+    fn worktree_gitdir_file_reads_remote_from_main_repository() {
+        let dir = temp_dir("project-worktree");
+        let main_git = dir.join("main").join(".git");
+        let worktree_git = main_git.join("worktrees").join("feature");
+        fs::create_dir_all(&worktree_git).unwrap();
+        fs::write(
+            main_git.join("config"),
+            "[core]\n\tbare = false\n[remote \"upstream\"]\n\turl = https://example.com/up/repo.git\n[remote \"origin\"]\n\turl = git@example.com:me/repo.git\n\tfetch = +refs/heads/*:refs/remotes/origin/*\n",
+        )
+        .unwrap();
+        fs::write(worktree_git.join("commondir"), "../..\n").unwrap();
+        fs::write(worktree_git.join("HEAD"), "ref: refs/heads/feature\n").unwrap();
+        let worktree = dir.join("feature");
+        fs::create_dir_all(worktree.join("src")).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../main/.git/worktrees/feature\n",
+        )
+        .unwrap();
+
+        let found = find_git_dir(&worktree.join("src"));
+        let (root, git_dir) = found.clone().unwrap();
+        let url = read_remote_url(&git_dir);
+        let branch = read_branch(&git_dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(root, worktree);
+        assert_eq!(git_dir, worktree.join("../main/.git/worktrees/feature"));
+        // 有 origin 时优先 origin, 不论其在配置中的位置
+        assert_eq!(url.as_deref(), Some("git@example.com:me/repo.git"));
+        assert_eq!(branch.as_deref(), Some("feature"));
+    }
+
+    #[test]
+    fn read_remote_url_falls_back_to_first_remote() {
+        let dir = temp_dir("project-remote");
+        fs::write(
+            dir.join("config"),
+            "[remote \"upstream\"]\n\turl = https://example.com/up/repo.git\n[remote \"fork\"]\n\turl = https://example.com/me/repo.git\n",
+        )
+        .unwrap();
+        let url = read_remote_url(&dir);
+        fs::write(dir.join("config"), "[core]\n\tbare = false\n").unwrap();
+        let none = read_remote_url(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(url.as_deref(), Some("https://example.com/up/repo.git"));
+        assert_eq!(none, None);
+    }
+}
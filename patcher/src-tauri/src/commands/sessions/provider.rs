@@ -0,0 +1,176 @@
+//! 对话记录 provider 抽象与注册表
+//!
+//! 每个 CLI 工具实现 SessionProvider, 命令层通过注册表按 id 分发
+
+use super::antigravity::AntigravityProvider;
+use super::claude::ClaudeProvider;
+use super::codex::CodexProvider;
+use super::gemini::GeminiProvider;
+use super::openclaw::OpenClawProvider;
+use super::opencode::OpenCodeProvider;
+use super::stats::SessionStats;
+use super::usage::SessionUsage;
+use super::util::Fingerprint;
+use super::{SessionMessage, SessionMeta, SessionTree};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// provider 支持的能力, 供前端决定展示哪些操作
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapabilities {
+    /// 支持删除单个对话
+    pub can_delete: bool,
+    /// 对话元数据包含项目目录
+    pub has_project_dir: bool,
+}
+
+/// 单独解析记录中的一行, 该行不对应消息时返回 None
+pub type LineParser = fn(&str) -> Option<SessionMessage>;
+
+/// 对话记录 provider
+pub trait SessionProvider: Sync {
+    /// 前端与命令参数使用的标识
+    fn id(&self) -> &'static str;
+
+    fn display_name(&self) -> &'static str;
+
+    /// 对话记录的默认存放目录
+    fn default_roots(&self, home: &Path) -> Vec<PathBuf>;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    /// 列出根目录下的对话数据源 (每个文件或目录对应一个对话)
+    fn list_sources(&self, root: &Path) -> Vec<PathBuf>;
+
+    /// 解析单个数据源的元数据 (只读元数据, 不加载消息体)
+    fn read_meta(&self, source: &Path) -> Option<SessionMeta>;
+
+    /// 加载 source_path 指向的对话的全部消息
+    fn load_messages(&self, source_path: &Path) -> Result<Vec<SessionMessage>, String>;
+
+    /// 每条消息对应记录中一行的 provider 返回逐行解析函数, 分页时按行偏移随机读取
+    /// 解析出的模型与用量可能依赖其他行, 以完整加载时的结果为准
+    fn line_parser(&self) -> Option<LineParser> {
+        None
+    }
+
+    /// 加载树状对话结构, 不区分分支与子代理的 provider 只返回主线消息
+    fn load_tree(
+        &self,
+        source_path: &Path,
+        sidechain_root: Option<&str>,
+    ) -> Result<SessionTree, String> {
+        if sidechain_root.is_some() {
+            return Err(format!("{} 不支持子代理对话", self.display_name()));
+        }
+        Ok(SessionTree {
+            messages: self.load_messages(source_path)?,
+            branches: Vec::new(),
+            subagents: Vec::new(),
+        })
+    }
+
+    /// 数据源是否存在; 数据源不是文件系统路径的 provider 需要覆盖
+    fn source_exists(&self, source: &Path) -> bool {
+        source.exists()
+    }
+
+    /// 数据源的指纹, 变化时元数据缓存、分页索引与搜索索引重新解析
+    fn fingerprint(&self, source: &Path) -> Fingerprint {
+        Fingerprint::of(source)
+    }
+
+    /// 对话包含的全部文件与目录, 删除时整体移入回收站
+    fn session_paths(&self, source_path: &Path, session_id: &str) -> Result<Vec<PathBuf>, String>;
+
+    /// 文件移入回收站后清理对话在其他位置的索引 (例如数据库中的记录),
+    /// 返回恢复时需要的数据
+    fn detach(&self, _source_path: &Path, _session_id: &str) -> Result<Option<Value>, String> {
+        Ok(None)
+    }
+
+    /// 从回收站恢复文件后还原 detach 清理的索引
+    fn reattach(&self, _detached: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// 从 detach 返回的数据中加载消息, 用于浏览只保存在索引中的对话 (例如归档中的数据库记录)
+    fn load_detached(&self, _detached: &Value) -> Result<Vec<SessionMessage>, String> {
+        Err(format!("{} 的对话数据不在文件中", self.display_name()))
+    }
+
+    /// 脱敏 session_paths 文件之外的记录 (例如数据库中的行), redact 返回替换后的文本,
+    /// 无需替换时返回 None; 返回修改前的记录作为备份
+    fn redact_records(
+        &self,
+        _source_path: &Path,
+        _session_id: &str,
+        _redact: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Option<Value>, String> {
+        Ok(None)
+    }
+
+    /// 保存在数据源之外的对话标题 (对话 id -> 标题), 例如单独的索引文件;
+    /// 其变化不影响数据源指纹, 因此不进入元数据缓存, 每次扫描时读取并覆盖解析出的标题
+    fn external_titles(&self, _home: &Path) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// 在 CLI 中继续该对话的命令 (程序名与参数), 在项目目录中执行; 不支持时返回 None
+    fn resume_command(&self, _meta: &SessionMeta) -> Option<Vec<String>> {
+        None
+    }
+
+    /// 列出全部默认目录下的对话数据源
+    fn sources(&self, home: &Path) -> Vec<PathBuf> {
+        self.default_roots(home)
+            .iter()
+            .flat_map(|root| self.list_sources(root))
+            .collect()
+    }
+
+    /// 监视对话变化时监视的目录, 数据源之外的文件 (例如数据库) 不在默认目录中时需要覆盖
+    fn watch_roots(&self, home: &Path) -> Vec<PathBuf> {
+        self.default_roots(home)
+    }
+
+    /// 统计消息数与用量, 需要加载完整对话; 只在按需统计时调用, 结果由统计缓存保存
+    fn read_stats(&self, meta: &SessionMeta) -> Option<SessionStats> {
+        let messages = self.load_messages(Path::new(&meta.source_path)).ok()?;
+        Some(SessionStats {
+            message_count: messages.len(),
+            usage: SessionUsage::from_messages(&messages),
+        })
+    }
+
+    /// 任一默认目录存在即视为已安装
+    fn is_installed(&self, home: &Path) -> bool {
+        self.default_roots(home).iter().any(|root| root.is_dir())
+    }
+}
+
+/// 已注册的 provider, 顺序即前端展示顺序
+static PROVIDERS: &[&dyn SessionProvider] = &[
+    &ClaudeProvider,
+    &CodexProvider,
+    &GeminiProvider,
+    &OpenCodeProvider,
+    &OpenClawProvider,
+    &AntigravityProvider,
+];
+
+pub fn all_providers() -> &'static [&'static dyn SessionProvider] {
+    PROVIDERS
+}
+
+/// 按 id 查找 provider
+pub fn find_provider(id: &str) -> Result<&'static dyn SessionProvider, String> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.id() == id)
+        .ok_or_else(|| format!("未知的 provider: {}", id))
+}
@@ -0,0 +1,415 @@
+//! 对话列表查询
+//!
+//! 对扫描结果按条件过滤、排序, 并以游标分页返回
+
+use super::cache::scan_cached;
+use super::project::attach_projects;
+use super::provider::{all_providers, find_provider};
+use super::scan::ScanControl;
+use super::stats::attach_stats;
+use super::SessionMeta;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::Path;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// 对话列表查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SessionQuery {
+    /// 为空时查询全部 provider
+    pub providers: Vec<String>,
+    pub project_dir: Option<String>,
+    pub project_dir_match: ProjectDirMatch,
+    /// 所属项目的标识 (ProjectInfo.key), 同一仓库的子目录与其他位置的克隆都会匹配
+    pub project: Option<String>,
+    /// 创建时间范围 (毫秒时间戳, 含)
+    pub created_since: Option<u64>,
+    pub created_until: Option<u64>,
+    /// 最近活跃时间范围 (毫秒时间戳, 含)
+    pub active_since: Option<u64>,
+    pub active_until: Option<u64>,
+    pub min_messages: Option<usize>,
+    /// 标题或摘要包含该文本 (不区分大小写)
+    pub text: Option<String>,
+    pub sort: SessionSortKey,
+    pub direction: SortDirection,
+    /// 上一页返回的 next_cursor
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// 丢弃元数据缓存并重新解析
+    pub rebuild: bool,
+}
+
+/// 项目目录匹配方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectDirMatch {
+    #[default]
+    Exact,
+    /// 该目录或其子目录
+    Prefix,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionSortKey {
+    #[default]
+    LastActive,
+    Created,
+    Title,
+    MessageCount,
+    Provider,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// 一页查询结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPage {
+    pub sessions: Vec<SessionMeta>,
+    /// 满足条件的对话总数
+    pub total: usize,
+    /// 还有下一页时返回, 作为下次查询的 cursor
+    pub next_cursor: Option<String>,
+    pub provider_counts: Vec<ProviderCount>,
+}
+
+/// 单个 provider 的对话数
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCount {
+    pub provider_id: String,
+    /// 扫描到的对话数
+    pub total: usize,
+    /// 满足条件的对话数
+    pub matched: usize,
+}
+
+/// 排序字段的取值, 同一次查询中只会出现一种
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum SortValue {
+    Number(Option<u64>),
+    Text(String),
+}
+
+/// 分页游标: 上一页最后一条的排序值, source_path 用于区分排序值相同的对话
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cursor {
+    sort: SessionSortKey,
+    direction: SortDirection,
+    value: SortValue,
+    source_path: String,
+}
+
+impl SessionQuery {
+    fn matches(&self, meta: &SessionMeta) -> bool {
+        let text = self
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_lowercase);
+
+        self.project_dir.as_deref().is_none_or(|dir| {
+            meta.project_dir
+                .as_deref()
+                .is_some_and(|project| match self.project_dir_match {
+                    ProjectDirMatch::Exact => Path::new(project) == Path::new(dir),
+                    ProjectDirMatch::Prefix => Path::new(project).starts_with(dir),
+                })
+        }) && self.project.as_deref().is_none_or(|key| {
+            meta.project
+                .as_ref()
+                .is_some_and(|project| project.key == key)
+        }) && in_range(meta.created_at, self.created_since, self.created_until)
+            && in_range(meta.last_active_at, self.active_since, self.active_until)
+            && self
+                .min_messages
+                .is_none_or(|min| meta.message_count.is_some_and(|count| count >= min))
+            && text.is_none_or(|text| {
+                [&meta.title, &meta.summary].iter().any(|field| {
+                    field
+                        .as_deref()
+                        .is_some_and(|f| f.to_lowercase().contains(&text))
+                })
+            })
+    }
+
+    fn compare(&self, a: (&SortValue, &str), b: (&SortValue, &str)) -> Ordering {
+        let by_value = match self.direction {
+            SortDirection::Asc => a.0.cmp(b.0),
+            SortDirection::Desc => b.0.cmp(a.0),
+        };
+        by_value.then_with(|| a.1.cmp(b.1))
+    }
+}
+
+/// 时间不存在时只在未设置范围时匹配
+fn in_range(value: Option<u64>, since: Option<u64>, until: Option<u64>) -> bool {
+    if since.is_none() && until.is_none() {
+        return true;
+    }
+    value.is_some_and(|value| {
+        since.is_none_or(|since| value >= since) && until.is_none_or(|until| value <= until)
+    })
+}
+
+fn sort_value(meta: &SessionMeta, key: SessionSortKey) -> SortValue {
+    match key {
+        SessionSortKey::LastActive => SortValue::Number(meta.last_active_at),
+        SessionSortKey::Created => SortValue::Number(meta.created_at),
+        SessionSortKey::MessageCount => SortValue::Number(meta.message_count.map(|c| c as u64)),
+        SessionSortKey::Title => SortValue::Text(
+            meta.title
+                .as_deref()
+                .or(meta.summary.as_deref())
+                .unwrap_or_default()
+                .to_lowercase(),
+        ),
+        SessionSortKey::Provider => SortValue::Text(meta.provider_id.clone()),
+    }
+}
+
+/// 填充所属项目后过滤、排序并截取一页
+pub fn query_sessions(
+    mut sessions: Vec<SessionMeta>,
+    provider_ids: &[&str],
+    query: &SessionQuery,
+) -> Result<SessionPage, String> {
+    let cursor = match query.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(raw) => {
+            let cursor: Cursor =
+                serde_json::from_str(raw).map_err(|_| "无效的分页游标".to_string())?;
+            if cursor.sort != query.sort || cursor.direction != query.direction {
+                return Err("分页游标与排序方式不匹配".to_string());
+            }
+            Some(cursor)
+        }
+        None => None,
+    };
+    attach_projects(&mut sessions)?;
+    // 消息数需要加载完整对话, 只在过滤或排序用到时统计
+    if query.min_messages.is_some() || query.sort == SessionSortKey::MessageCount {
+        attach_stats(&mut sessions)?;
+    }
+
+    let mut provider_counts: Vec<ProviderCount> = provider_ids
+        .iter()
+        .map(|id| ProviderCount {
+            provider_id: id.to_string(),
+            total: 0,
+            matched: 0,
+        })
+        .collect();
+
+    let mut matched = Vec::new();
+    for meta in sessions {
+        let count = provider_counts
+            .iter_mut()
+            .find(|count| count.provider_id == meta.provider_id);
+        let is_match = query.matches(&meta);
+        if let Some(count) = count {
+            count.total += 1;
+            count.matched += is_match as usize;
+        }
+        if is_match {
+            matched.push((sort_value(&meta, query.sort), meta));
+        }
+    }
+    matched.sort_by(|a, b| query.compare((&a.0, &a.1.source_path), (&b.0, &b.1.source_path)));
+
+    let total = matched.len();
+    let start = match &cursor {
+        Some(cursor) => matched.partition_point(|(value, meta)| {
+            query.compare(
+                (value, &meta.source_path),
+                (&cursor.value, &cursor.source_path),
+            ) != Ordering::Greater
+        }),
+        None => 0,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+    let end = (start + limit).min(total);
+
+    let next_cursor = if end < total {
+        let (value, meta) = &matched[end - 1];
+        serde_json::to_string(&Cursor {
+            sort: query.sort,
+            direction: query.direction,
+            value: value.clone(),
+            source_path: meta.source_path.clone(),
+        })
+        .ok()
+    } else {
+        None
+    };
+
+    Ok(SessionPage {
+        sessions: matched.drain(start..end).map(|(_, meta)| meta).collect(),
+        total,
+        next_cursor,
+        provider_counts,
+    })
+}
+
+/// 扫描并返回全部满足条件的对话, 忽略分页参数; 供批量操作使用
+pub fn matching_sessions(query: &SessionQuery) -> Result<Vec<SessionMeta>, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let providers = if query.providers.is_empty() {
+        all_providers().to_vec()
+    } else {
+        query
+            .providers
+            .iter()
+            .map(|id| find_provider(id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let sessions =
+        scan_cached(&providers, &home, query.rebuild, &ScanControl::default())?.unwrap_or_default();
+    let provider_ids: Vec<&str> = providers.iter().map(|provider| provider.id()).collect();
+    let query = SessionQuery {
+        cursor: None,
+        limit: Some(sessions.len()),
+        ..query.clone()
+    };
+    query_sessions(sessions, &provider_ids, &query).map(|page| page.sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(provider_id: &str, source_path: &str, title: &str, last_active_at: u64) -> SessionMeta {
+        SessionMeta {
+            provider_id: provider_id.to_string(),
+            session_id: source_path.to_string(),
+            title: Some(title.to_string()),
+            summary: None,
+            project_dir: None,
+            project_name: None,
+            created_at: None,
+            last_active_at: Some(last_active_at),
+            source_path: source_path.to_string(),
+            message_count: None,
+            usage: None,
+            project: None,
+        }
+    }
+
+    fn sessions() -> Vec<SessionMeta> {
+        vec![
+            meta("claude", "/a", "Delta", 30),
+            meta("claude", "/b", "alpha", 10),
+            meta("codex", "/c", "Charlie", 30),
+            meta("codex", "/d", "bravo", 20),
+            meta("codex", "/e", "echo", 30),
+        ]
+    }
+
+    /// 逐页查询直到没有下一页, 返回各页的 source_path
+    fn collect_pages(query: &SessionQuery) -> Vec<Vec<String>> {
+        let mut query = query.clone();
+        let mut pages = Vec::new();
+        loop {
+            let page = query_sessions(sessions(), &["claude", "codex"], &query).unwrap();
+            assert_eq!(page.total, 5);
+            pages.push(
+                page.sessions
+                    .iter()
+                    .map(|meta| meta.source_path.clone())
+                    .collect(),
+            );
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => return pages,
+            }
+        }
+    }
+
+    #[test]
+    fn cursor_pages_break_ties_by_source_path() {
+        let query = SessionQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            collect_pages(&query),
+            vec![vec!["/a", "/c"], vec!["/e", "/d"], vec!["/b"]]
+        );
+
+        let query = SessionQuery {
+            direction: SortDirection::Asc,
+            limit: Some(2),
+            ..query
+        };
+        assert_eq!(
+            collect_pages(&query),
+            vec![vec!["/b", "/d"], vec!["/a", "/c"], vec!["/e"]]
+        );
+    }
+
+    #[test]
+    fn cursor_pages_by_title_ignore_case() {
+        let query = SessionQuery {
+            sort: SessionSortKey::Title,
+            direction: SortDirection::Asc,
+            limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            collect_pages(&query),
+            vec![vec!["/b", "/d", "/c"], vec!["/a", "/e"]]
+        );
+    }
+
+    #[test]
+    fn cursor_must_match_sort() {
+        let query = SessionQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let cursor = query_sessions(sessions(), &["claude", "codex"], &query)
+            .unwrap()
+            .next_cursor;
+        let resorted = SessionQuery {
+            sort: SessionSortKey::Title,
+            cursor,
+            ..query.clone()
+        };
+        assert!(query_sessions(sessions(), &["claude", "codex"], &resorted).is_err());
+
+        let invalid = SessionQuery {
+            cursor: Some("not a cursor".to_string()),
+            ..query
+        };
+        assert!(query_sessions(sessions(), &["claude", "codex"], &invalid).is_err());
+    }
+
+    #[test]
+    fn provider_counts_include_unmatched_sessions() {
+        let query = SessionQuery {
+            text: Some("ALPHA".to_string()),
+            ..Default::default()
+        };
+        let page = query_sessions(sessions(), &["claude", "codex"], &query).unwrap();
+        assert_eq!(page.total, 1);
+        let counts: Vec<(&str, usize, usize)> = page
+            .provider_counts
+            .iter()
+            .map(|count| (count.provider_id.as_str(), count.total, count.matched))
+            .collect();
+        assert_eq!(counts, vec![("claude", 2, 1), ("codex", 3, 0)]);
+    }
+}
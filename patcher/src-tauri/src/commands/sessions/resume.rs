@@ -0,0 +1,379 @@
+//! 在 CLI 中继续对话
+//!
+//! 由 provider 生成继续对话的命令, 在对话的项目目录中执行 (仓库移动后为新位置).
+//! 命令可在配置的终端中直接打开; 打开失败时前端退回到复制命令行
+
+use super::cache::cached_meta;
+use super::project::attach_projects;
+use super::provider::find_provider;
+use crate::commands::config;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+/// 继续对话的命令
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeCommand {
+    pub provider_id: String,
+    pub session_id: String,
+    pub program: String,
+    pub args: Vec<String>,
+    /// 执行命令的目录, 项目目录已不存在且找不到新位置时为 None
+    pub cwd: Option<String>,
+    /// 可直接粘贴到终端执行的命令行 (含切换目录), Windows 上为 cmd 语法
+    pub command_line: String,
+}
+
+/// POSIX shell 单引号转义, 只含安全字符时原样返回
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// cmd 参数转义, 含空白或特殊字符时加双引号
+/// 双引号内的 %VAR% 与 !VAR! (延迟扩展) 仍会展开, 交互式 cmd 中无法转义, 因此拒绝
+fn cmd_quote(value: &str) -> Result<String, String> {
+    if value.contains(['%', '!']) {
+        return Err(format!("参数包含 cmd 无法安全转义的 % 或 !: {}", value));
+    }
+    let safe = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || "\"&|<>^()".contains(c));
+    Ok(if safe {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    })
+}
+
+/// wt 将 ; 视为子命令分隔符, 传给 wt 的命令行与参数需要转义
+#[cfg(any(windows, test))]
+fn wt_escape(value: &str) -> String {
+    value.replace(';', "\\;")
+}
+
+fn quote(value: &str) -> Result<String, String> {
+    if cfg!(windows) {
+        cmd_quote(value)
+    } else {
+        Ok(shell_quote(value))
+    }
+}
+
+impl ResumeCommand {
+    /// 不含切换目录的命令部分
+    fn program_line(&self) -> Result<String, String> {
+        Ok(std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|arg| quote(arg))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(" "))
+    }
+}
+
+fn build_resume_command(provider_id: &str, source_path: &str) -> Result<ResumeCommand, String> {
+    let provider = find_provider(provider_id)?;
+    let path = Path::new(source_path);
+    if !provider.source_exists(path) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+    let mut meta = cached_meta(provider, source_path).ok_or("无法读取对话元数据")?;
+    attach_projects(std::slice::from_mut(&mut meta))?;
+
+    let mut argv = provider
+        .resume_command(&meta)
+        .ok_or_else(|| format!("{} 不支持在 CLI 中继续对话", provider.display_name()))?
+        .into_iter();
+    let program = argv.next().ok_or("继续对话的命令为空")?;
+    let cwd = meta
+        .project
+        .as_ref()
+        .and_then(|project| project.resolved_dir.clone())
+        .or_else(|| {
+            meta.project_dir
+                .clone()
+                .filter(|dir| Path::new(dir).is_dir())
+        });
+
+    let mut command = ResumeCommand {
+        provider_id: provider_id.to_string(),
+        session_id: meta.session_id.clone(),
+        program,
+        args: argv.collect(),
+        cwd,
+        command_line: String::new(),
+    };
+    let program_line = command.program_line()?;
+    command.command_line = match &command.cwd {
+        Some(dir) if cfg!(windows) => format!("cd /d {} && {}", cmd_quote(dir)?, program_line),
+        Some(dir) => format!("cd {} && {}", shell_quote(dir), program_line),
+        None => program_line,
+    };
+    Ok(command)
+}
+
+/// 在 PATH 中查找程序
+#[cfg(not(target_os = "macos"))]
+fn find_in_path(program: &str) -> bool {
+    if Path::new(program).is_absolute() {
+        return Path::new(program).is_file();
+    }
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        dir.join(program).is_file()
+            || (cfg!(windows) && dir.join(format!("{}.exe", program)).is_file())
+    })
+}
+
+/// 在 Terminal 或 iTerm 中执行命令行
+#[cfg(target_os = "macos")]
+fn open_in_terminal(command: &ResumeCommand, terminal: Option<&str>) -> Result<(), String> {
+    let script = command
+        .command_line
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    let apple_script = match terminal.unwrap_or("Terminal") {
+        "Terminal" => format!(
+            "tell application \"Terminal\"\nactivate\ndo script \"{}\"\nend tell",
+            script
+        ),
+        "iTerm" | "iTerm2" => format!(
+            "tell application \"iTerm\"\nactivate\nset newWindow to (create window with default profile)\ntell current session of newWindow to write text \"{}\"\nend tell",
+            script
+        ),
+        other => return Err(format!("不支持的终端: {}", other)),
+    };
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(apple_script)
+        .output()
+        .map_err(|e| format!("启动终端失败: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "启动终端失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// 在新的控制台窗口中执行, 配置为 wt 或未配置且已安装 Windows Terminal 时使用 Windows Terminal
+#[cfg(target_os = "windows")]
+fn open_in_terminal(command: &ResumeCommand, terminal: Option<&str>) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    let use_wt = match terminal {
+        Some(terminal) if terminal.eq_ignore_ascii_case("wt") => true,
+        Some(terminal) if terminal.eq_ignore_ascii_case("cmd") => false,
+        Some(other) => return Err(format!("不支持的终端: {}", other)),
+        None => find_in_path("wt"),
+    };
+    // 命令行已按 cmd 语法转义, 原样传给 cmd /k
+    let program_line = command.program_line()?;
+    let mut process = if use_wt {
+        let mut process = Command::new("wt");
+        if let Some(dir) = &command.cwd {
+            process.arg("-d").arg(wt_escape(dir));
+        }
+        process.args(["cmd", "/k"]);
+        process.raw_arg(wt_escape(&program_line));
+        process
+    } else {
+        let mut process = crate::commands::clean::new_windows_command("cmd");
+        process.args(["/c", "start", "", "cmd", "/k"]);
+        process.raw_arg(program_line);
+        process
+    };
+    if let Some(dir) = &command.cwd {
+        process.current_dir(dir);
+    }
+    process
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("启动终端失败: {}", e))
+}
+
+/// 未配置时依次尝试 $TERMINAL 与常见的终端模拟器
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const DEFAULT_TERMINALS: &[&str] = &[
+    "x-terminal-emulator",
+    "gnome-terminal",
+    "konsole",
+    "xfce4-terminal",
+    "kitty",
+    "alacritty",
+    "wezterm",
+    "ghostty",
+    "foot",
+    "xterm",
+];
+
+/// 各终端模拟器在要执行的命令前需要的参数
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn terminal_exec_args(terminal: &str) -> &'static [&'static str] {
+    let name = Path::new(terminal)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(terminal);
+    match name {
+        "gnome-terminal" | "kgx" | "ptyxis" => &["--"],
+        "xfce4-terminal" | "mate-terminal" | "terminator" => &["-x"],
+        "wezterm" => &["start", "--"],
+        "kitty" | "foot" => &[],
+        _ => &["-e"],
+    }
+}
+
+/// 在终端模拟器中通过 sh 执行, 命令结束后留在用户的 shell 中
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn open_in_terminal(command: &ResumeCommand, terminal: Option<&str>) -> Result<(), String> {
+    let env_terminal = std::env::var("TERMINAL").ok();
+    let terminal = terminal
+        .map(str::to_string)
+        .or(env_terminal.filter(|terminal| find_in_path(terminal)))
+        .or_else(|| {
+            DEFAULT_TERMINALS
+                .iter()
+                .find(|terminal| find_in_path(terminal))
+                .map(|terminal| terminal.to_string())
+        })
+        .ok_or("未找到终端模拟器")?;
+
+    let script = format!("{}; exec \"${{SHELL:-sh}}\"", command.program_line()?);
+    let mut process = Command::new(&terminal);
+    process
+        .args(terminal_exec_args(&terminal))
+        .args(["sh", "-c", &script]);
+    if let Some(dir) = &command.cwd {
+        process.current_dir(dir);
+    }
+    process
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("启动终端失败: {}: {}", terminal, e))
+}
+
+// Tauri 命令
+
+/// 生成在 CLI 中继续对话的命令
+#[tauri::command]
+pub fn session_resume_command(
+    provider_id: String,
+    source_path: String,
+) -> Result<ResumeCommand, String> {
+    build_resume_command(&provider_id, &source_path)
+}
+
+/// 在配置的终端中继续对话, 返回执行的命令; 失败时前端可复制 session_resume_command 的结果
+#[tauri::command]
+pub fn launch_session_resume(
+    provider_id: String,
+    source_path: String,
+) -> Result<ResumeCommand, String> {
+    let command = build_resume_command(&provider_id, &source_path)?;
+    let terminal = config::get_config()
+        .terminal
+        .filter(|terminal| !terminal.trim().is_empty());
+    open_in_terminal(&command, terminal.as_deref())?;
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_keeps_safe_values() {
+        assert_eq!(shell_quote("claude"), "claude");
+        assert_eq!(shell_quote("/home/u/proj-1"), "/home/u/proj-1");
+        assert_eq!(shell_quote("ses_01J:a=b@c"), "ses_01J:a=b@c");
+    }
+
+    #[test]
+    fn shell_quote_wraps_unsafe_values() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("my project"), "'my project'");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("项目"), "'项目'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_quote_round_trips_through_sh() {
+        for value in ["a b", "it's", "$HOME `id` \"q\"", "*?[x]", "a\nb", "-n"] {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf '%s' {}", shell_quote(value)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+    }
+
+    #[test]
+    fn cmd_quote_keeps_safe_values() {
+        assert_eq!(cmd_quote("codex").unwrap(), "codex");
+        assert_eq!(
+            cmd_quote("C:\\Users\\u\\proj").unwrap(),
+            "C:\\Users\\u\\proj"
+        );
+    }
+
+    #[test]
+    fn cmd_quote_rejects_expansion_characters() {
+        for value in ["%PATH%", "50%", "C:\\a%b%\\c", "hi!", "!VAR!"] {
+            assert!(cmd_quote(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn wt_escape_escapes_semicolons() {
+        assert_eq!(wt_escape("codex resume abc"), "codex resume abc");
+        assert_eq!(
+            wt_escape("\"C:\\a;b\" ; new-tab"),
+            "\"C:\\a\\;b\" \\; new-tab"
+        );
+    }
+
+    #[test]
+    fn cmd_quote_wraps_unsafe_values() {
+        assert_eq!(cmd_quote("").unwrap(), "\"\"");
+        assert_eq!(
+            cmd_quote("C:\\My Projects\\app").unwrap(),
+            "\"C:\\My Projects\\app\""
+        );
+        assert_eq!(cmd_quote("a&b").unwrap(), "\"a&b\"");
+        assert_eq!(cmd_quote("x|y").unwrap(), "\"x|y\"");
+        assert_eq!(cmd_quote("say \"hi\"").unwrap(), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn program_line_quotes_each_argument() {
+        let command = ResumeCommand {
+            provider_id: "codex".to_string(),
+            session_id: "s 1".to_string(),
+            program: "codex".to_string(),
+            args: vec!["resume".to_string(), "s 1".to_string()],
+            cwd: None,
+            command_line: String::new(),
+        };
+        let expected = if cfg!(windows) {
+            "codex resume \"s 1\""
+        } else {
+            "codex resume 's 1'"
+        };
+        assert_eq!(command.program_line().unwrap(), expected);
+    }
+}
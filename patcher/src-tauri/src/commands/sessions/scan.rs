@@ -0,0 +1,195 @@
+//! 后台对话扫描
+//!
+//! 在后台线程中扫描对话列表, 通过 Tauri 事件分批推送进度与已解析的对话,
+//! 前端关闭对话浏览器时可按扫描 id 取消
+
+use super::cache::scan_cached;
+use super::project::attach_projects;
+use super::provider::find_provider;
+use super::SessionMeta;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 扫描进度事件, 携带自上次事件以来新解析的对话
+pub const SCAN_PROGRESS_EVENT: &str = "session-scan:progress";
+/// 扫描结束事件 (完成、失败或取消)
+pub const SCAN_DONE_EVENT: &str = "session-scan:done";
+
+/// 累计到该数量的对话后立即推送
+const BATCH_SIZE: usize = 64;
+/// 两次推送的最小间隔
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+static NEXT_SCAN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 进行中的扫描及其取消标记
+static ACTIVE_SCANS: Mutex<Option<HashMap<u64, Arc<AtomicBool>>>> = Mutex::new(None);
+
+/// 单个 provider 的扫描进度
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    pub provider_id: String,
+    pub files_total: usize,
+    pub files_scanned: usize,
+    pub sessions_found: usize,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanProgressEvent {
+    scan_id: u64,
+    providers: Vec<ScanProgress>,
+    sessions: Vec<SessionMeta>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanDoneEvent {
+    scan_id: u64,
+    canceled: bool,
+    total: usize,
+    error: Option<String>,
+}
+
+/// 进度回调, 参数为各 provider 进度与新解析的对话
+pub type ScanUpdateFn<'a> = &'a dyn Fn(&[ScanProgress], Vec<SessionMeta>);
+
+/// 扫描过程的取消标记与进度回调
+#[derive(Default)]
+pub struct ScanControl<'a> {
+    pub cancel: Option<&'a AtomicBool>,
+    /// 在调用扫描的线程上执行
+    pub on_update: Option<ScanUpdateFn<'a>>,
+}
+
+impl ScanControl<'_> {
+    pub fn is_canceled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+/// 按数量与时间间隔合并推送, 避免每个文件触发一次事件
+pub struct ScanReporter<'a> {
+    control: &'a ScanControl<'a>,
+    pending: Vec<SessionMeta>,
+    last_flush: Instant,
+}
+
+impl<'a> ScanReporter<'a> {
+    pub fn new(control: &'a ScanControl<'a>) -> Self {
+        Self {
+            control,
+            pending: Vec::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn push(&mut self, meta: &SessionMeta) {
+        if self.control.on_update.is_some() {
+            self.pending.push(meta.clone());
+        }
+    }
+
+    pub fn maybe_flush(&mut self, progress: &[ScanProgress]) {
+        if self.pending.len() >= BATCH_SIZE || self.last_flush.elapsed() >= BATCH_INTERVAL {
+            self.flush(progress);
+        }
+    }
+
+    pub fn flush(&mut self, progress: &[ScanProgress]) {
+        if let Some(on_update) = self.control.on_update {
+            on_update(progress, std::mem::take(&mut self.pending));
+        }
+        self.last_flush = Instant::now();
+    }
+}
+
+fn with_active_scans<T>(op: impl FnOnce(&mut HashMap<u64, Arc<AtomicBool>>) -> T) -> T {
+    let mut guard = ACTIVE_SCANS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    op(guard.get_or_insert_with(HashMap::new))
+}
+
+// Tauri 命令
+
+/// 在后台开始扫描对话列表, 立即返回扫描 id
+/// 进度与部分结果通过 session-scan:progress 事件推送, 结束时发送 session-scan:done
+#[tauri::command]
+pub fn start_session_scan(
+    app: AppHandle,
+    providers: Vec<String>,
+    rebuild: Option<bool>,
+) -> Result<u64, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let providers = providers
+        .iter()
+        .map(|id| find_provider(id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let scan_id = NEXT_SCAN_ID.fetch_add(1, Ordering::Relaxed);
+    let cancel = Arc::new(AtomicBool::new(false));
+    with_active_scans(|scans| scans.insert(scan_id, cancel.clone()));
+
+    std::thread::spawn(move || {
+        // 项目记录无法保存时仍推送结果, 在扫描结束时报告
+        let project_error = RefCell::new(None);
+        let on_update = |progress: &[ScanProgress], mut sessions: Vec<SessionMeta>| {
+            if let Err(err) = attach_projects(&mut sessions) {
+                project_error.borrow_mut().get_or_insert(err);
+            }
+            let _ = app.emit(
+                SCAN_PROGRESS_EVENT,
+                ScanProgressEvent {
+                    scan_id,
+                    providers: progress.to_vec(),
+                    sessions,
+                },
+            );
+        };
+        let control = ScanControl {
+            cancel: Some(&cancel),
+            on_update: Some(&on_update),
+        };
+
+        let result = scan_cached(&providers, &home, rebuild.unwrap_or(false), &control);
+        with_active_scans(|scans| scans.remove(&scan_id));
+
+        let (canceled, total, error) = match result {
+            Ok(Some(sessions)) => (false, sessions.len(), None),
+            Ok(None) => (true, 0, None),
+            Err(err) => (false, 0, Some(err)),
+        };
+        let error = error.or_else(|| project_error.take());
+        let _ = app.emit(
+            SCAN_DONE_EVENT,
+            ScanDoneEvent {
+                scan_id,
+                canceled,
+                total,
+                error,
+            },
+        );
+    });
+
+    Ok(scan_id)
+}
+
+/// 取消进行中的扫描, 返回该扫描是否仍在进行
+#[tauri::command]
+pub fn cancel_session_scan(scan_id: u64) -> Result<bool, String> {
+    Ok(with_active_scans(|scans| match scans.get(&scan_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }))
+}
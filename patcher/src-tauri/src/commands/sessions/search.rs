@@ -0,0 +1,843 @@
+//! 对话全文搜索
+//!
+//! 在应用数据目录的 SQLite 数据库中维护按消息粒度的倒排索引, 每次搜索前按元数据缓存的扫描结果
+//! 增量更新, 只重建新增或修改过的对话且逐个提交. 候选消息按 BM25 排序后重新读取原文
+//! 校验短语并生成高亮片段, 按行存储的记录只读取命中消息所在的行
+
+use super::cache::scan_cached;
+use super::page::read_message_at;
+use super::provider::{all_providers, find_provider, SessionProvider};
+use super::scan::ScanControl;
+use super::util::{run_blocking, Fingerprint};
+use super::{SessionMessage, SessionMeta};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 索引格式版本 (记录在 user_version 中), 分词或结构变化时递增以重建索引
+const INDEX_VERSION: i32 = 4;
+const INDEX_FILE_NAME: &str = "session-index.db";
+/// 旧版本的 JSON 索引, 打开数据库时删除
+const LEGACY_INDEX_FILE_NAME: &str = "session-index.json";
+const DEFAULT_LIMIT: usize = 50;
+/// 高亮片段在首个命中前保留的字符数
+const SNIPPET_BEFORE: usize = 60;
+/// 高亮片段的最大字符数
+const SNIPPET_CHARS: usize = 200;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// posting 中的 len 为消息词数, 冗余存储以免排序时逐条查询 message
+const SCHEMA: &str = "
+create table if not exists doc (
+    id integer primary key,
+    provider_id text not null,
+    session_id text not null,
+    source_path text not null unique,
+    title text,
+    project_dir text,
+    last_active_at integer,
+    fingerprint text not null
+);
+create table if not exists message (
+    doc_id integer not null,
+    idx integer not null,
+    role text not null,
+    ts integer,
+    len integer not null,
+    line_offset integer,
+    primary key (doc_id, idx)
+) without rowid;
+create table if not exists posting (
+    term text not null,
+    doc_id integer not null,
+    idx integer not null,
+    tf integer not null,
+    len integer not null,
+    primary key (term, doc_id, idx)
+) without rowid;
+create index if not exists posting_doc on posting (doc_id);
+";
+
+/// 索引数据库, 首次搜索时打开
+static INDEX: Mutex<Option<SearchIndex>> = Mutex::new(None);
+
+/// 搜索条件
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SessionSearchQuery {
+    /// 搜索词, 空格分隔的词需全部出现, 双引号包裹的短语需连续出现
+    pub query: String,
+    /// 为空时搜索全部 provider
+    pub providers: Vec<String>,
+    /// 消息角色 (user / assistant), 为空时不限
+    pub roles: Vec<String>,
+    /// 仅搜索该目录 (含子目录) 下项目的对话
+    pub project_dir: Option<String>,
+    /// 起始时间 (毫秒时间戳, 含)
+    pub since: Option<u64>,
+    /// 截止时间 (毫秒时间戳, 含)
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// 搜索命中的单条消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub provider_id: String,
+    pub session_id: String,
+    pub source_path: String,
+    pub title: Option<String>,
+    pub project_dir: Option<String>,
+    /// 消息在 load_session_messages 结果中的下标
+    pub message_index: usize,
+    pub role: String,
+    pub ts: Option<u64>,
+    pub score: f64,
+    pub snippet: Vec<SnippetPart>,
+}
+
+/// 片段中的一段文本, highlight 为 true 表示命中词
+#[derive(Debug, Clone, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+/// 倒排索引
+struct SearchIndex {
+    conn: Connection,
+}
+
+/// 已索引的对话
+struct IndexedDoc {
+    provider_id: String,
+    session_id: String,
+    source_path: String,
+    title: Option<String>,
+    project_dir: Option<String>,
+    last_active_at: Option<u64>,
+}
+
+/// 已索引的消息
+struct IndexedMessage {
+    role: String,
+    ts: Option<u64>,
+    /// 按行存储的记录中消息所在行的偏移
+    line_offset: Option<u64>,
+}
+
+/// 包含某个词的消息
+struct Posting {
+    doc_id: i64,
+    index: u32,
+    /// 词频
+    tf: u32,
+    /// 消息词数
+    len: u32,
+}
+
+/// 分词结果, start/end 为原文中的字节偏移
+struct Token {
+    term: String,
+    start: usize,
+    end: usize,
+    cjk: bool,
+}
+
+/// 解析后的查询
+struct ParsedQuery {
+    terms: Vec<String>,
+    phrases: Vec<Vec<String>>,
+}
+
+impl ParsedQuery {
+    /// 候选消息必须包含的全部词
+    fn required_terms(&self) -> Vec<String> {
+        let mut required: Vec<String> = Vec::new();
+        for term in self.terms.iter().chain(self.phrases.iter().flatten()) {
+            if !required.contains(term) {
+                required.push(term.clone());
+            }
+        }
+        required
+    }
+}
+
+// 索引读写与增量更新
+
+impl SearchIndex {
+    fn open() -> rusqlite::Result<Self> {
+        let path = get_index_path();
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+            let _ = fs::remove_file(dir.join(LEGACY_INDEX_FILE_NAME));
+        }
+        let conn = Connection::open(&path)?;
+        conn.pragma_update(None, "journal_mode", "wal")?;
+        conn.pragma_update(None, "synchronous", "normal")?;
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != INDEX_VERSION {
+            conn.execute_batch(
+                "drop table if exists posting; drop table if exists message; drop table if exists doc;",
+            )?;
+            conn.pragma_update(None, "user_version", INDEX_VERSION)?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// 根据扫描结果增量更新: 只重建新增或修改过的对话, 并移除已不存在的对话
+    /// 每个对话单独提交, 中断后已完成的部分无需重建
+    fn refresh(
+        &mut self,
+        providers: &[&dyn SessionProvider],
+        metas: Vec<SessionMeta>,
+    ) -> rusqlite::Result<()> {
+        let indexed: HashMap<String, (i64, String, Option<Fingerprint>)> = self
+            .conn
+            .prepare("select source_path, id, provider_id, fingerprint from doc")?
+            .query_map([], |row| {
+                let fingerprint: String = row.get(3)?;
+                Ok((
+                    row.get(0)?,
+                    (
+                        row.get(1)?,
+                        row.get(2)?,
+                        serde_json::from_str(&fingerprint).ok(),
+                    ),
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut seen = HashSet::new();
+
+        for meta in metas {
+            let Some(provider) = providers.iter().find(|p| p.id() == meta.provider_id) else {
+                continue;
+            };
+            let fingerprint = provider.fingerprint(Path::new(&meta.source_path));
+            let previous = indexed.get(&meta.source_path);
+            seen.insert(meta.source_path.clone());
+            if previous.is_some_and(|(_, _, indexed)| *indexed == Some(fingerprint)) {
+                continue;
+            }
+
+            let messages = provider
+                .load_messages(Path::new(&meta.source_path))
+                .unwrap_or_default();
+            self.replace_doc(
+                previous.map(|(id, _, _)| *id),
+                &meta,
+                fingerprint,
+                &messages,
+            )?;
+        }
+
+        let tx = self.conn.transaction()?;
+        for (source_path, (id, provider_id, _)) in &indexed {
+            if providers.iter().any(|p| p.id() == provider_id) && !seen.contains(source_path) {
+                remove_doc(&tx, *id)?;
+            }
+        }
+        tx.commit()
+    }
+
+    fn replace_doc(
+        &mut self,
+        previous: Option<i64>,
+        meta: &SessionMeta,
+        fingerprint: Fingerprint,
+        messages: &[SessionMessage],
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        if let Some(id) = previous {
+            remove_doc(&tx, id)?;
+        }
+        tx.execute(
+            "insert into doc (provider_id, session_id, source_path, title, project_dir, last_active_at, fingerprint)
+             values (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                meta.provider_id,
+                meta.session_id,
+                meta.source_path,
+                meta.title,
+                meta.project_dir,
+                meta.last_active_at,
+                serde_json::to_string(&fingerprint).unwrap_or_default(),
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+
+        {
+            let mut insert_message = tx.prepare(
+                "insert into message (doc_id, idx, role, ts, len, line_offset) values (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            let mut insert_posting = tx.prepare(
+                "insert into posting (term, doc_id, idx, tf, len) values (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (index, message) in messages.iter().enumerate() {
+                let tokens = tokenize(&message.content);
+                let mut frequencies: HashMap<&str, u32> = HashMap::new();
+                for token in &tokens {
+                    *frequencies.entry(token.term.as_str()).or_default() += 1;
+                }
+                let len = tokens.len() as u32;
+                insert_message.execute(params![
+                    id,
+                    index as u32,
+                    message.role,
+                    message.ts,
+                    len,
+                    message.line_offset,
+                ])?;
+                for (term, tf) in frequencies {
+                    insert_posting.execute(params![term, id, index as u32, tf, len])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+
+    // 查询
+
+    /// 返回同时包含全部词的消息及其 BM25 得分, 按得分倒序
+    fn rank(&self, terms: &[String]) -> rusqlite::Result<Vec<(i64, u32, f64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("select doc_id, idx, tf, len from posting where term = ?1")?;
+        let mut lists = Vec::with_capacity(terms.len());
+        for term in terms {
+            let list = stmt
+                .query_map([term], |row| {
+                    Ok(Posting {
+                        doc_id: row.get(0)?,
+                        index: row.get(1)?,
+                        tf: row.get(2)?,
+                        len: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if list.is_empty() {
+                return Ok(Vec::new());
+            }
+            lists.push(list);
+        }
+        lists.sort_by_key(|list| list.len());
+
+        let (total_messages, total_len): (u64, u64) = self.conn.query_row(
+            "select count(*), coalesce(sum(len), 0) from message",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let avg_len = (total_len as f64 / total_messages.max(1) as f64).max(1.0);
+
+        let mut scores: HashMap<(i64, u32), f64> = HashMap::new();
+        for (position, list) in lists.iter().enumerate() {
+            let df = list.len() as f64;
+            let idf = (1.0 + (total_messages as f64 - df + 0.5) / (df + 0.5)).ln();
+            let mut next = HashMap::new();
+            for posting in list {
+                let key = (posting.doc_id, posting.index);
+                let previous = if position == 0 {
+                    0.0
+                } else {
+                    match scores.get(&key) {
+                        Some(score) => *score,
+                        None => continue,
+                    }
+                };
+                let tf = posting.tf as f64;
+                let norm = 1.0 - BM25_B + BM25_B * posting.len as f64 / avg_len;
+                next.insert(
+                    key,
+                    previous + idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm),
+                );
+            }
+            scores = next;
+        }
+
+        let mut ranked: Vec<(i64, u32, f64)> = scores
+            .into_iter()
+            .map(|((doc_id, message_index), score)| (doc_id, message_index, score))
+            .collect();
+        ranked.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+        Ok(ranked)
+    }
+
+    fn doc(&self, id: i64) -> rusqlite::Result<Option<IndexedDoc>> {
+        self.conn
+            .query_row(
+                "select provider_id, session_id, source_path, title, project_dir, last_active_at
+                 from doc where id = ?1",
+                [id],
+                |row| {
+                    Ok(IndexedDoc {
+                        provider_id: row.get(0)?,
+                        session_id: row.get(1)?,
+                        source_path: row.get(2)?,
+                        title: row.get(3)?,
+                        project_dir: row.get(4)?,
+                        last_active_at: row.get(5)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    fn message(&self, doc_id: i64, index: u32) -> rusqlite::Result<Option<IndexedMessage>> {
+        self.conn
+            .query_row(
+                "select role, ts, line_offset from message where doc_id = ?1 and idx = ?2",
+                params![doc_id, index],
+                |row| {
+                    Ok(IndexedMessage {
+                        role: row.get(0)?,
+                        ts: row.get(1)?,
+                        line_offset: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
+
+fn remove_doc(conn: &Connection, id: i64) -> rusqlite::Result<()> {
+    conn.execute("delete from posting where doc_id = ?1", [id])?;
+    conn.execute("delete from message where doc_id = ?1", [id])?;
+    conn.execute("delete from doc where id = ?1", [id])?;
+    Ok(())
+}
+
+fn matches(doc: &IndexedDoc, message: &IndexedMessage, filter: &SessionSearchQuery) -> bool {
+    let ts = message.ts.or(doc.last_active_at);
+
+    (filter.providers.is_empty() || filter.providers.contains(&doc.provider_id))
+        && (filter.roles.is_empty() || filter.roles.contains(&message.role))
+        && filter.project_dir.as_deref().is_none_or(|dir| {
+            doc.project_dir
+                .as_deref()
+                .is_some_and(|project| Path::new(project).starts_with(dir))
+        })
+        && filter
+            .since
+            .is_none_or(|since| ts.is_some_and(|ts| ts >= since))
+        && filter
+            .until
+            .is_none_or(|until| ts.is_some_and(|ts| ts <= until))
+}
+
+/// 读取命中的消息: 有行偏移时只解析该行, 否则完整加载对话并缓存
+fn read_hit_message(
+    doc: &IndexedDoc,
+    message: &IndexedMessage,
+    message_index: u32,
+    loaded: &mut HashMap<String, Vec<SessionMessage>>,
+) -> Option<SessionMessage> {
+    let provider = find_provider(&doc.provider_id).ok()?;
+    let path = Path::new(&doc.source_path);
+    if let (Some(offset), Some(_)) = (message.line_offset, provider.line_parser()) {
+        return read_message_at(provider, path, offset).ok();
+    }
+    loaded
+        .entry(doc.source_path.clone())
+        .or_insert_with(|| provider.load_messages(path).unwrap_or_default())
+        .get(message_index as usize)
+        .cloned()
+}
+
+fn get_index_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join(INDEX_FILE_NAME)
+}
+
+// 分词与查询解析
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+/// 分词: 连续的字母数字为一个词 (转小写), CJK 字符逐字成词
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start = None;
+
+    for (offset, c) in text.char_indices() {
+        if is_cjk(c) {
+            if let Some(start) = word_start.take() {
+                tokens.push(word_token(text, start, offset));
+            }
+            tokens.push(Token {
+                term: c.to_string(),
+                start: offset,
+                end: offset + c.len_utf8(),
+                cjk: true,
+            });
+        } else if c.is_alphanumeric() || c == '_' {
+            word_start.get_or_insert(offset);
+        } else if let Some(start) = word_start.take() {
+            tokens.push(word_token(text, start, offset));
+        }
+    }
+    if let Some(start) = word_start {
+        tokens.push(word_token(text, start, text.len()));
+    }
+
+    tokens
+}
+
+fn word_token(text: &str, start: usize, end: usize) -> Token {
+    Token {
+        term: text[start..end].to_lowercase(),
+        start,
+        end,
+        cjk: false,
+    }
+}
+
+/// 解析查询: 双引号内为短语; 引号外的词各自独立匹配, 但连续的 CJK 字符视为短语
+fn parse_query(query: &str) -> ParsedQuery {
+    let mut parsed = ParsedQuery {
+        terms: Vec::new(),
+        phrases: Vec::new(),
+    };
+
+    for (index, segment) in query.split('"').enumerate() {
+        let tokens = tokenize(segment);
+        if index % 2 == 1 {
+            push_phrase(&mut parsed, tokens.into_iter().map(|t| t.term).collect());
+            continue;
+        }
+
+        let mut run: Vec<String> = Vec::new();
+        let mut run_end = 0;
+        for token in tokens {
+            if token.cjk && (run.is_empty() || token.start == run_end) {
+                run.push(token.term);
+                run_end = token.end;
+                continue;
+            }
+            push_phrase(&mut parsed, std::mem::take(&mut run));
+            if token.cjk {
+                run.push(token.term);
+                run_end = token.end;
+            } else if !parsed.terms.contains(&token.term) {
+                parsed.terms.push(token.term);
+            }
+        }
+        push_phrase(&mut parsed, run);
+    }
+
+    parsed
+}
+
+fn push_phrase(parsed: &mut ParsedQuery, phrase: Vec<String>) {
+    match phrase.len() {
+        0 => {}
+        1 => {
+            let term = phrase.into_iter().next().unwrap_or_default();
+            if !parsed.terms.contains(&term) {
+                parsed.terms.push(term);
+            }
+        }
+        _ => parsed.phrases.push(phrase),
+    }
+}
+
+/// 计算消息中需要高亮的字节范围, 任一短语未出现时返回 None
+fn match_ranges(content: &str, query: &ParsedQuery) -> Option<Vec<(usize, usize)>> {
+    let tokens = tokenize(content);
+    let mut ranges = Vec::new();
+
+    for phrase in &query.phrases {
+        let mut found = false;
+        for window in tokens.windows(phrase.len()) {
+            if window
+                .iter()
+                .zip(phrase)
+                .all(|(token, term)| &token.term == term)
+            {
+                ranges.push((window[0].start, window[phrase.len() - 1].end));
+                found = true;
+            }
+        }
+        if !found {
+            return None;
+        }
+    }
+    for token in &tokens {
+        if query.terms.contains(&token.term) {
+            ranges.push((token.start, token.end));
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Some(merged)
+}
+
+/// 以首个命中为中心截取片段, 并拆分为普通与高亮文本
+fn build_snippet(content: &str, ranges: &[(usize, usize)]) -> Vec<SnippetPart> {
+    let first = ranges.first().map(|range| range.0).unwrap_or_default();
+    let start = content[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_BEFORE.saturating_sub(1))
+        .map(|(offset, _)| offset)
+        .unwrap_or(0);
+    let end = content[start..]
+        .char_indices()
+        .nth(SNIPPET_CHARS)
+        .map(|(offset, _)| start + offset)
+        .unwrap_or(content.len());
+
+    let mut parts = Vec::new();
+    let mut push = |text: &str, highlight: bool| {
+        if !text.is_empty() {
+            parts.push(SnippetPart {
+                text: text.to_string(),
+                highlight,
+            });
+        }
+    };
+
+    if start > 0 {
+        push("…", false);
+    }
+    let mut cursor = start;
+    for &(range_start, range_end) in ranges {
+        if range_start < cursor || range_end > end {
+            continue;
+        }
+        push(&content[cursor..range_start], false);
+        push(&content[range_start..range_end], true);
+        cursor = range_end;
+    }
+    push(&content[cursor..end], false);
+    if end < content.len() {
+        push("…", false);
+    }
+
+    parts
+}
+
+// Tauri 命令
+
+/// 全文搜索对话内容, 返回按相关度排序的命中消息
+/// 首次搜索或大量对话变化后需要重建索引, 在后台线程中执行
+#[tauri::command]
+pub async fn search_sessions(query: SessionSearchQuery) -> Result<Vec<SearchHit>, String> {
+    run_blocking(move || search(&query)).await
+}
+
+fn search(query: &SessionSearchQuery) -> Result<Vec<SearchHit>, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let providers = if query.providers.is_empty() {
+        all_providers().to_vec()
+    } else {
+        query
+            .providers
+            .iter()
+            .map(|id| find_provider(id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let parsed = parse_query(&query.query);
+    let terms = parsed.required_terms();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let metas = scan_cached(&providers, &home, false, &ScanControl::default())?.unwrap_or_default();
+    let mut guard = INDEX.lock().map_err(|_| "搜索索引不可用".to_string())?;
+    if guard.is_none() {
+        *guard = Some(SearchIndex::open().map_err(|e| format!("打开搜索索引失败: {}", e))?);
+    }
+    let index = guard.as_mut().ok_or("搜索索引不可用")?;
+    index
+        .refresh(&providers, metas)
+        .map_err(|e| format!("更新搜索索引失败: {}", e))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let ranked = index
+        .rank(&terms)
+        .map_err(|e| format!("读取搜索索引失败: {}", e))?;
+    let mut docs: HashMap<i64, Option<IndexedDoc>> = HashMap::new();
+    let mut loaded: HashMap<String, Vec<SessionMessage>> = HashMap::new();
+    let mut hits = Vec::new();
+
+    for (doc_id, message_index, score) in ranked {
+        if hits.len() >= limit {
+            break;
+        }
+        let doc = match docs.entry(doc_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                index
+                    .doc(doc_id)
+                    .map_err(|e| format!("读取搜索索引失败: {}", e))?,
+            ),
+        };
+        let Some(doc) = doc.as_ref() else {
+            continue;
+        };
+        let Some(indexed) = index
+            .message(doc_id, message_index)
+            .map_err(|e| format!("读取搜索索引失败: {}", e))?
+        else {
+            continue;
+        };
+        if !matches(doc, &indexed, query) {
+            continue;
+        }
+        let Some(message) = read_hit_message(doc, &indexed, message_index, &mut loaded) else {
+            continue;
+        };
+        let Some(ranges) = match_ranges(&message.content, &parsed) else {
+            continue;
+        };
+
+        hits.push(SearchHit {
+            provider_id: doc.provider_id.clone(),
+            session_id: doc.session_id.clone(),
+            source_path: doc.source_path.clone(),
+            title: doc.title.clone(),
+            project_dir: doc.project_dir.clone(),
+            message_index: message_index as usize,
+            role: message.role.clone(),
+            ts: message.ts,
+            score,
+            snippet: build_snippet(&message.content, &ranges),
+        });
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(tokens: &[Token]) -> Vec<(&str, usize, usize, bool)> {
+        tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.start, t.end, t.cjk))
+            .collect()
+    }
+
+    fn parts(snippet: &[SnippetPart]) -> Vec<(&str, bool)> {
+        snippet
+            .iter()
+            .map(|part| (part.text.as_str(), part.highlight))
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_splits_words_and_cjk_characters() {
+        assert_eq!(
+            terms(&tokenize("Hello, World_2 中文x")),
+            vec![
+                ("hello", 0, 5, false),
+                ("world_2", 7, 14, false),
+                ("中", 15, 18, true),
+                ("文", 18, 21, true),
+                ("x", 21, 22, false),
+            ]
+        );
+        assert!(tokenize(" ,.;- ").is_empty());
+    }
+
+    #[test]
+    fn parse_query_collects_terms_and_phrases() {
+        let parsed = parse_query(r#"Fix bug "read me" 搜索引擎 fix a 中 "solo""#);
+        assert_eq!(parsed.terms, vec!["fix", "bug", "a", "中", "solo"]);
+        assert_eq!(
+            parsed.phrases,
+            vec![
+                vec!["read".to_string(), "me".to_string()],
+                vec![
+                    "搜".to_string(),
+                    "索".to_string(),
+                    "引".to_string(),
+                    "擎".to_string()
+                ],
+            ]
+        );
+        assert_eq!(
+            parsed.required_terms(),
+            vec!["fix", "bug", "a", "中", "solo", "read", "me", "搜", "索", "引", "擎"]
+        );
+        assert!(parse_query(r#" "" , "#).required_terms().is_empty());
+    }
+
+    #[test]
+    fn match_ranges_requires_every_phrase() {
+        let content = "Read me first, then read ME again";
+        assert_eq!(
+            match_ranges(content, &parse_query(r#""read me""#)),
+            Some(vec![(0, 7), (20, 27)])
+        );
+        assert_eq!(match_ranges(content, &parse_query(r#""me read""#)), None);
+        // 单独的词不要求出现, 与短语重叠的范围合并
+        assert_eq!(
+            match_ranges(content, &parse_query(r#"read again missing "read me""#)),
+            Some(vec![(0, 7), (20, 27), (28, 33)])
+        );
+        assert_eq!(
+            match_ranges("中文搜索", &parse_query("搜索")),
+            Some(vec![(6, 12)])
+        );
+    }
+
+    #[test]
+    fn build_snippet_highlights_short_content() {
+        assert_eq!(
+            parts(&build_snippet("say hello world", &[(4, 9)])),
+            vec![("say ", false), ("hello", true), (" world", false)]
+        );
+    }
+
+    #[test]
+    fn build_snippet_centers_on_first_hit() {
+        let content = format!("{}needle{}", "a".repeat(100), "b".repeat(300));
+        let a = "a".repeat(SNIPPET_BEFORE);
+        let b = "b".repeat(SNIPPET_CHARS - SNIPPET_BEFORE - 6);
+        assert_eq!(
+            parts(&build_snippet(&content, &[(100, 106), (380, 386)])),
+            vec![
+                ("…", false),
+                (a.as_str(), false),
+                ("needle", true),
+                (b.as_str(), false),
+                ("…", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn build_snippet_cuts_on_char_boundaries() {
+        let content = format!("{}目标{}", "中".repeat(80), "文".repeat(300));
+        let start = "中".len() * 80;
+        let snippet = build_snippet(&content, &[(start, start + "目标".len())]);
+        assert_eq!(snippet[2].text, "目标");
+        assert!(snippet[2].highlight);
+        assert_eq!(snippet[1].text.chars().count(), SNIPPET_BEFORE);
+    }
+}
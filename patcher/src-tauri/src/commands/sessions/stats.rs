@@ -0,0 +1,169 @@
+//! 对话统计缓存
+//!
+//! 消息数与 token 用量需要加载完整对话, 扫描元数据时不统计;
+//! 按消息数过滤或排序、生成用量报告时按需计算, 与数据源指纹一同缓存
+
+use super::provider::find_provider;
+use super::usage::SessionUsage;
+use super::util::Fingerprint;
+use super::SessionMeta;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// 缓存格式版本, 统计逻辑变化时递增以丢弃旧缓存
+const STATS_VERSION: u32 = 1;
+const STATS_FILE_NAME: &str = "session-stats-cache.json";
+
+/// 已加载的缓存, 首次统计时从磁盘读取
+static CACHE: Mutex<Option<StatsCache>> = Mutex::new(None);
+
+/// 对话的消息数与用量
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    pub message_count: usize,
+    pub usage: Option<SessionUsage>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsCache {
+    version: u32,
+    /// provider id -> 数据源路径 -> 缓存项
+    providers: HashMap<String, HashMap<String, CachedStats>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedStats {
+    fingerprint: Fingerprint,
+    /// 无法加载的对话同样缓存, 避免反复读取
+    stats: Option<SessionStats>,
+}
+
+impl StatsCache {
+    fn load() -> Self {
+        fs::read_to_string(get_cache_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<StatsCache>(&content).ok())
+            .filter(|cache| cache.version == STATS_VERSION)
+            .unwrap_or_else(|| StatsCache {
+                version: STATS_VERSION,
+                providers: HashMap::new(),
+            })
+    }
+
+    /// 先写临时文件再替换, 避免中断时留下损坏的缓存
+    fn save(&self) -> Result<(), String> {
+        let path = get_cache_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建缓存目录失败: {}", e))?;
+        }
+        let content =
+            serde_json::to_string(self).map_err(|e| format!("序列化统计缓存失败: {}", e))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content).map_err(|e| format!("写入统计缓存失败: {}", e))?;
+        fs::rename(&temp, &path).map_err(|e| format!("写入统计缓存失败: {}", e))
+    }
+}
+
+fn get_cache_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join(STATS_FILE_NAME)
+}
+
+/// 为对话填充消息数与用量, 只加载新增或修改过的对话
+/// sessions 应为所属 provider 的完整扫描结果, 这些 provider 下不在其中的缓存项视为已删除;
+/// 缓存无法保存时返回错误 (sessions 已填充)
+pub fn attach_stats(sessions: &mut [SessionMeta]) -> Result<(), String> {
+    let mut guard = CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let cache = guard.get_or_insert_with(StatsCache::load);
+
+    let mut pending = Vec::new();
+    let mut changed = false;
+    for (index, meta) in sessions.iter_mut().enumerate() {
+        let Ok(provider) = find_provider(&meta.provider_id) else {
+            continue;
+        };
+        let fingerprint = provider.fingerprint(Path::new(&meta.source_path));
+        match cache
+            .providers
+            .get(&meta.provider_id)
+            .and_then(|entries| entries.get(&meta.source_path))
+            .filter(|cached| cached.fingerprint == fingerprint)
+        {
+            Some(cached) => apply(meta, cached.stats.as_ref()),
+            None => pending.push((index, provider, fingerprint)),
+        }
+    }
+
+    // 工作线程并行加载, 结果在当前线程写回
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(pending.len());
+    let computed: Vec<(usize, Option<SessionStats>)> = thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, pending, sessions) = (&next, &pending, &*sessions);
+            scope.spawn(move || loop {
+                let item = next.fetch_add(1, Ordering::Relaxed);
+                let Some((index, provider, _)) = pending.get(item) else {
+                    break;
+                };
+                if tx
+                    .send((item, provider.read_stats(&sessions[*index])))
+                    .is_err()
+                {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        rx.into_iter().collect()
+    });
+    for (item, stats) in computed {
+        let (index, _, fingerprint) = pending[item];
+        let meta = &mut sessions[index];
+        apply(meta, stats.as_ref());
+        cache
+            .providers
+            .entry(meta.provider_id.clone())
+            .or_default()
+            .insert(meta.source_path.clone(), CachedStats { fingerprint, stats });
+        changed = true;
+    }
+
+    let live: HashSet<(&str, &str)> = sessions
+        .iter()
+        .map(|meta| (meta.provider_id.as_str(), meta.source_path.as_str()))
+        .collect();
+    let provider_ids: HashSet<&str> = live.iter().map(|(provider_id, _)| *provider_id).collect();
+    for (provider_id, entries) in cache.providers.iter_mut() {
+        if provider_ids.contains(provider_id.as_str()) {
+            let before = entries.len();
+            entries.retain(|path, _| live.contains(&(provider_id.as_str(), path.as_str())));
+            changed |= entries.len() != before;
+        }
+    }
+
+    if changed {
+        cache.save()?;
+    }
+    Ok(())
+}
+
+fn apply(meta: &mut SessionMeta, stats: Option<&SessionStats>) {
+    meta.message_count = stats.map(|stats| stats.message_count);
+    meta.usage = stats.and_then(|stats| stats.usage.clone());
+}
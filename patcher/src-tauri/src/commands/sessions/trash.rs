@@ -0,0 +1,305 @@
+//! 已删除对话的回收站
+//!
+//! 删除对话时将其全部文件移入应用数据目录下的回收站, 每个对话一个目录,
+//! manifest.json 记录原始路径与 provider 恢复索引所需的数据.
+//! 超过保留天数的对话在下次删除或列出回收站时清除
+
+use super::cache::cached_meta;
+use super::provider::{find_provider, SessionProvider};
+use super::util::{now_ms, path_size, remove_path};
+use crate::commands::audit::{self, AuditRecord};
+use crate::commands::config;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// 回收站目录内存放对话文件的子目录
+const FILES_DIR_NAME: &str = "files";
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+const DAY_MS: u64 = 86_400_000;
+
+/// 回收站中的一个对话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    pub provider_id: String,
+    pub session_id: String,
+    pub title: Option<String>,
+    pub project_dir: Option<String>,
+    pub source_path: String,
+    pub deleted_at: u64,
+    pub items: Vec<TrashItem>,
+    /// provider 恢复索引 (例如数据库记录) 所需的数据
+    #[serde(default)]
+    pub detached: Option<Value>,
+}
+
+/// 移入回收站的单个文件或目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub original_path: String,
+    /// 在回收站 files 目录中的名称
+    pub stored_name: String,
+    pub size: u64,
+}
+
+fn get_trash_dir() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("anti-power")
+        .join("trash")
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// 移动文件或目录, 跨文件系统时退回到复制后删除
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to).inspect_err(|_| {
+        let _ = remove_path(to);
+    })?;
+    remove_path(from)
+}
+
+fn read_manifest(dir: &Path) -> Option<TrashEntry> {
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE_NAME)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_manifest(dir: &Path, entry: &TrashEntry) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(entry).map_err(|e| format!("序列化回收站记录失败: {}", e))?;
+    fs::write(dir.join(MANIFEST_FILE_NAME), content)
+        .map_err(|e| format!("写入回收站记录失败: {}", e))
+}
+
+/// 生成回收站目录名: 删除时间加对话 id 中可用作文件名的字符
+pub fn new_entry_dir(trash_dir: &Path, deleted_at: u64, session_id: &str) -> PathBuf {
+    let safe_id: String = session_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(64)
+        .collect();
+    let base = format!("{}-{}", deleted_at, safe_id);
+    let mut dir = trash_dir.join(&base);
+    let mut suffix = 1;
+    while dir.exists() {
+        dir = trash_dir.join(format!("{}-{}", base, suffix));
+        suffix += 1;
+    }
+    dir
+}
+
+fn load_entries(trash_dir: &Path) -> Vec<(PathBuf, TrashEntry)> {
+    let Ok(entries) = fs::read_dir(trash_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|dir| read_manifest(&dir).map(|entry| (dir, entry)))
+        .collect()
+}
+
+/// 清除超过保留天数的对话, 返回清除的数量
+fn purge_expired(trash_dir: &Path) -> usize {
+    let days = config::get_config()
+        .trash_retention_days
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    if days == 0 {
+        return 0;
+    }
+    let cutoff = now_ms().saturating_sub(days as u64 * DAY_MS);
+    load_entries(trash_dir)
+        .into_iter()
+        .filter(|(_, entry)| entry.deleted_at < cutoff)
+        .filter(|(dir, _)| fs::remove_dir_all(dir).is_ok())
+        .count()
+}
+
+/// 将对话的全部文件移入回收站, 并清理 provider 在其他位置的索引
+/// 移动中途失败时已移动的文件会放回原处
+pub fn move_to_trash(
+    provider: &dyn SessionProvider,
+    source_path: &Path,
+    session_id: &str,
+) -> Result<TrashEntry, String> {
+    let paths: Vec<PathBuf> = provider
+        .session_paths(source_path, session_id)?
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+    // 只保存在数据库中的对话没有文件, 由 detach 保存其记录
+    if paths.is_empty() && !provider.source_exists(source_path) {
+        return Err(format!("文件不存在: {}", source_path.display()));
+    }
+    let meta = cached_meta(provider, &source_path.to_string_lossy());
+
+    let trash_dir = get_trash_dir();
+    purge_expired(&trash_dir);
+    let deleted_at = now_ms();
+    let entry_dir = new_entry_dir(&trash_dir, deleted_at, session_id);
+    let files_dir = entry_dir.join(FILES_DIR_NAME);
+    fs::create_dir_all(&files_dir).map_err(|e| format!("创建回收站目录失败: {}", e))?;
+
+    let mut items: Vec<TrashItem> = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        let stored_name = index.to_string();
+        let size = path_size(path);
+        if let Err(err) = move_path(path, &files_dir.join(&stored_name)) {
+            for (item, original) in items.iter().zip(&paths) {
+                let _ = move_path(&files_dir.join(&item.stored_name), original);
+            }
+            let _ = fs::remove_dir_all(&entry_dir);
+            return Err(format!("移入回收站失败: {}: {}", path.display(), err));
+        }
+        audit::track_file(path);
+        items.push(TrashItem {
+            original_path: path.to_string_lossy().to_string(),
+            stored_name,
+            size,
+        });
+    }
+
+    let mut entry = TrashEntry {
+        id: entry_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        provider_id: provider.id().to_string(),
+        session_id: session_id.to_string(),
+        title: meta.as_ref().and_then(|meta| meta.title.clone()),
+        project_dir: meta.as_ref().and_then(|meta| meta.project_dir.clone()),
+        source_path: source_path.to_string_lossy().to_string(),
+        deleted_at,
+        items,
+        detached: None,
+    };
+    write_manifest(&entry_dir, &entry)?;
+
+    // 文件已在回收站中, 索引清理失败时仍可恢复
+    entry.detached = provider
+        .detach(source_path, session_id)
+        .map_err(|e| format!("对话已移入回收站, 但{}", e))?;
+    if entry.detached.is_some() {
+        write_manifest(&entry_dir, &entry)?;
+    }
+    Ok(entry)
+}
+
+fn restore_entry(trash_id: &str) -> Result<TrashEntry, String> {
+    let entry_dir = find_entry_dir(trash_id)?;
+    let entry = read_manifest(&entry_dir).ok_or("回收站记录已损坏")?;
+    let provider = find_provider(&entry.provider_id)?;
+    let files_dir = entry_dir.join(FILES_DIR_NAME);
+
+    if let Some(item) = entry
+        .items
+        .iter()
+        .find(|item| Path::new(&item.original_path).exists())
+    {
+        return Err(format!("原位置已存在同名文件: {}", item.original_path));
+    }
+    for item in &entry.items {
+        let original = Path::new(&item.original_path);
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        move_path(&files_dir.join(&item.stored_name), original)
+            .map_err(|e| format!("恢复失败: {}: {}", item.original_path, e))?;
+        audit::track_file(original);
+    }
+    if let Some(detached) = &entry.detached {
+        provider.reattach(detached)?;
+    }
+
+    let _ = fs::remove_dir_all(&entry_dir);
+    Ok(entry)
+}
+
+fn remove_entries(trash_ids: Option<&[String]>) -> Result<usize, String> {
+    let dirs = match trash_ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| find_entry_dir(id))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => load_entries(&get_trash_dir())
+            .into_iter()
+            .map(|(dir, _)| dir)
+            .collect(),
+    };
+    for dir in &dirs {
+        fs::remove_dir_all(dir).map_err(|e| format!("删除失败: {}", e))?;
+        audit::track_file(dir);
+    }
+    Ok(dirs.len())
+}
+
+fn find_entry_dir(trash_id: &str) -> Result<PathBuf, String> {
+    // trash_id 只能是回收站下的目录名
+    if trash_id.is_empty() || trash_id.contains(['/', '\\']) || trash_id.starts_with('.') {
+        return Err(format!("无效的回收站 id: {}", trash_id));
+    }
+    let dir = get_trash_dir().join(trash_id);
+    if !dir.join(MANIFEST_FILE_NAME).is_file() {
+        return Err(format!("回收站中不存在该对话: {}", trash_id));
+    }
+    Ok(dir)
+}
+
+// Tauri 命令
+
+/// 列出回收站中的对话, 按删除时间倒序; 先清除超过保留天数的对话
+#[tauri::command]
+pub fn list_trash() -> Result<Vec<TrashEntry>, String> {
+    let trash_dir = get_trash_dir();
+    purge_expired(&trash_dir);
+    let mut entries: Vec<TrashEntry> = load_entries(&trash_dir)
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+/// 将回收站中的对话恢复到原位置, 任一原路径已被占用时不做任何改动
+#[tauri::command]
+pub fn restore_session(trash_id: String) -> Result<TrashEntry, String> {
+    let audit = AuditRecord::new("restore_session")
+        .with_config(serde_json::json!({ "trashId": &trash_id }));
+
+    let result = restore_entry(&trash_id);
+
+    audit.finish_with_message(result)
+}
+
+/// 永久删除回收站中的对话, trash_ids 为空时清空回收站; 返回删除的数量
+#[tauri::command]
+pub fn empty_trash(trash_ids: Option<Vec<String>>) -> Result<usize, String> {
+    let audit =
+        AuditRecord::new("empty_trash").with_config(serde_json::json!({ "trashIds": &trash_ids }));
+
+    let result = remove_entries(trash_ids.as_deref());
+
+    audit.finish_with_message(result)
+}
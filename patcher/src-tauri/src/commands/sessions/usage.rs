@@ -0,0 +1,435 @@
+//! Token 用量统计
+//!
+//! 各 provider 解析时为消息填充用量, 对话级用量按 (小时, 模型) 分桶后随统计缓存保存,
+//! 用量报告汇总缓存中的分桶, 只重新加载新增或修改过的对话
+
+use super::cache::scan_cached;
+use super::provider::{all_providers, find_provider};
+use super::scan::ScanControl;
+use super::stats::attach_stats;
+use super::util::{format_iso8601, run_blocking};
+use super::SessionMessage;
+use crate::commands::config::{self, ModelPrice};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::AddAssign;
+use std::path::Path;
+
+const HOUR_MS: u64 = 3_600_000;
+/// 无法确定模型时使用的名称
+const UNKNOWN_MODEL: &str = "unknown";
+
+/// Token 用量
+/// input 不含缓存命中部分, output 不含推理部分, 各字段互不重叠
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    pub reasoning: u64,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.input + self.output + self.cache_read + self.cache_write + self.reasoning
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// 按单价 (每百万 token) 估算费用, 推理 token 按输出价格计算
+    fn cost(&self, price: &ModelPrice) -> f64 {
+        (self.input as f64 * price.input
+            + (self.output + self.reasoning) as f64 * price.output
+            + self.cache_read as f64 * price.cache_read
+            + self.cache_write as f64 * price.cache_write)
+            / 1_000_000.0
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_read += other.cache_read;
+        self.cache_write += other.cache_write;
+        self.reasoning += other.reasoning;
+    }
+}
+
+/// 对话级用量
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SessionUsage {
+    pub total: TokenUsage,
+    pub models: Vec<ModelUsage>,
+    /// 按 (小时, 模型) 分桶的用量, 供报告按天汇总
+    pub buckets: Vec<UsageBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    pub model: String,
+    pub usage: TokenUsage,
+    /// 带用量的消息数
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// 整点的毫秒时间戳, 消息没有时间时为 None
+    pub hour: Option<u64>,
+    pub model: String,
+    pub usage: TokenUsage,
+}
+
+impl SessionUsage {
+    /// 汇总消息用量, 没有任何用量时返回 None
+    pub fn from_messages(messages: &[SessionMessage]) -> Option<Self> {
+        let mut models: BTreeMap<&str, ModelUsage> = BTreeMap::new();
+        let mut buckets: BTreeMap<(Option<u64>, &str), TokenUsage> = BTreeMap::new();
+        let mut total = TokenUsage::default();
+
+        for message in messages {
+            let Some(usage) = message.usage.filter(|usage| !usage.is_empty()) else {
+                continue;
+            };
+            let model = message.model.as_deref().unwrap_or(UNKNOWN_MODEL);
+            total += usage;
+            let entry = models.entry(model).or_insert_with(|| ModelUsage {
+                model: model.to_string(),
+                usage: TokenUsage::default(),
+                messages: 0,
+            });
+            entry.usage += usage;
+            entry.messages += 1;
+            *buckets
+                .entry((message.ts.map(|ts| ts - ts % HOUR_MS), model))
+                .or_default() += usage;
+        }
+
+        if total.is_empty() {
+            return None;
+        }
+        Some(SessionUsage {
+            total,
+            models: models.into_values().collect(),
+            buckets: buckets
+                .into_iter()
+                .map(|((hour, model), usage)| UsageBucket {
+                    hour,
+                    model: model.to_string(),
+                    usage,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// 用量报告条件
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UsageReportQuery {
+    /// 为空时统计全部 provider
+    pub providers: Vec<String>,
+    /// 仅统计该目录 (含子目录) 下项目的对话
+    pub project_dir: Option<String>,
+    /// 时间范围 (毫秒时间戳, 含), 按消息时间过滤
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    /// 本地时区相对 UTC 的偏移 (分钟), 用于按本地日期分组
+    pub utc_offset_minutes: i64,
+}
+
+/// 用量报告
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub total: UsageGroup,
+    /// key 为 YYYY-MM-DD, 没有时间的用量不计入
+    pub by_day: Vec<UsageGroup>,
+    /// key 为项目目录, 未知项目为空字符串
+    pub by_project: Vec<UsageGroup>,
+    pub by_provider: Vec<UsageGroup>,
+    pub by_model: Vec<UsageGroup>,
+    /// 有用量但单价表中没有的模型, 其费用未计入 cost
+    pub unpriced_models: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageGroup {
+    pub key: String,
+    pub usage: TokenUsage,
+    /// 未配置单价表时为 None
+    pub cost: Option<f64>,
+    pub sessions: usize,
+}
+
+/// 按分组键累计用量与涉及的对话数
+#[derive(Default)]
+struct GroupBuilder {
+    groups: BTreeMap<String, (UsageGroup, HashSet<usize>)>,
+}
+
+impl GroupBuilder {
+    fn add(&mut self, key: &str, session: usize, usage: TokenUsage, cost: Option<f64>) {
+        let (group, sessions) = self.groups.entry(key.to_string()).or_insert_with(|| {
+            (
+                UsageGroup {
+                    key: key.to_string(),
+                    ..UsageGroup::default()
+                },
+                HashSet::new(),
+            )
+        });
+        group.usage += usage;
+        if let Some(cost) = cost {
+            *group.cost.get_or_insert(0.0) += cost;
+        }
+        sessions.insert(session);
+    }
+
+    fn finish(self) -> Vec<UsageGroup> {
+        self.groups
+            .into_values()
+            .map(|(mut group, sessions)| {
+                group.sessions = sessions.len();
+                group
+            })
+            .collect()
+    }
+}
+
+/// 查找模型单价: 精确匹配优先, 其次取最长的前缀匹配
+fn find_price<'a>(prices: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+    prices
+        .iter()
+        .find(|price| price.model == model)
+        .or_else(|| {
+            prices
+                .iter()
+                .filter(|price| price.matches(model))
+                .max_by_key(|price| price.model.len())
+        })
+}
+
+// Tauri 命令
+
+/// 汇总对话用量, 按天、项目目录、provider 与模型分组, 配置了单价表时估算费用
+/// 新增或修改过的对话需要重新加载统计, 在后台线程中执行
+#[tauri::command]
+pub async fn session_usage_report(query: Option<UsageReportQuery>) -> Result<UsageReport, String> {
+    run_blocking(move || usage_report(&query.unwrap_or_default())).await
+}
+
+fn usage_report(query: &UsageReportQuery) -> Result<UsageReport, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let providers = if query.providers.is_empty() {
+        all_providers().to_vec()
+    } else {
+        query
+            .providers
+            .iter()
+            .map(|id| find_provider(id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let prices = config::get_config().model_prices;
+    let offset_ms = query.utc_offset_minutes * 60_000;
+
+    let mut sessions =
+        scan_cached(&providers, &home, false, &ScanControl::default())?.unwrap_or_default();
+    attach_stats(&mut sessions)?;
+
+    let mut total = GroupBuilder::default();
+    let mut by_day = GroupBuilder::default();
+    let mut by_project = GroupBuilder::default();
+    let mut by_provider = GroupBuilder::default();
+    let mut by_model = GroupBuilder::default();
+    let mut unpriced = HashSet::new();
+
+    for (index, meta) in sessions.iter().enumerate() {
+        let Some(usage) = &meta.usage else {
+            continue;
+        };
+        let project = meta.project_dir.as_deref().unwrap_or_default();
+        if let Some(dir) = &query.project_dir {
+            if meta.project_dir.is_none() || !Path::new(project).starts_with(dir) {
+                continue;
+            }
+        }
+
+        for bucket in &usage.buckets {
+            // 分桶按整点对齐, 时间范围的精度为一小时
+            let in_range = match bucket.hour {
+                Some(hour) => {
+                    query.since.is_none_or(|since| hour + HOUR_MS > since)
+                        && query.until.is_none_or(|until| hour <= until)
+                }
+                None => query.since.is_none() && query.until.is_none(),
+            };
+            if !in_range {
+                continue;
+            }
+
+            let cost = if prices.is_empty() {
+                None
+            } else {
+                match find_price(&prices, &bucket.model) {
+                    Some(price) => Some(bucket.usage.cost(price)),
+                    None => {
+                        unpriced.insert(bucket.model.clone());
+                        Some(0.0)
+                    }
+                }
+            };
+
+            total.add("", index, bucket.usage, cost);
+            if let Some(hour) = bucket.hour {
+                let local = (hour as i64 + offset_ms).max(0) as u64;
+                by_day.add(&format_iso8601(local)[..10], index, bucket.usage, cost);
+            }
+            by_project.add(project, index, bucket.usage, cost);
+            by_provider.add(&meta.provider_id, index, bucket.usage, cost);
+            by_model.add(&bucket.model, index, bucket.usage, cost);
+        }
+    }
+
+    let mut unpriced_models: Vec<String> = unpriced.into_iter().collect();
+    unpriced_models.sort();
+
+    Ok(UsageReport {
+        total: total.finish().pop().unwrap_or_default(),
+        by_day: by_day.finish(),
+        by_project: by_project.finish(),
+        by_provider: by_provider.finish(),
+        by_model: by_model.finish(),
+        unpriced_models,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(model: Option<&str>, ts: Option<u64>, usage: Option<TokenUsage>) -> SessionMessage {
+        SessionMessage {
+            id: None,
+            role: "assistant".to_string(),
+            content: String::new(),
+            ts,
+            blocks: Vec::new(),
+            model: model.map(str::to_string),
+            usage,
+            line_offset: None,
+        }
+    }
+
+    fn price(model: &str, input: f64) -> ModelPrice {
+        ModelPrice {
+            model: model.to_string(),
+            input,
+            ..ModelPrice::default()
+        }
+    }
+
+    #[test]
+    fn from_messages_groups_by_model_and_hour() {
+        let usage = |input, output, cache_read| TokenUsage {
+            input,
+            output,
+            cache_read,
+            ..TokenUsage::default()
+        };
+        let messages = [
+            message(Some("claude-a"), Some(1_000), Some(usage(10, 5, 0))),
+            message(Some("claude-a"), Some(HOUR_MS + 5), Some(usage(0, 0, 100))),
+            message(None, None, Some(usage(0, 7, 0))),
+            message(Some("claude-b"), Some(2_000), Some(TokenUsage::default())),
+            message(Some("claude-b"), Some(3_000), None),
+        ];
+        let session = SessionUsage::from_messages(&messages).unwrap();
+
+        assert_eq!(session.total, usage(10, 12, 100));
+        let models: Vec<_> = session
+            .models
+            .iter()
+            .map(|m| (m.model.as_str(), m.usage, m.messages))
+            .collect();
+        assert_eq!(
+            models,
+            vec![
+                ("claude-a", usage(10, 5, 100), 2),
+                (UNKNOWN_MODEL, usage(0, 7, 0), 1),
+            ]
+        );
+        let buckets: Vec<_> = session
+            .buckets
+            .iter()
+            .map(|b| (b.hour, b.model.as_str(), b.usage))
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![
+                (None, UNKNOWN_MODEL, usage(0, 7, 0)),
+                (Some(0), "claude-a", usage(10, 5, 0)),
+                (Some(HOUR_MS), "claude-a", usage(0, 0, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_messages_without_usage_is_none() {
+        let messages = [
+            message(Some("m"), Some(1), None),
+            message(Some("m"), Some(2), Some(TokenUsage::default())),
+        ];
+        assert!(SessionUsage::from_messages(&messages).is_none());
+        assert!(SessionUsage::from_messages(&[]).is_none());
+    }
+
+    #[test]
+    fn find_price_prefers_exact_then_longest_prefix() {
+        let prices = [
+            price("claude-*", 1.0),
+            price("claude-3-opus*", 2.0),
+            price("claude-3-opus-20240229", 3.0),
+            price("gpt-4o", 4.0),
+            price("o1*", 5.0),
+            price("o1", 6.0),
+        ];
+        let input = |model| find_price(&prices, model).map(|price| price.input);
+        assert_eq!(input("claude-3-opus-20240229"), Some(3.0));
+        assert_eq!(input("claude-3-opus-latest"), Some(2.0));
+        assert_eq!(input("claude-sonnet-4"), Some(1.0));
+        assert_eq!(input("gpt-4o"), Some(4.0));
+        assert_eq!(input("gpt-4o-mini"), None);
+        assert_eq!(input("o1"), Some(6.0));
+        assert_eq!(input("o1-mini"), Some(5.0));
+        assert_eq!(input(UNKNOWN_MODEL), None);
+    }
+
+    #[test]
+    fn cost_charges_reasoning_at_output_price() {
+        let usage = TokenUsage {
+            input: 1_000_000,
+            output: 500_000,
+            cache_read: 2_000_000,
+            cache_write: 100_000,
+            reasoning: 500_000,
+        };
+        let price = ModelPrice {
+            model: "m".to_string(),
+            input: 3.0,
+            output: 15.0,
+            cache_read: 0.3,
+            cache_write: 3.75,
+        };
+        assert!((usage.cost(&price) - 18.975).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,476 @@
+//! 对话解析与删除的通用工具函数
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// 从 JSON Value 中提取文本内容
+/// 兼容三种格式：纯字符串、对象数组（含 text/input_text/output_text）、单对象
+pub fn extract_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(arr) => {
+            let mut parts = Vec::new();
+            for item in arr {
+                if let Some(s) = item.get("text").and_then(|v| v.as_str()) {
+                    parts.push(s.to_string());
+                } else if let Some(s) = item.get("input_text").and_then(|v| v.as_str()) {
+                    parts.push(s.to_string());
+                } else if let Some(s) = item.get("output_text").and_then(|v| v.as_str()) {
+                    parts.push(s.to_string());
+                } else if let Some(s) = item.get("content").and_then(|v| v.as_str()) {
+                    parts.push(s.to_string());
+                }
+            }
+            parts.join("\n")
+        }
+        Value::Object(obj) => obj
+            .get("text")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+/// 解析工具参数, 字符串形式的 JSON 展开为对象, 无法解析时保留原字符串
+pub fn parse_tool_input(value: &Value) -> Value {
+    match value {
+        Value::String(raw) => serde_json::from_str(raw).unwrap_or_else(|_| value.clone()),
+        other => other.clone(),
+    }
+}
+
+/// 工具输出转为文本, 非文本的结构化输出保留为 JSON 字符串
+pub fn tool_output_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(_) => extract_text(value),
+        other => other.to_string(),
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// 标准 base64 编码 (带填充)
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// base64 解码, 兼容 URL 安全字符与缺省填充, 含非法字符时返回 None
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim().trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// 截断摘要到指定字符数
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_chars).collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// 标题的最大字符数
+const TITLE_MAX_CHARS: usize = 80;
+
+/// CLI 以用户身份注入的内容 (命令输出、环境信息、项目说明等) 的开头
+const INJECTED_PROMPT_PREFIXES: &[&str] = &[
+    "Caveat: The messages below were generated",
+    "# AGENTS.md instructions",
+    "[Request interrupted by user",
+];
+
+/// 由用户消息生成标题: 取第一行非空文本;
+/// 以 XML 标签开头的包装消息 (如 <command-name>、<environment_context>) 与 CLI 注入的内容返回 None
+pub fn prompt_title(text: &str) -> Option<String> {
+    let text = text.trim();
+    let is_wrapper = text.strip_prefix('<').is_some_and(|rest| {
+        rest.split_once('>').is_some_and(|(tag, _)| {
+            !tag.is_empty()
+                && tag
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+    });
+    if is_wrapper
+        || INJECTED_PROMPT_PREFIXES
+            .iter()
+            .any(|prefix| text.starts_with(prefix))
+    {
+        return None;
+    }
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| truncate(line, TITLE_MAX_CHARS))
+}
+
+/// 将时间字段解析为毫秒级 Unix 时间戳
+pub fn parse_timestamp(value: &Value) -> Option<u64> {
+    value
+        .as_str()
+        .and_then(|s| {
+            // 纯数字字符串时，兼容秒级与毫秒级时间戳
+            if let Ok(ts) = s.parse::<u64>() {
+                return Some(if ts > 1_000_000_000_000 {
+                    ts
+                } else {
+                    ts * 1000
+                });
+            }
+
+            // 非纯数字字符串时，尝试解析 ISO 8601 / RFC 3339 格式
+            parse_iso8601_to_ms(s)
+        })
+        .or_else(|| {
+            // 直接兼容 JSON 数字类型
+            value.as_f64().map(|f| {
+                let ts = f as u64;
+                if ts > 1_000_000_000_000 {
+                    ts
+                } else {
+                    ts * 1000
+                }
+            })
+        })
+}
+
+/// 简易 ISO 8601 解析 (无需引入 chrono)
+pub fn parse_iso8601_to_ms(s: &str) -> Option<u64> {
+    // 格式: 2024-01-15T10:30:00.000Z 或 2024-01-15T10:30:00+08:00
+    let s = s.trim();
+    if s.len() < 19 {
+        return None;
+    }
+
+    let year: i64 = s.get(0..4)?.parse().ok()?;
+    let month: i64 = s.get(5..7)?.parse().ok()?;
+    let day: i64 = s.get(8..10)?.parse().ok()?;
+    let hour: i64 = s.get(11..13)?.parse().ok()?;
+    let minute: i64 = s.get(14..16)?.parse().ok()?;
+    let second: i64 = s.get(17..19)?.parse().ok()?;
+
+    // 简易计算（不考虑闰秒等，足够用于排序和展示）
+    let days_since_epoch = days_from_civil(year, month, day)?;
+    let secs = days_since_epoch * 86400 + hour * 3600 + minute * 60 + second;
+
+    // 解析毫秒部分
+    let mut millis: i64 = 0;
+    let rest = &s[19..];
+    if let Some(frac) = rest.strip_prefix('.') {
+        let frac_digits: String = frac.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if !frac_digits.is_empty() {
+            let padded = format!("{:0<3}", &frac_digits[..frac_digits.len().min(3)]);
+            millis = padded.parse().unwrap_or(0);
+        }
+    }
+
+    Some((secs * 1000 + millis) as u64)
+}
+
+/// 计算从 1970-01-01 起的天数 (civil date to days)
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let y = if month <= 2 { year - 1 } else { year };
+    let m = if month <= 2 { month + 9 } else { month - 3 };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146097 + doe - 719468)
+}
+
+/// 毫秒时间戳格式化为 UTC 的 ISO 8601 字符串 (days_from_civil 的逆运算)
+pub fn format_iso8601(ms: u64) -> String {
+    let secs = (ms / 1000) as i64;
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 获取文件系统的最后修改时间（毫秒 epoch）
+pub fn file_modified_ms(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// 数据源的修改时间与大小, 变化时需要重新解析
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint(u64, u64);
+
+impl Fingerprint {
+    /// 由不对应单个文件的数据源自行给出的修改时间与大小
+    pub fn new(modified: u64, size: u64) -> Self {
+        Fingerprint(modified, size)
+    }
+
+    /// 读取路径的修改时间与大小, 路径不存在时返回零值
+    pub fn of(path: &Path) -> Self {
+        let Ok(metadata) = fs::metadata(path) else {
+            return Fingerprint(0, 0);
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Fingerprint(modified, metadata.len())
+    }
+}
+
+/// 文件或目录的总大小, 目录递归统计, 不跟随符号链接
+pub fn path_size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| path_size(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or_default(),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+/// 删除文件或整个目录
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// tar 中普通文件的头部; 路径由 tar::Builder::append_data 写入, 过长时写为 GNU 长文件名
+pub fn tar_header(size: u64, mtime_secs: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime_secs);
+    header
+}
+
+/// 当前时间的毫秒时间戳
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 提取路径最后一个组件
+pub fn path_basename(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_string())
+}
+
+/// 递归收集目录下所有匹配扩展名的文件
+pub fn collect_files_recursive(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                result.extend(collect_files_recursive(&path, extension));
+            } else if path.extension().and_then(|e| e.to_str()) == Some(extension) {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
+
+/// 读取 JSONL 文件的前 N 行
+pub fn read_head_lines(path: &Path, n: usize) -> Vec<String> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+    BufReader::new(file)
+        .lines()
+        .take(n)
+        .filter_map(|l| l.ok())
+        .collect()
+}
+
+/// 读取 JSONL 文件的尾部 N 行（对大文件使用 seek 优化）
+pub fn read_tail_lines(path: &Path, n: usize) -> Vec<String> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+    let metadata = match file.metadata() {
+        Ok(m) => m,
+        Err(_) => return Vec::new(),
+    };
+
+    let file_size = metadata.len();
+    if file_size < 16 * 1024 {
+        // 小文件直接全量读取
+        let all_lines: Vec<String> = BufReader::new(file)
+            .lines()
+            .filter_map(|l| l.ok())
+            .collect();
+        let start = all_lines.len().saturating_sub(n);
+        return all_lines[start..].to_vec();
+    }
+
+    // 大文件从末尾读取
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = file;
+    let seek_pos = file_size.saturating_sub(32 * 1024);
+    if file.seek(SeekFrom::Start(seek_pos)).is_err() {
+        return Vec::new();
+    }
+    let mut buf = String::new();
+    if file.read_to_string(&mut buf).is_err() {
+        return Vec::new();
+    }
+    let lines: Vec<String> = buf.lines().map(|s| s.to_string()).collect();
+    let start = lines.len().saturating_sub(n);
+    lines[start..].to_vec()
+}
+
+/// 逐行读取, 同时返回每行起始处的字节偏移 (行内容不含换行符)
+pub fn lines_with_offsets<R: BufRead>(
+    mut reader: R,
+) -> impl Iterator<Item = std::io::Result<(u64, String)>> {
+    let mut offset = 0u64;
+    std::iter::from_fn(move || {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(read) => {
+                let start = offset;
+                offset += read as u64;
+                let trimmed = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(trimmed);
+                Some(Ok((start, line)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// 在阻塞线程池中执行耗时的命令 (读取完整记录、重建索引等), 避免阻塞主线程
+pub async fn run_blocking<T: Send + 'static>(
+    op: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(op)
+        .await
+        .map_err(|e| format!("后台任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_title_takes_first_non_empty_line() {
+        assert_eq!(
+            prompt_title("\n\n  fix the login bug  \nand add tests").as_deref(),
+            Some("fix the login bug")
+        );
+        assert_eq!(prompt_title("   \n  "), None);
+    }
+
+    #[test]
+    fn prompt_title_truncates_long_lines() {
+        let title = prompt_title(&"字".repeat(TITLE_MAX_CHARS + 5)).unwrap();
+        assert_eq!(title, format!("{}...", "字".repeat(TITLE_MAX_CHARS)));
+    }
+
+    #[test]
+    fn prompt_title_skips_wrapped_and_injected_prompts() {
+        for text in [
+            "<command-name>/clear</command-name>",
+            "<environment_context>\n  <cwd>/x</cwd>\n</environment_context>",
+            "  <user-prompt-submit-hook>ok</user-prompt-submit-hook>",
+            "Caveat: The messages below were generated by the user while running local commands.",
+            "# AGENTS.md instructions for /x\n\n<INSTRUCTIONS>",
+            "[Request interrupted by user for tool use]",
+        ] {
+            assert_eq!(prompt_title(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn prompt_title_keeps_text_that_only_looks_like_markup() {
+        assert_eq!(prompt_title("< 5 items").as_deref(), Some("< 5 items"));
+        assert_eq!(
+            prompt_title("<> empty tag").as_deref(),
+            Some("<> empty tag")
+        );
+        assert_eq!(
+            prompt_title("a <tag> inside").as_deref(),
+            Some("a <tag> inside")
+        );
+    }
+}
@@ -0,0 +1,565 @@
+//! 对话的实时更新
+//!
+//! 通过 notify 监视各 provider 的对话目录, 一段时间内的变化合并后只刷新涉及的数据源,
+//! 与扫描共用元数据缓存; 新建、追加与删除的对话通过 session-watch:changed 事件推送.
+//! 打开的对话可单独跟随其数据源, 按行存储的记录从上次读取的位置解析新增的行,
+//! 新写入的消息通过 session-tail:messages 事件推送
+
+use super::cache::{refresh_cached, scan_cached};
+use super::page::{
+    read_appended, read_range, tail_start, to_paged, PagedMessage, DEFAULT_MAX_BLOCK_CHARS,
+};
+use super::project::attach_projects;
+use super::provider::{find_provider, SessionProvider};
+use super::scan::ScanControl;
+use super::util::Fingerprint;
+use super::{SessionMessage, SessionMeta};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 对话列表变化事件
+pub const WATCH_CHANGED_EVENT: &str = "session-watch:changed";
+/// 跟随的对话写入新消息的事件
+pub const TAIL_MESSAGES_EVENT: &str = "session-tail:messages";
+
+/// 合并文件变化的时间窗口, 对话写入时通常连续产生多个事件
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+const MIN_DEBOUNCE: Duration = Duration::from_millis(100);
+const TAIL_INTERVAL: Duration = Duration::from_secs(1);
+/// 等待期间检查停止标记的间隔
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+static NEXT_WATCH_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 进行中的监视与跟随及其停止标记, 两者共用 id 序列
+static ACTIVE_WATCHES: Mutex<Option<HashMap<u64, Arc<AtomicBool>>>> = Mutex::new(None);
+
+/// 已删除的对话
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedSession {
+    pub provider_id: String,
+    pub session_id: String,
+    pub source_path: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchChangedEvent {
+    watch_id: u64,
+    created: Vec<SessionMeta>,
+    /// 数据源有新内容的对话及其最新元数据
+    appended: Vec<SessionMeta>,
+    deleted: Vec<RemovedSession>,
+    /// 监视或刷新失败的原因; 监视无法继续时以此结束
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TailMessagesEvent {
+    tail_id: u64,
+    /// 对话当前的消息总数
+    total: usize,
+    /// 已推送的消息被改写 (例如编辑消息后主线切换到新分支), 前端需重新加载;
+    /// 此时 messages 为空
+    reset: bool,
+    messages: Vec<PagedMessage>,
+}
+
+/// 各数据源上次刷新时的指纹与元数据 (无法解析时为 None), 键为数据源路径
+type KnownSources = HashMap<String, (Fingerprint, Option<SessionMeta>)>;
+
+/// 一个时间窗口内各 provider 变化的路径
+struct Changes {
+    paths: Vec<Vec<PathBuf>>,
+    /// 无法确定变化位置 (例如事件丢失) 时刷新该 provider 的全部数据源
+    full: Vec<bool>,
+}
+
+impl Changes {
+    fn new(count: usize) -> Self {
+        Changes {
+            paths: vec![Vec::new(); count],
+            full: vec![false; count],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.paths.iter().all(Vec::is_empty) && !self.full.contains(&true)
+    }
+}
+
+/// 跟随对话时已推送的内容
+enum TailState {
+    /// 按行存储的记录: 已推送的消息数与已解析到的字节偏移
+    Lines { count: usize, offset: u64 },
+    /// 整体读取的记录: 已推送消息的标识, 变化时重新加载并比较
+    Loaded { keys: Vec<MessageKey> },
+}
+
+fn with_active_watches<T>(op: impl FnOnce(&mut HashMap<u64, Arc<AtomicBool>>) -> T) -> T {
+    let mut guard = ACTIVE_WATCHES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    op(guard.get_or_insert_with(HashMap::new))
+}
+
+fn register() -> (u64, Arc<AtomicBool>) {
+    let id = NEXT_WATCH_ID.fetch_add(1, Ordering::Relaxed);
+    let stop = Arc::new(AtomicBool::new(false));
+    with_active_watches(|watches| watches.insert(id, stop.clone()));
+    (id, stop)
+}
+
+/// 等待一个间隔, 期间被停止时返回 false
+fn wait(stop: &AtomicBool, interval: Duration) -> bool {
+    let mut waited = Duration::ZERO;
+    while waited < interval {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(STOP_CHECK_INTERVAL);
+        waited += STOP_CHECK_INTERVAL;
+    }
+    !stop.load(Ordering::Relaxed)
+}
+
+/// 扫描并记录各 provider 数据源的指纹, 被停止时返回 None
+fn snapshot(
+    providers: &[&dyn SessionProvider],
+    home: &Path,
+    stop: &AtomicBool,
+) -> Result<Option<Vec<KnownSources>>, String> {
+    let control = ScanControl {
+        cancel: Some(stop),
+        on_update: None,
+    };
+    let Some(sessions) = scan_cached(providers, home, false, &control)? else {
+        return Ok(None);
+    };
+    let mut known: Vec<KnownSources> = providers.iter().map(|_| HashMap::new()).collect();
+    for meta in sessions {
+        let Some(index) = providers.iter().position(|p| p.id() == meta.provider_id) else {
+            continue;
+        };
+        let fingerprint = providers[index].fingerprint(Path::new(&meta.source_path));
+        known[index].insert(meta.source_path.clone(), (fingerprint, Some(meta)));
+    }
+    Ok(Some(known))
+}
+
+/// 收集一个时间窗口内的文件变化: 等待第一个事件, 之后继续收集 debounce 时长;
+/// 被停止时返回 None
+fn collect_changes(
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+    roots: &[(PathBuf, usize)],
+    count: usize,
+    debounce: Duration,
+    stop: &AtomicBool,
+) -> Result<Option<Changes>, String> {
+    let mut changes = Changes::new(count);
+    let mut deadline: Option<Instant> = None;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) && !changes.is_empty() {
+            return Ok(Some(changes));
+        }
+        let event = match rx.recv_timeout(STOP_CHECK_INTERVAL) {
+            Ok(event) => event,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err("目录监视已中断".to_string()),
+        };
+        match event {
+            Ok(event) if event.kind.is_access() => continue,
+            Ok(event) => {
+                for path in event.paths {
+                    // SQLite 读取时也会更新 -shm 文件, 忽略以免读取数据库触发刷新
+                    if path.to_string_lossy().ends_with("-shm") {
+                        continue;
+                    }
+                    for (root, index) in roots {
+                        if path.starts_with(root) {
+                            changes.paths[*index].push(path.clone());
+                        }
+                    }
+                }
+            }
+            Err(_) => changes.full.iter_mut().for_each(|full| *full = true),
+        }
+        deadline.get_or_insert_with(|| Instant::now() + debounce);
+    }
+}
+
+/// 数据源与变化的路径相关: 路径相同、变化位于数据源目录中, 或数据源位于变化的目录中
+fn is_affected(source: &Path, path: &Path) -> bool {
+    path.starts_with(source) || source.starts_with(path)
+}
+
+/// 刷新 provider 中受变化影响的数据源, 将差异写入 event
+fn refresh_provider(
+    provider: &dyn SessionProvider,
+    home: &Path,
+    changed: &[PathBuf],
+    full: bool,
+    known: &mut KnownSources,
+    event: &mut WatchChangedEvent,
+) -> Result<(), String> {
+    let sources = provider.sources(home);
+    let listed: HashSet<String> = sources
+        .iter()
+        .map(|source| source.to_string_lossy().to_string())
+        .collect();
+
+    // 变化不属于任何数据源时 (例如对话的附属文件) 无法确定对应的对话, 检查全部数据源的指纹
+    let full = full
+        || changed
+            .iter()
+            .any(|path| !sources.iter().any(|source| is_affected(source, path)));
+    let candidates: Vec<PathBuf> = sources
+        .into_iter()
+        .filter(|source| full || changed.iter().any(|path| is_affected(source, path)))
+        .filter(|source| {
+            known
+                .get(source.to_string_lossy().as_ref())
+                .is_none_or(|(fingerprint, _)| *fingerprint != provider.fingerprint(source))
+        })
+        .collect();
+    let removed: Vec<String> = known
+        .keys()
+        .filter(|key| !listed.contains(*key))
+        .cloned()
+        .collect();
+
+    for key in &removed {
+        if let Some((_, Some(meta))) = known.remove(key) {
+            event.deleted.push(RemovedSession {
+                provider_id: meta.provider_id,
+                session_id: meta.session_id,
+                source_path: meta.source_path,
+            });
+        }
+    }
+    for (key, fingerprint, meta) in refresh_cached(provider, home, &candidates, &removed)? {
+        let previous = known.insert(key, (fingerprint, meta.clone()));
+        match (previous.and_then(|(_, meta)| meta), meta) {
+            (None, Some(meta)) => event.created.push(meta),
+            (Some(_), Some(meta)) => event.appended.push(meta),
+            (Some(meta), None) => event.deleted.push(RemovedSession {
+                provider_id: meta.provider_id,
+                session_id: meta.session_id,
+                source_path: meta.source_path,
+            }),
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// 监视直到被停止, 监视无法继续时返回错误
+fn watch_sources(
+    app: &AppHandle,
+    watch_id: u64,
+    providers: &[&'static dyn SessionProvider],
+    home: &Path,
+    debounce: Duration,
+    stop: &AtomicBool,
+) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("监视对话目录失败: {}", e))?;
+    // 开始监视时不存在的目录 (未安装的工具) 不会被监视
+    let mut roots = Vec::new();
+    for (index, provider) in providers.iter().enumerate() {
+        for root in provider.watch_roots(home) {
+            if root.is_dir() {
+                watcher
+                    .watch(&root, RecursiveMode::Recursive)
+                    .map_err(|e| format!("监视 {} 失败: {}", root.display(), e))?;
+                roots.push((root, index));
+            }
+        }
+    }
+
+    // 先开始监视再扫描基准, 扫描期间的变化不会遗漏
+    let Some(mut known) = snapshot(providers, home, stop)? else {
+        return Ok(());
+    };
+    while let Some(changes) = collect_changes(&rx, &roots, providers.len(), debounce, stop)? {
+        let mut event = WatchChangedEvent {
+            watch_id,
+            created: Vec::new(),
+            appended: Vec::new(),
+            deleted: Vec::new(),
+            error: None,
+        };
+        for (index, provider) in providers.iter().enumerate() {
+            if changes.paths[index].is_empty() && !changes.full[index] {
+                continue;
+            }
+            // 刷新失败 (例如缓存无法保存) 不中断监视, 随事件报告
+            if let Err(error) = refresh_provider(
+                *provider,
+                home,
+                &changes.paths[index],
+                changes.full[index],
+                &mut known[index],
+                &mut event,
+            ) {
+                event.error = Some(error);
+            }
+        }
+        let changed = !event.created.is_empty()
+            || !event.appended.is_empty()
+            || !event.deleted.is_empty()
+            || event.error.is_some();
+        if changed {
+            if let Err(error) = attach_projects(&mut event.created)
+                .and_then(|_| attach_projects(&mut event.appended))
+            {
+                event.error.get_or_insert(error);
+            }
+            let _ = app.emit(WATCH_CHANGED_EVENT, event);
+        }
+    }
+    Ok(())
+}
+
+/// 消息的标识 (id, 时间, 文本长度), 用于判断已推送的消息是否被改写
+type MessageKey = (Option<String>, Option<u64>, usize);
+
+fn message_key(message: &SessionMessage) -> MessageKey {
+    (message.id.clone(), message.ts, message.content.len())
+}
+
+/// 读取跟随起点之后的消息, 返回跟随状态与需要推送的事件
+fn start_tail(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    tail_id: u64,
+    from_index: usize,
+    max_block_chars: usize,
+) -> Result<(TailState, Option<TailMessagesEvent>), String> {
+    let (total, offset) = tail_start(provider, path)?;
+    let event = if total < from_index {
+        Some(TailMessagesEvent {
+            tail_id,
+            total,
+            reset: true,
+            messages: Vec::new(),
+        })
+    } else if total > from_index {
+        let messages = read_range(provider, path, from_index..total)?;
+        Some(TailMessagesEvent {
+            tail_id,
+            total,
+            reset: false,
+            messages: to_paged(messages, from_index, max_block_chars),
+        })
+    } else {
+        None
+    };
+    Ok((tail_state(provider, path, total, offset)?, event))
+}
+
+fn tail_state(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    total: usize,
+    offset: Option<u64>,
+) -> Result<TailState, String> {
+    Ok(match offset {
+        Some(offset) => TailState::Lines {
+            count: total,
+            offset,
+        },
+        None => TailState::Loaded {
+            keys: read_range(provider, path, 0..total)?
+                .iter()
+                .map(message_key)
+                .collect(),
+        },
+    })
+}
+
+/// 数据源变化后读取新消息, 更新跟随状态并返回需要推送的事件
+fn continue_tail(
+    provider: &dyn SessionProvider,
+    path: &Path,
+    tail_id: u64,
+    state: &mut TailState,
+    max_block_chars: usize,
+) -> Result<Option<TailMessagesEvent>, String> {
+    match state {
+        TailState::Lines { count, offset } => {
+            let Some((messages, end)) = read_appended(provider, path, *offset)? else {
+                // 已读取的部分被改写, 以新的索引为准重新开始
+                let (total, offset) = tail_start(provider, path)?;
+                *state = tail_state(provider, path, total, offset)?;
+                return Ok(Some(TailMessagesEvent {
+                    tail_id,
+                    total,
+                    reset: true,
+                    messages: Vec::new(),
+                }));
+            };
+            *offset = end;
+            if messages.is_empty() {
+                return Ok(None);
+            }
+            let start = *count;
+            *count += messages.len();
+            Ok(Some(TailMessagesEvent {
+                tail_id,
+                total: *count,
+                reset: false,
+                messages: to_paged(messages, start, max_block_chars),
+            }))
+        }
+        TailState::Loaded { keys } => {
+            let messages = provider.load_messages(path)?;
+            let rewritten = messages.len() < keys.len()
+                || keys
+                    .iter()
+                    .zip(&messages)
+                    .any(|(key, message)| *key != message_key(message));
+            let start = keys.len();
+            let total = messages.len();
+            *keys = messages.iter().map(message_key).collect();
+            Ok(if rewritten {
+                Some(TailMessagesEvent {
+                    tail_id,
+                    total,
+                    reset: true,
+                    messages: Vec::new(),
+                })
+            } else if total > start {
+                Some(TailMessagesEvent {
+                    tail_id,
+                    total,
+                    reset: false,
+                    messages: to_paged(messages[start..].to_vec(), start, max_block_chars),
+                })
+            } else {
+                None
+            })
+        }
+    }
+}
+
+// Tauri 命令
+
+/// 在后台监视 provider 的对话目录, 立即返回监视 id
+/// 以开始监视时的对话列表为基准, 之后的变化通过 session-watch:changed 事件推送;
+/// interval_ms 为合并变化的时间窗口
+#[tauri::command]
+pub fn start_session_watch(
+    app: AppHandle,
+    providers: Vec<String>,
+    interval_ms: Option<u64>,
+) -> Result<u64, String> {
+    let home = dirs::home_dir().ok_or("无法确定用户目录")?;
+    let providers = providers
+        .iter()
+        .map(|id| find_provider(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let debounce = interval_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_DEBOUNCE)
+        .max(MIN_DEBOUNCE);
+
+    let (watch_id, stop) = register();
+    std::thread::spawn(move || {
+        if let Err(error) = watch_sources(&app, watch_id, &providers, &home, debounce, &stop) {
+            let _ = app.emit(
+                WATCH_CHANGED_EVENT,
+                WatchChangedEvent {
+                    watch_id,
+                    created: Vec::new(),
+                    appended: Vec::new(),
+                    deleted: Vec::new(),
+                    error: Some(error),
+                },
+            );
+        }
+        with_active_watches(|watches| watches.remove(&watch_id));
+    });
+
+    Ok(watch_id)
+}
+
+/// 在后台跟随对话的数据源, 立即返回跟随 id
+/// from_index 为前端已加载的消息数, 此后写入的消息通过 session-tail:messages 事件推送;
+/// 按行存储的记录只解析新增的行, 新消息不含依赖其他行的 model 与 usage,
+/// 分支与子代理结构以重新加载时为准; 数据源被删除时停止跟随
+#[tauri::command]
+pub fn start_session_tail(
+    app: AppHandle,
+    provider_id: String,
+    source_path: String,
+    from_index: usize,
+    max_block_chars: Option<usize>,
+) -> Result<u64, String> {
+    let provider = find_provider(&provider_id)?;
+    if !provider.source_exists(Path::new(&source_path)) {
+        return Err(format!("文件不存在: {}", source_path));
+    }
+    let max_block_chars = max_block_chars.unwrap_or(DEFAULT_MAX_BLOCK_CHARS);
+
+    let (tail_id, stop) = register();
+    std::thread::spawn(move || {
+        let path = Path::new(&source_path);
+        let mut fingerprint = None;
+        let mut state: Option<TailState> = None;
+        loop {
+            if !provider.source_exists(path) {
+                break;
+            }
+            let current = provider.fingerprint(path);
+            if fingerprint != Some(current) {
+                let result = match state.as_mut() {
+                    Some(state) => continue_tail(provider, path, tail_id, state, max_block_chars),
+                    None => start_tail(provider, path, tail_id, from_index, max_block_chars).map(
+                        |(started, event)| {
+                            state = Some(started);
+                            event
+                        },
+                    ),
+                };
+                // 写入到一半的记录可能解析失败, 下次变化时重试
+                fingerprint = Some(current);
+                if let Ok(Some(event)) = result {
+                    let _ = app.emit(TAIL_MESSAGES_EVENT, event);
+                }
+            }
+            if !wait(&stop, TAIL_INTERVAL) {
+                break;
+            }
+        }
+        with_active_watches(|watches| watches.remove(&tail_id));
+    });
+
+    Ok(tail_id)
+}
+
+/// 停止监视或跟随, 返回其是否仍在进行
+#[tauri::command]
+pub fn stop_session_watch(watch_id: u64) -> Result<bool, String> {
+    Ok(with_active_watches(|watches| {
+        match watches.get(&watch_id) {
+            Some(stop) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }))
+}
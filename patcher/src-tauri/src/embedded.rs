@@ -5,6 +5,7 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 #[derive(Debug)]
 pub enum EmbeddedError {
@@ -12,6 +13,9 @@ pub enum EmbeddedError {
     ReadPatchFileFailed { path: PathBuf, detail: String },
 }
 
+/// 提权进程使用的补丁目录 (开发模式下由父进程通过操作计划传入)
+static PATCHES_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 // 编译时生成的嵌入文件列表
 include!(concat!(env!("OUT_DIR"), "/embedded_patches.rs"));
 
@@ -21,9 +25,28 @@ pub fn get_all_files() -> Vec<(&'static str, &'static str)> {
     EMBEDDED_FILES.to_vec()
 }
 
+/// 指定补丁目录，优先于按当前目录搜索
+pub fn set_patches_dir_override(dir: PathBuf) {
+    let _ = PATCHES_DIR_OVERRIDE.set(dir);
+}
+
+/// 开发模式下使用的补丁目录，发布模式返回 None
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn dev_patches_dir() -> Option<PathBuf> {
+    if cfg!(debug_assertions) {
+        find_patches_dir()
+    } else {
+        None
+    }
+}
+
 /// 查找 patches 目录
 /// 从当前目录向上搜索，最多查找 6 层
 fn find_patches_dir() -> Option<PathBuf> {
+    if let Some(dir) = PATCHES_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+
     let mut dir = std::env::current_dir().ok()?;
 
     for _ in 0..6 {
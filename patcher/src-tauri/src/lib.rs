@@ -6,13 +6,40 @@ mod commands;
 mod embedded;
 
 use commands::{
+    archive_sessions, bulk_session_action, cancel_message_stream, cancel_session_scan,
     check_patch_status, delete_session, detect_antigravity_path, detect_antigravity_version,
-    get_config, install_patch, load_session_messages, normalize_antigravity_path,
-    read_manager_patch_config, read_patch_config, run_anti_clean, save_config, scan_sessions,
+    empty_trash, export_session, export_sessions, get_audit_log, get_config, install_patch,
+    launch_session_resume, list_archives, list_session_projects, list_session_providers,
+    list_trash, load_archived_session, load_message_block, load_session_messages,
+    load_session_page, load_session_tree, normalize_antigravity_path, probe_patch_permissions,
+    read_manager_patch_config, read_patch_config, redact_session, restore_archived_session,
+    restore_session, run_anti_clean, save_config, scan_session_secrets, scan_sessions,
+    search_sessions, session_resume_command, session_usage_report, start_message_stream,
+    start_session_scan, start_session_tail, start_session_watch, stop_session_watch,
     uninstall_patch, update_config,
 };
 use tauri::Manager;
 
+/// 是否以提权辅助模式 (`--privileged-apply`) 启动
+pub fn is_privileged_apply() -> bool {
+    commands::is_privileged_apply()
+}
+
+/// 提权辅助模式入口: 从 stdin 读取操作计划并执行, 返回进程退出码
+pub fn run_privileged_apply() -> i32 {
+    commands::run_privileged_apply()
+}
+
+/// 是否以命令行查看审计日志模式 (`--audit-log`) 启动
+pub fn is_audit_log_cli() -> bool {
+    commands::is_audit_log_cli()
+}
+
+/// 命令行查看审计日志入口, 返回进程退出码
+pub fn run_audit_log_cli() -> i32 {
+    commands::run_audit_log_cli()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -43,12 +70,42 @@ pub fn run() {
             check_patch_status,
             read_patch_config,
             read_manager_patch_config,
+            probe_patch_permissions,
             get_config,
             save_config,
+            get_audit_log,
             run_anti_clean,
             scan_sessions,
             load_session_messages,
-            delete_session
+            load_session_tree,
+            delete_session,
+            list_session_providers,
+            list_session_projects,
+            session_resume_command,
+            launch_session_resume,
+            search_sessions,
+            start_session_scan,
+            cancel_session_scan,
+            export_session,
+            export_sessions,
+            session_usage_report,
+            load_session_page,
+            start_message_stream,
+            cancel_message_stream,
+            load_message_block,
+            list_trash,
+            restore_session,
+            empty_trash,
+            bulk_session_action,
+            archive_sessions,
+            list_archives,
+            load_archived_session,
+            restore_archived_session,
+            scan_session_secrets,
+            redact_session,
+            start_session_watch,
+            start_session_tail,
+            stop_session_watch
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
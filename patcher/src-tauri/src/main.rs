@@ -7,5 +7,15 @@
 
 /// 程序入口点
 fn main() {
+    // 提权辅助模式: 不启动界面, 执行完操作计划后直接退出
+    if patcher_lib::is_privileged_apply() {
+        std::process::exit(patcher_lib::run_privileged_apply());
+    }
+
+    // 命令行查看审计日志
+    if patcher_lib::is_audit_log_cli() {
+        std::process::exit(patcher_lib::run_audit_log_cli());
+    }
+
     patcher_lib::run()
 }